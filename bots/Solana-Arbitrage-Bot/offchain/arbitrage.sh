@@ -7,5 +7,5 @@ do
     echo "------" >> log.txt
     echo $(date) >> log.txt
     echo "------" >> log.txt
-    ./target/release/main --cluster mainnet >> log.txt 2>&1
+    ./target/release/main --config arb.toml >> log.txt 2>&1
 done
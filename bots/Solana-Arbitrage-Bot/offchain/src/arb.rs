@@ -1,146 +1,441 @@
 use anchor_client::solana_client::rpc_client::RpcClient;
-use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
+use anchor_client::solana_client::rpc_config::{RpcSendTransactionConfig, RpcSimulateTransactionConfig};
 
 use anchor_client::solana_sdk::pubkey::Pubkey;
 
-use anchor_client::solana_sdk::signature::{Keypair, Signer};
+use anchor_client::solana_sdk::signature::{Keypair, Signature, Signer};
 use anchor_client::{Cluster, Program};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
+use solana_sdk::hash::Hash;
 use solana_sdk::instruction::Instruction;
-use solana_sdk::transaction::Transaction;
+use spl_token::native_mint;
 
 use std::borrow::Borrow;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use std::vec;
 
-use log::info;
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use rayon::prelude::*;
+use serde::Serialize;
 
 use tmp::accounts as tmp_accounts;
 use tmp::instruction as tmp_ix;
 
-use crate::pool::PoolOperations;
+use crate::compute::{writable_accounts, ComputeBudget, ComputeConfig, MAX_COMPUTE_UNITS};
+use crate::dedup::{RouteKey, SentArbs};
+use crate::error::{PoolError, PoolResult};
+use crate::jito::JitoClient;
+use crate::ledger::{Ledger, LedgerEntry, LedgerHop};
+use crate::lookup::{route_accounts, LookupTables};
+use crate::mints::MintRegistry;
+use crate::search::{
+    build_edges, find_candidate_cycles_from, find_dirty_cycles_from, update_edge_weights, value_rates, Cycle, Edge,
+};
+use crate::sizing::{optimal_size, SizedArb};
+use crate::svm::Simulator;
+use crate::utils::{unix_now, PoolGraph, PoolQuote};
+
+/// start mint idx -> owner's (scaled) balance of it
+pub type Inventory = HashMap<usize, u128>;
+
+/// an arb's ixs by step -- flattened, they're the tx which gets sent
+#[derive(Debug, Clone)]
+pub struct ArbSteps {
+    pub start: Vec<Instruction>,
+    pub hops: Vec<Vec<Instruction>>,
+    // quoted (scaled) output of each hop
+    pub quoted: Vec<u128>,
+    pub end: Vec<Instruction>,
+}
+
+impl ArbSteps {
+    pub fn instructions(&self) -> Vec<Instruction> {
+        let mut ixs = self.start.clone();
+        ixs.extend(self.hops.iter().flatten().cloned());
+        ixs.extend(self.end.iter().cloned());
+        ixs
+    }
+}
 
-use crate::utils::{derive_token_address, PoolGraph, PoolIndex, PoolQuote};
+/// a sized cycle clearing min_profit (before fees)
+pub struct FoundArb<'a> {
+    pub cycle: &'a Cycle,
+    pub sized: SizedArb,
+    // profit in (scaled) value mint units
+    pub value: f64,
+}
+
+/// one line of a replay's output
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ArbRecord {
+    pub mints: Vec<String>,
+    // "{name}:{address}"
+    pub pools: Vec<String>,
+    pub amount_in: u128,
+    pub amount_out: u128,
+    pub profit: i128,
+    pub value: f64,
+}
 
 pub struct Arbitrager {
     pub token_mints: Vec<Pubkey>,
-    pub graph_edges: Vec<HashSet<usize>>, // used for quick searching over the graph
+    // token programs + transfer fees of the mints (its epoch kept current)
+    pub registry: MintRegistry,
     pub graph: PoolGraph,
     pub cluster: Cluster,
+    pub max_hops: usize,
+    // profits + size bounds are in (scaled) units of the value mint
+    pub value_mint_idx: usize,
+    pub min_swap_value: u128,
+    pub max_swap_value: Option<u128>,
+    pub min_profit: u128,
+    // simulate instead of sending
+    pub dry_run: bool,
+    // route accounts are looked up through these (v0 txs)
+    pub lookup_tables: LookupTables,
+    // arbs go out as tipped jito bundles when set (plain rpc sends otherwise)
+    pub jito: Option<JitoClient>,
+    // compute limit + priority fee of each route
+    pub compute: ComputeConfig,
+    // arbs are run against an in-process bank + reported instead of sent
+    pub simulator: Option<Simulator>,
+    // every arb sent is recorded here
+    pub ledger: Ledger,
     // vv -- need to clone these explicitly -- vv
-    pub owner: Rc<Keypair>,
+    // (program isnt Send so only the search phase runs across threads)
+    pub owner: Arc<Keypair>,
     pub program: Program,
     pub connection: RpcClient,
 }
 
 impl Arbitrager {
-    pub fn brute_force_search(
+    pub fn build_edges(&self) -> Vec<Edge> {
+        let edges = build_edges(&self.graph, &self.registry, &self.token_mints);
+        info!("built {:?} pool edges", edges.len());
+        edges
+    }
+
+    /// candidate cycles through the start mints, best (most negative) first
+    pub fn find_cycles(&self, edges: &[Edge], start_mint_idxs: &[usize]) -> Vec<Cycle> {
+        let cycles =
+            find_candidate_cycles_from(edges, self.token_mints.len(), start_mint_idxs, self.max_hops);
+        info!("found {:?} candidate cycles", cycles.len());
+        cycles
+    }
+
+    /// re-weight the changed pools + search only the cycles through them
+    pub fn research_dirty_cycles(
         &self,
-        start_mint_idx: usize,
-        init_balance: u128,
-        curr_balance: u128,
-        path: Vec<usize>,
-        pool_path: Vec<PoolQuote>,
-        sent_arbs: &mut HashSet<String>,
-    ) {
-        let src_curr = path[path.len() - 1]; // last mint
-        let src_mint = self.token_mints[src_curr];
+        edges: &mut [Edge],
+        start_mint_idxs: &[usize],
+        dirty: &[PoolQuote],
+    ) -> Vec<Cycle> {
+        update_edge_weights(edges, &self.registry, &self.token_mints, dirty);
+        let cycles =
+            find_dirty_cycles_from(edges, self.token_mints.len(), start_mint_idxs, self.max_hops, dirty);
+        debug!("found {:?} affected cycles", cycles.len());
+        cycles
+    }
+
+    /// each mint's marginal rate into the value mint
+    pub fn value_rates(&self, edges: &[Edge]) -> Vec<Option<f64>> {
+        value_rates(edges, self.token_mints.len(), self.value_mint_idx, self.max_hops)
+    }
+
+    /// size each candidate cycle within its start mint's inventory + value
+    /// the profits in the value mint -- the ones clearing min_profit, best first
+    pub fn find_arbs<'a>(
+        &self,
+        cycles: &'a [Cycle],
+        inventory: &Inventory,
+        rates: &[Option<f64>],
+    ) -> Vec<FoundArb<'a>> {
+        // sizing is independent per cycle -- run it across the cores
+        // (only capture Sync fields, not self)
+        let (registry, token_mints) = (&self.registry, &self.token_mints);
+        let (min_swap_value, max_swap_value) = (self.min_swap_value, self.max_swap_value);
+        let min_profit = self.min_profit as f64;
+        let mut arbs: Vec<FoundArb> = cycles
+            .par_iter()
+            .filter_map(|cycle| {
+                let start = cycle.mints[0];
+                let rate = rates[start]?;
+                let balance = *inventory.get(&start)?;
+
+                // value bounds -> start mint units
+                let min_amount = (min_swap_value as f64 / rate) as u128;
+                let max_amount = match max_swap_value {
+                    Some(max) => balance.min((max as f64 / rate) as u128),
+                    None => balance,
+                };
+                if max_amount == 0 || max_amount < min_amount {
+                    return None;
+                }
+
+                // a pool which fails to quote is flagged + the cycle skipped
+                let sized = optimal_size(cycle, registry, token_mints, min_amount, max_amount).ok()?;
+                debug!("profit curve: {:?}", sized.curve);
+                let value = sized.profit as f64 * rate;
+                Some(FoundArb { cycle, sized, value })
+            })
+            .filter(|arb| arb.sized.profit > 0 && arb.value > min_profit)
+            .collect();
+        // most valuable first, across every start mint (ties by route, so a
+        // replayed search always lists them the same way)
+        arbs.sort_by(|a, b| {
+            b.value
+                .partial_cmp(&a.value)
+                .unwrap()
+                .then_with(|| self.route_key(a.cycle).cmp(&self.route_key(b.cycle)))
+        });
+        arbs
+    }
+
+    fn route_key(&self, cycle: &Cycle) -> (Vec<Pubkey>, Vec<Pubkey>) {
+        let mints = cycle.mints.iter().map(|idx| self.token_mints[*idx]).collect();
+        let pools = cycle.pools.iter().map(|p| p.snapshot().get_own_addr()).collect();
+        (mints, pools)
+    }
 
-        let out_edges = &self.graph_edges[src_curr];
+    /// the arb as a replay reports it
+    pub fn arb_record(&self, arb: &FoundArb) -> ArbRecord {
+        let (mints, pools) = self.route_key(arb.cycle);
+        ArbRecord {
+            mints: mints.iter().map(|mint| mint.to_string()).collect(),
+            pools: arb
+                .cycle
+                .pools
+                .iter()
+                .zip(pools)
+                .map(|(pool, addr)| format!("{}:{}", pool.snapshot().get_name(), addr))
+                .collect(),
+            amount_in: arb.sized.amount_in,
+            amount_out: arb.sized.amount_out,
+            profit: arb.sized.profit,
+            value: arb.value,
+        }
+    }
 
-        // path = 4 = A -> B -> C -> D
-        // path >= 5 == not valid bc max tx size is swaps
-        if path.len() == 4 {
+    /// find the arbs in the cycles and send the best ones (net of fees)
+    pub fn search_cycles(
+        &self,
+        cycles: &[Cycle],
+        inventory: &Inventory,
+        rates: &[Option<f64>],
+        sent_arbs: &mut SentArbs,
+    ) {
+        // the simulator runs the arbs at the time they were quoted
+        let quoted_at = unix_now();
+        let arbs = self.find_arbs(cycles, inventory, rates);
+        if arbs.is_empty() {
             return;
+        }
+        // sends are deduped by the slots since a route was last sent (the
+        // bank has no cluster to ask) -- without the slot they cant be, so
+        // nothing is sent
+        let slot = match &self.simulator {
+            Some(_) => 0,
+            None => match self.connection.get_slot() {
+                Ok(slot) => slot,
+                Err(err) => {
+                    warn!("failed to get the slot -- not sending: {}", err);
+                    return;
+                }
+            },
         };
+        let min_profit = self.min_profit as f64;
+
+        // tips are paid in sol -- price them through wsol's value rate
+        let sol_rate = self
+            .token_mints
+            .iter()
+            .position(|mint| *mint == native_mint::id())
+            .and_then(|idx| rates[idx]);
+
+        // dont spend more of an inventory than we hold
+        let mut remaining = inventory.clone();
+        for FoundArb { cycle, sized, value } in arbs {
+            // ... profitable arb!
+            let start = cycle.mints[0];
+            info!(
+                "found arbitrage from {}: {:?} -> {:?} (+{:?}, value {:.0})",
+                self.token_mints[start], sized.amount_in, sized.amount_out, sized.profit, value
+            );
+
+            let balance = remaining.get_mut(&start).unwrap();
+            if sized.amount_in > *balance {
+                info!("not enough inventory left...");
+                continue;
+            }
 
-        for dst_mint_idx in out_edges {
-            let pools = self
-                .graph
-                .0
-                .get(&PoolIndex(src_curr))
-                .unwrap()
-                .0
-                .get(&PoolIndex(*dst_mint_idx))
-                .unwrap();
+            // dont re-send an already sent arb -- bad for network
+            let route = RouteKey {
+                mints: cycle.mints.iter().map(|idx| self.token_mints[*idx]).collect(),
+                pools: cycle.pools.iter().map(|p| p.snapshot().get_own_addr()).collect(),
+            };
+            if !sent_arbs.should_send(&route, sized.amount_in, slot, unix_now()) {
+                info!("arb already sent...");
+                continue;
+            }
 
-            if path.contains(dst_mint_idx) && *dst_mint_idx != start_mint_idx {
+            let steps = match self.get_arbitrage_steps(sized.amount_in, &cycle.mints, &cycle.pools) {
+                Ok(steps) => steps,
+                Err(err) => {
+                    warn!("failed to build arb ixs: {}", err);
+                    continue;
+                }
+            };
+            if let Some(simulator) = &self.simulator {
+                let pool_keys: Vec<String> = cycle.pools.iter().map(|p| p.snapshot().get_name()).collect();
+                match simulator.simulate(&self.owner, &steps, &route.mints, &pool_keys, quoted_at) {
+                    Ok(report) => println!("{}", report),
+                    Err(err) => warn!("offline simulation failed: {:?}", err),
+                }
                 continue;
             }
+            let mut ixs = steps.instructions();
+            let budget = match self.compute_budget(&ixs, cycle) {
+                Ok(budget) => budget,
+                Err(err) => {
+                    info!("skipping arb: {}", err);
+                    continue;
+                }
+            };
+            let tip = self.jito.as_ref().map(|jito| jito.config.tip_lamports(value, sol_rate));
+
+            // fees + the tip are paid in sol -- the arb has to cover them
+            let cost_lamports = budget.fee_lamports() + tip.unwrap_or(0);
+            let cost_value = match sol_rate {
+                Some(rate) => cost_lamports as f64 * rate,
+                None => {
+                    info!("cant value the {} lamport fees -- skipping", cost_lamports);
+                    continue;
+                }
+            };
+            let net_value = value - cost_value;
+            if net_value <= min_profit {
+                info!(
+                    "unprofitable after fees: value {:.0} - fees {:.0} ({:?})",
+                    value, cost_value, budget
+                );
+                continue;
+            }
+            debug!("net value {:.0} after {:?}", net_value, budget);
+
+            budget.apply(&mut ixs);
+            if let (Some(jito), Some(tip)) = (&self.jito, tip) {
+                // after profit_or_revert -- the tip is only paid if the cycle is
+                ixs.push(jito.tip_ix(&self.owner.pubkey(), tip));
+            }
+            // only what went out is held back + spent
+            if let Some(signature) = self.send_ixs(ixs) {
+                sent_arbs.insert(&route, sized.amount_in, slot, unix_now());
+                *balance -= sized.amount_in;
+                let entry = self.ledger_entry(cycle, &sized, &steps, &signature, budget.fee_lamports(), tip.unwrap_or(0));
+                if let Err(err) = self.ledger.record(&entry) {
+                    warn!("failed to record arb: {:?}", err);
+                }
+            }
+        }
+    }
 
-            let dst_mint_idx = *dst_mint_idx;
-            let dst_mint = self.token_mints[dst_mint_idx];
-
-            for pool in pools {
-                let new_balance =
-                    pool.0
-                        .get_quote_with_amounts_scaled(curr_balance, &src_mint, &dst_mint);
-
-                let mut new_path = path.clone();
-                new_path.push(dst_mint_idx);
-
-                let mut new_pool_path = pool_path.clone();
-                new_pool_path.push(pool.clone()); // clone the pointer
-
-                if dst_mint_idx == start_mint_idx {
-                    // info!("{:?} -> {:?} (-{:?})", init_balance, new_balance, init_balance - new_balance);
-
-                    // if new_balance > init_balance - 1086310399 {
-                    if new_balance > init_balance {
-                        // ... profitable arb!
-                        info!("found arbitrage: {:?} -> {:?}", init_balance, new_balance);
-
-                        // check if arb was sent with a larger size
-                        // key = {mint_path}{pool_names}
-                        let mint_keys: Vec<String> =
-                            new_path.clone().iter_mut().map(|i| i.to_string()).collect();
-                        let pool_keys: Vec<String> =
-                            new_pool_path.iter().map(|p| p.0.get_name()).collect();
-                        let arb_key = format!("{}{}", mint_keys.join(""), pool_keys.join(""));
-                        if sent_arbs.contains(&arb_key) {
-                            info!("arb already sent...");
-                            continue; // dont re-send an already sent arb -- bad for network
-                        } else {
-                            sent_arbs.insert(arb_key);
-                        }
-
-                        let ixs = self.get_arbitrage_instructions(
-                            init_balance,
-                            &new_path,
-                            &new_pool_path,
-                        );
-                        self.send_ixs(ixs);
+    /// the route's compute limit (simulated or from the venue table) and a
+    /// unit price from the recent fees on the accounts it writes
+    fn compute_budget(&self, ixs: &[Instruction], cycle: &Cycle) -> Result<ComputeBudget> {
+        let units = if self.compute.simulate {
+            self.simulated_units(ixs)?
+        } else {
+            let venues: Vec<String> = cycle.pools.iter().map(|p| p.snapshot().get_name()).collect();
+            self.compute.table_units(&venues)
+        };
+        let writable = writable_accounts(ixs, &self.owner.pubkey());
+        let unit_price = self
+            .compute
+            .recent_unit_price(&self.connection, &writable)
+            .unwrap_or_else(|err| {
+                warn!("failed to get recent priority fees: {}", err);
+                self.compute.min_priority_fee
+            });
+        Ok(ComputeBudget { units, unit_price })
+    }
+
+    // a route which fails in simulation (eg, profit_or_revert) is an error
+    fn simulated_units(&self, ixs: &[Instruction]) -> Result<u32> {
+        let mut ixs = ixs.to_vec();
+        ComputeBudget { units: MAX_COMPUTE_UNITS, unit_price: 0 }.apply(&mut ixs);
+        let tx = self.lookup_tables.compile(&self.owner, &ixs, Hash::default())?;
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            ..RpcSimulateTransactionConfig::default()
+        };
+        let res = self.connection.simulate_transaction_with_config(&tx, config)?.value;
+        if let Some(err) = res.err {
+            return Err(anyhow!("route fails in simulation: {}", err));
+        }
+        let used = res
+            .units_consumed
+            .ok_or_else(|| anyhow!("simulation didnt report compute used"))?;
+        Ok(self.compute.with_margin(used))
+    }
+
+    /// every account a swap through the graph can touch -- each pool is
+    /// built in both directions with a full token in
+    pub fn route_addresses(&self) -> Vec<Pubkey> {
+        let owner = self.owner.pubkey();
+        let (swap_state_pda, _) =
+            Pubkey::find_program_address(&[b"swap_state"], &self.program.id());
+        let mut addresses = vec![self.program.id(), swap_state_pda];
+        for (src, pool_edge) in &self.graph.0 {
+            for (dst, pools) in &pool_edge.0 {
+                let [mint_in, mint_out] = [self.token_mints[src.0], self.token_mints[dst.0]];
+                for pool in pools {
+                    let snapshot = pool.snapshot();
+                    let amount_in = 10_u64.saturating_pow(snapshot.mint_2_scale(&mint_in) as u32);
+                    // pools which cant build a swap right now are covered once a route uses them
+                    if let Ok(ixs) = snapshot.swap_ix(&self.program, &owner, &mint_in, &mint_out, amount_in, 0) {
+                        addresses.extend(route_accounts(&ixs, &owner));
                     }
-                } else if !path.contains(&dst_mint_idx) {
-                    // ... search deeper
-                    self.brute_force_search(
-                        start_mint_idx,
-                        init_balance,
-                        new_balance,   // !
-                        new_path,      // !
-                        new_pool_path, // !
-                        sent_arbs,
-                    );
                 }
             }
         }
+        addresses
+    }
+
+    /// add the accounts recent routes were missing to the lookup tables
+    pub fn extend_lookup_tables(&mut self) {
+        match self.lookup_tables.flush_pending(&self.connection, &self.owner) {
+            Ok(0) => {}
+            Ok(n) => info!("extended lookup tables with {} route accounts", n),
+            Err(err) => warn!("failed to extend lookup tables: {:?}", err),
+        }
+    }
+
+    pub fn get_arbitrage_instructions(
+        &self,
+        swap_start_amount: u128,
+        mint_idxs: &Vec<usize>,
+        pools: &Vec<PoolQuote>,
+    ) -> PoolResult<Vec<Instruction>> {
+        Ok(self.get_arbitrage_steps(swap_start_amount, mint_idxs, pools)?.instructions())
     }
 
-    fn get_arbitrage_instructions(
+    /// the arb's ixs grouped by step (start_swap, each hop, profit_or_revert)
+    /// along with the quoted output of each hop
+    pub fn get_arbitrage_steps(
         &self,
         swap_start_amount: u128,
         mint_idxs: &Vec<usize>,
         pools: &Vec<PoolQuote>,
-    ) -> Vec<Instruction> {
-        // gather swap ixs
-        let mut ixs = vec![];
+    ) -> PoolResult<ArbSteps> {
         let (swap_state_pda, _) =
             Pubkey::find_program_address(&[b"swap_state"], &self.program.id());
 
         let src_mint = self.token_mints[mint_idxs[0]];
-        let src_ata = derive_token_address(&self.owner.pubkey(), &src_mint);
+        let src_ata = self.registry.token_address(&self.owner.pubkey(), &src_mint);
 
         // initialize swap ix
         let ix = self
@@ -153,19 +448,47 @@ impl Arbitrager {
             .args(tmp_ix::StartSwap {
                 swap_input: swap_start_amount as u64,
             })
-            .instructions()
-            .unwrap();
-        ixs.push(ix);
+            .instructions()?;
+        let mut steps = ArbSteps { start: ix, hops: vec![], quoted: vec![], end: vec![] };
+
+        // direct swaps are given the quoted input of their hop -- they dont
+        // write swap_state, so a tmp program swap cant follow one. each hop
+        // before a direct swap has to pay out at least what it pulls (else
+        // the difference comes out of the owner's balance), so a direct swap
+        // cant follow a tmp program swap either (those have no min out)
+        let hops = mint_idxs.len() - 1;
+        let direct = !pools[0].snapshot().uses_swap_state();
+        if let Some(pool) = pools[..hops].iter().find(|pool| pool.snapshot().uses_swap_state() == direct) {
+            return Err(PoolError::UnsupportedRoute(format!(
+                "{} mixed with {} swaps",
+                pool.snapshot().get_name(),
+                if direct { "direct" } else { "tmp program" }
+            )));
+        }
 
-        for i in 0..mint_idxs.len() - 1 {
+        let mut hop_amount = swap_start_amount;
+        for i in 0..hops {
             let [mint_idx0, mint_idx1] = [mint_idxs[i], mint_idxs[i + 1]];
             let [mint0, mint1] = [self.token_mints[mint_idx0], self.token_mints[mint_idx1]];
             let pool = &pools[i];
-
-            let swap_ix = pool
-                .0
-                .swap_ix(&self.program, &self.owner.pubkey(), &mint0, &mint1);
-            ixs.push(swap_ix);
+            let snapshot = pool.snapshot();
+
+            // the last hop pays back into the start mint (profit_or_revert
+            // checks that)
+            let (swap_ix, out) = pool
+                .quote(&self.registry, hop_amount, &mint0, &mint1)
+                .and_then(|out| {
+                    let min_out = if i + 1 < hops { out as u64 } else { 0 };
+                    let ix = snapshot.swap_ix(&self.program, &self.owner.pubkey(), &mint0, &mint1, hop_amount as u64, min_out)?;
+                    Ok((ix, out))
+                })
+                .map_err(|err| {
+                    pool.flag(&err);
+                    err
+                })?;
+            hop_amount = out;
+            steps.hops.push(swap_ix);
+            steps.quoted.push(hop_amount);
         }
 
         // PROFIT OR REVERT instruction
@@ -177,38 +500,106 @@ impl Arbitrager {
                 swap_state: swap_state_pda,
             })
             .args(tmp_ix::ProfitOrRevert {})
-            .instructions()
-            .unwrap();
-        ixs.push(ix);
+            .instructions()?;
+        steps.end = ix;
+        Ok(steps)
+    }
 
-        // flatten to Vec<Instructions>
-        ixs.concat()
+    /// what the arb was sent expecting (reconciled once it lands)
+    fn ledger_entry(
+        &self,
+        cycle: &Cycle,
+        sized: &SizedArb,
+        steps: &ArbSteps,
+        signature: &Signature,
+        fee_lamports: u64,
+        tip_lamports: u64,
+    ) -> LedgerEntry {
+        let owner = self.owner.pubkey();
+        let mints: Vec<Pubkey> = cycle.mints.iter().map(|idx| self.token_mints[*idx]).collect();
+        let hops = cycle
+            .pools
+            .iter()
+            .enumerate()
+            .map(|(i, pool)| {
+                let snapshot = pool.snapshot();
+                LedgerHop {
+                    venue: snapshot.get_name(),
+                    pool: snapshot.get_own_addr().to_string(),
+                    mint_in: mints[i].to_string(),
+                    mint_out: mints[i + 1].to_string(),
+                    destination: self.registry.token_address(&owner, &mints[i + 1]).to_string(),
+                    quoted_out: steps.quoted[i] as u64,
+                }
+            })
+            .collect();
+        LedgerEntry {
+            signature: signature.to_string(),
+            sent_at: unix_now(),
+            owner: owner.to_string(),
+            mints: mints.iter().map(|mint| mint.to_string()).collect(),
+            hops,
+            amount_in: sized.amount_in as u64,
+            expected_out: sized.amount_out as u64,
+            fee_lamports,
+            tip_lamports,
+            reconciled: None,
+        }
     }
 
-    fn send_ixs(&self, ixs: Vec<Instruction>) {
+    /// the tx's signature, if it was sent
+    fn send_ixs(&self, ixs: Vec<Instruction>) -> Option<Signature> {
         let owner: &Keypair = self.owner.borrow();
-        let tx = Transaction::new_signed_with_payer(
-            &ixs,
-            Some(&owner.pubkey()),
-            &[owner],
-            self.connection.get_latest_blockhash().unwrap(),
-        );
-
-        if self.cluster == Cluster::Localnet {
-            let res = self.connection.simulate_transaction(&tx).unwrap();
-            println!("{:#?}", res);
-        } else if self.cluster == Cluster::Mainnet {
-            let signature = self
-                .connection
-                .send_transaction_with_config(
-                    &tx,
-                    RpcSendTransactionConfig {
-                        skip_preflight: true,
-                        ..RpcSendTransactionConfig::default()
-                    },
-                )
-                .unwrap();
-            println!("signature: {:?}", signature);
+        // accounts outside the tables cost 32 bytes each -- queue them for the next extend
+        self.lookup_tables.note_missing(&ixs, &owner.pubkey());
+        let blockhash = match self.connection.get_latest_blockhash() {
+            Ok(blockhash) => blockhash,
+            Err(err) => {
+                warn!("failed to get a blockhash: {}", err);
+                return None;
+            }
+        };
+        let tx = match self.lookup_tables.compile(owner, &ixs, blockhash) {
+            Ok(tx) => tx,
+            Err(err) => {
+                warn!("route doesnt fit in a tx: {}", err);
+                return None;
+            }
+        };
+
+        if self.dry_run {
+            match self.connection.simulate_transaction(&tx) {
+                Ok(res) => println!("{:#?}", res),
+                Err(err) => warn!("failed to simulate tx: {}", err),
+            }
+            None
+        } else if let Some(jito) = &self.jito {
+            let signature = tx.signatures[0];
+            match jito.send_bundle(&[tx]) {
+                Ok(bundle_id) => {
+                    println!("bundle: {} (signature: {:?})", bundle_id, signature);
+                    Some(signature)
+                }
+                Err(err) => {
+                    warn!("failed to send bundle: {}", err);
+                    None
+                }
+            }
+        } else {
+            let config = RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..RpcSendTransactionConfig::default()
+            };
+            match self.connection.send_transaction_with_config(&tx, config) {
+                Ok(signature) => {
+                    println!("signature: {:?}", signature);
+                    Some(signature)
+                }
+                Err(err) => {
+                    warn!("failed to send tx: {}", err);
+                    None
+                }
+            }
         }
     }
 }
@@ -0,0 +1,187 @@
+//! Compute budget + priority fees for the arb txs
+//!
+//! each route gets a compute unit limit -- summed from a per-venue table, or
+//! measured by simulating the route -- and a price per unit taken from the
+//! recent prioritization fees paid to write its pool accounts. the lamports
+//! this costs (plus the signature fee) are valued through wsol and taken off
+//! the arb's profit before it is sent.
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_request::RpcRequest;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use solana_sdk::compute_budget::{self, ComputeBudgetInstruction};
+use solana_sdk::instruction::Instruction;
+
+// the most a tx can ask for
+pub const MAX_COMPUTE_UNITS: u32 = 1_400_000;
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+// getRecentPrioritizationFees takes at most this many accounts
+const MAX_FEE_ACCOUNTS: usize = 128;
+
+// start_swap + profit_or_revert + the budget / tip ixs
+const ROUTE_OVERHEAD_UNITS: u32 = 30_000;
+// venues missing from the table
+const DEFAULT_SWAP_UNITS: u32 = 150_000;
+
+/// rough compute used by one swap on each venue (by `get_name`)
+pub fn venue_compute_units(name: &str) -> u32 {
+    match name {
+        "Orca" | "Saber" | "Mercurial" | "AldrinV1" | "AldrinV2" => 60_000,
+        "RaydiumAmmV4" | "RaydiumCpmm" => 70_000,
+        "MeteoraDynamicAmm" => 110_000,
+        "MeteoraDammV2" | "PumpSwap" => 80_000,
+        "PumpFun" => 90_000,
+        "Serum" | "OpenBookV2" | "Phoenix" => 120_000,
+        // tick / bin crossing -- grows with the price move
+        "RaydiumClmm" | "OrcaWhirlpool" | "MeteoraDlmm" => 180_000,
+        _ => DEFAULT_SWAP_UNITS,
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct ComputeConfig {
+    /// measure each route's compute by simulating it (else use the venue table)
+    pub simulate: bool,
+    /// headroom on top of the estimate
+    pub unit_margin: f64,
+    /// percentile of the recent fees on the route's accounts to pay
+    pub priority_fee_percentile: u8,
+    // micro-lamports per compute unit
+    pub min_priority_fee: u64,
+    pub max_priority_fee: u64,
+}
+
+impl Default for ComputeConfig {
+    fn default() -> Self {
+        Self {
+            simulate: false,
+            unit_margin: 1.1,
+            priority_fee_percentile: 75,
+            min_priority_fee: 0,
+            max_priority_fee: 1_000_000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+struct PrioritizationFee {
+    prioritization_fee: u64,
+}
+
+/// the compute budget of one route
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComputeBudget {
+    pub units: u32,
+    // micro-lamports per unit
+    pub unit_price: u64,
+}
+
+impl ComputeBudget {
+    /// lamports the tx pays in fees (the priority fee + one signature)
+    pub fn fee_lamports(&self) -> u64 {
+        let priority = (self.units as u128 * self.unit_price as u128 + MICRO_LAMPORTS_PER_LAMPORT - 1)
+            / MICRO_LAMPORTS_PER_LAMPORT;
+        priority as u64 + LAMPORTS_PER_SIGNATURE
+    }
+
+    /// set the budget ahead of `ixs` (replacing any budget already there)
+    pub fn apply(&self, ixs: &mut Vec<Instruction>) {
+        ixs.retain(|ix| ix.program_id != compute_budget::id());
+        ixs.insert(0, ComputeBudgetInstruction::set_compute_unit_limit(self.units));
+        if self.unit_price > 0 {
+            ixs.insert(1, ComputeBudgetInstruction::set_compute_unit_price(self.unit_price));
+        }
+    }
+}
+
+impl ComputeConfig {
+    /// units for a route through the named venues (with margin)
+    pub fn table_units(&self, venues: &[String]) -> u32 {
+        let units = ROUTE_OVERHEAD_UNITS
+            + venues.iter().map(|name| venue_compute_units(name)).sum::<u32>();
+        self.with_margin(units as u64)
+    }
+
+    pub fn with_margin(&self, units: u64) -> u32 {
+        ((units as f64 * self.unit_margin) as u64).min(MAX_COMPUTE_UNITS as u64) as u32
+    }
+
+    /// the configured percentile of the fees, clamped to [min, max]
+    pub fn unit_price(&self, mut fees: Vec<u64>) -> u64 {
+        let price = if fees.is_empty() {
+            0
+        } else {
+            fees.sort_unstable();
+            let rank = (fees.len() - 1) * self.priority_fee_percentile.min(100) as usize / 100;
+            fees[rank]
+        };
+        price.clamp(self.min_priority_fee, self.max_priority_fee.max(self.min_priority_fee))
+    }
+
+    /// unit price from what recently landed writing the same accounts
+    pub fn recent_unit_price(&self, connection: &RpcClient, writable: &[Pubkey]) -> Result<u64> {
+        let keys: Vec<String> = writable
+            .iter()
+            .take(MAX_FEE_ACCOUNTS)
+            .map(|key| key.to_string())
+            .collect();
+        let fees: Vec<PrioritizationFee> = connection.send(
+            RpcRequest::Custom { method: "getRecentPrioritizationFees" },
+            json!([keys]),
+        )?;
+        Ok(self.unit_price(fees.iter().map(|fee| fee.prioritization_fee).collect()))
+    }
+}
+
+/// the accounts the ixs write (the ones fee markets are local to)
+pub fn writable_accounts(ixs: &[Instruction], payer: &Pubkey) -> Vec<Pubkey> {
+    let mut keys = vec![];
+    for meta in ixs.iter().flat_map(|ix| ix.accounts.iter()) {
+        if meta.is_writable && meta.pubkey != *payer && !keys.contains(&meta.pubkey) {
+            keys.push(meta.pubkey);
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fees_round_up_plus_signature() {
+        let budget = ComputeBudget { units: 300_000, unit_price: 10_001 };
+        // 3_000.3 lamports -> 3_001
+        assert_eq!(budget.fee_lamports(), 3_001 + LAMPORTS_PER_SIGNATURE);
+        let free = ComputeBudget { units: 300_000, unit_price: 0 };
+        assert_eq!(free.fee_lamports(), LAMPORTS_PER_SIGNATURE);
+    }
+
+    #[test]
+    fn unit_price_percentile_is_clamped() {
+        let config = ComputeConfig { min_priority_fee: 10, max_priority_fee: 500, ..ComputeConfig::default() };
+        let fees: Vec<u64> = (0..=100).collect();
+        assert_eq!(config.unit_price(fees), 75);
+        assert_eq!(config.unit_price(vec![]), 10);
+        assert_eq!(config.unit_price(vec![1_000, 2_000]), 500);
+    }
+
+    #[test]
+    fn budget_goes_first() {
+        let payer = Pubkey::new_unique();
+        let mut ixs = vec![solana_sdk::system_instruction::transfer(&payer, &Pubkey::new_unique(), 1)];
+        let budget = ComputeBudget { units: 200_000, unit_price: 5 };
+        budget.apply(&mut ixs);
+        budget.apply(&mut ixs);
+        assert_eq!(ixs.len(), 3);
+        assert_eq!(ixs[0], ComputeBudgetInstruction::set_compute_unit_limit(200_000));
+        assert_eq!(ixs[1], ComputeBudgetInstruction::set_compute_unit_price(5));
+        assert_eq!(writable_accounts(&ixs, &payer), vec![ixs[2].accounts[1].pubkey]);
+    }
+}
@@ -0,0 +1,324 @@
+//! Settings for the arbitrage binary
+//!
+//! read from a toml file, then overridden by `ARB_*` env vars, then by cli
+//! flags (see `main.rs`). see `arb.example.toml` for every field.
+
+use std::env;
+use std::str::FromStr;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::{read_keypair_file, Keypair};
+use anchor_client::Cluster;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::compute::ComputeConfig;
+use crate::dedup::DedupConfig;
+use crate::jito::JitoConfig;
+use crate::pool::{PoolDir, PoolType};
+use crate::search::MAX_HOPS;
+use crate::svm::SvmConfig;
+
+pub const DEFAULT_CONFIG_PATH: &str = "arb.toml";
+pub const DEFAULT_LOOKUP_TABLES_PATH: &str = "lookup_tables.json";
+pub const DEFAULT_LEDGER_PATH: &str = "arb_ledger.jsonl";
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// localnet | devnet | testnet | mainnet | any http(s) url
+    pub cluster: String,
+    /// read endpoint (defaults to the cluster's url)
+    pub rpc_url: Option<String>,
+    /// endpoint txs are sent to (defaults to the cluster's url)
+    pub send_rpc_url: Option<String>,
+    /// keypair json file, or an env var holding the keypair's json bytes
+    pub keypair_path: Option<String>,
+    pub keypair_env: Option<String>,
+    /// simulate txs instead of sending them
+    pub dry_run: bool,
+
+    pub pools: Vec<PoolDir>,
+    /// mints we hold inventory in -- cycles start + end at each of them
+    pub start_mints: Vec<String>,
+    /// profits from every start mint are compared in this mint (default = first start mint)
+    pub value_mint: Option<String>,
+
+    // scaled amounts, in value mint units
+    pub min_swap_amount: u64,
+    pub max_swap_amount: Option<u64>,
+    pub min_profit: u64,
+
+    pub max_hops: usize,
+    /// json list of the lookup tables routes are compiled against (created if missing)
+    pub lookup_tables_path: String,
+    /// json lines of every arb sent (see the reconcile_ledger bin)
+    pub ledger_path: String,
+    /// send arbs as tipped jito bundles instead of through send_rpc_url
+    pub jito: Option<JitoConfig>,
+    /// compute limits + priority fees (their cost comes off each arb's profit)
+    pub compute: ComputeConfig,
+    /// how long sent arbs are held back from being re-sent
+    pub dedup: DedupConfig,
+    /// run arbs offline against dumped programs + accounts instead of the cluster
+    pub svm: Option<SvmConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            cluster: "localnet".to_string(),
+            rpc_url: None,
+            send_rpc_url: None,
+            keypair_path: None,
+            keypair_env: None,
+            dry_run: false,
+            pools: vec![
+                PoolDir {
+                    tipe: PoolType::OrcaPoolType,
+                    dir_path: "../pools/orca".to_string(),
+                    enabled: true,
+                },
+                PoolDir {
+                    tipe: PoolType::MercurialPoolType,
+                    dir_path: "../pools/mercurial".to_string(),
+                    enabled: true,
+                },
+                PoolDir {
+                    tipe: PoolType::SaberPoolType,
+                    dir_path: "../pools/saber/".to_string(),
+                    enabled: true,
+                },
+            ],
+            // USDC
+            start_mints: vec!["EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string()],
+            value_mint: None,
+            min_swap_amount: 1_000_000, // 1 USDC
+            max_swap_amount: None,
+            min_profit: 0,
+            max_hops: MAX_HOPS,
+            lookup_tables_path: DEFAULT_LOOKUP_TABLES_PATH.to_string(),
+            ledger_path: DEFAULT_LEDGER_PATH.to_string(),
+            jito: None,
+            compute: ComputeConfig::default(),
+            dedup: DedupConfig::default(),
+            svm: None,
+        }
+    }
+}
+
+fn env_var(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|v| !v.is_empty())
+}
+
+fn parse_env<T: FromStr>(key: &str) -> Result<Option<T>> {
+    match env_var(key) {
+        Some(v) => v
+            .parse::<T>()
+            .map(Some)
+            .map_err(|_| anyhow!("invalid value for {}: {}", key, v)),
+        None => Ok(None),
+    }
+}
+
+impl Config {
+    /// the given file (must exist), else ./arb.toml if there is one, else defaults
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let config = match path {
+            Some(path) => Self::from_file(path)?,
+            None if std::path::Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(DEFAULT_CONFIG_PATH)?
+            }
+            None => Self::default(),
+        };
+        config.with_env()
+    }
+
+    pub fn from_file(path: &str) -> Result<Self> {
+        let contents =
+            std::fs::read_to_string(path).with_context(|| format!("reading config {}", path))?;
+        toml::from_str(&contents).with_context(|| format!("parsing config {}", path))
+    }
+
+    /// ARB_* env vars override the file
+    pub fn with_env(mut self) -> Result<Self> {
+        if let Some(v) = env_var("ARB_CLUSTER") {
+            self.cluster = v;
+        }
+        if let Some(v) = env_var("ARB_RPC_URL") {
+            self.rpc_url = Some(v);
+        }
+        if let Some(v) = env_var("ARB_SEND_RPC_URL") {
+            self.send_rpc_url = Some(v);
+        }
+        if let Some(v) = env_var("ARB_KEYPAIR_PATH") {
+            self.keypair_path = Some(v);
+        }
+        if let Some(v) = env_var("ARB_START_MINTS") {
+            self.start_mints = v.split(',').map(|m| m.trim().to_string()).collect();
+        }
+        if let Some(v) = env_var("ARB_VALUE_MINT") {
+            self.value_mint = Some(v);
+        }
+        if let Some(v) = parse_env("ARB_DRY_RUN")? {
+            self.dry_run = v;
+        }
+        if let Some(v) = parse_env("ARB_MIN_SWAP_AMOUNT")? {
+            self.min_swap_amount = v;
+        }
+        if let Some(v) = parse_env("ARB_MAX_SWAP_AMOUNT")? {
+            self.max_swap_amount = Some(v);
+        }
+        if let Some(v) = parse_env("ARB_MIN_PROFIT")? {
+            self.min_profit = v;
+        }
+        if let Some(v) = parse_env("ARB_MAX_HOPS")? {
+            self.max_hops = v;
+        }
+        if let Some(v) = env_var("ARB_LOOKUP_TABLES_PATH") {
+            self.lookup_tables_path = v;
+        }
+        if let Some(v) = env_var("ARB_LEDGER_PATH") {
+            self.ledger_path = v;
+        }
+        Ok(self)
+    }
+
+    pub fn cluster(&self) -> Result<Cluster> {
+        Cluster::from_str(&self.cluster).map_err(|_| anyhow!("invalid cluster: {}", self.cluster))
+    }
+
+    pub fn rpc_url(&self) -> Result<String> {
+        match &self.rpc_url {
+            Some(url) => Ok(url.clone()),
+            None => Ok(self.cluster()?.url().to_string()),
+        }
+    }
+
+    pub fn send_rpc_url(&self) -> Result<String> {
+        match &self.send_rpc_url {
+            Some(url) => Ok(url.clone()),
+            None => Ok(self.cluster()?.url().to_string()),
+        }
+    }
+
+    pub fn keypair(&self) -> Result<Keypair> {
+        if let Some(var) = &self.keypair_env {
+            let json = env::var(var).with_context(|| format!("keypair env var {} not set", var))?;
+            let bytes: Vec<u8> = serde_json::from_str(&json)
+                .with_context(|| format!("keypair env var {} is not a json byte array", var))?;
+            return Keypair::from_bytes(&bytes).map_err(|e| anyhow!("invalid keypair: {}", e));
+        }
+        match &self.keypair_path {
+            Some(path) => read_keypair_file(path)
+                .map_err(|e| anyhow!("reading keypair {}: {}", path, e)),
+            None => Err(anyhow!("no keypair_path or keypair_env configured")),
+        }
+    }
+
+    pub fn start_mints(&self) -> Result<Vec<Pubkey>> {
+        self.start_mints
+            .iter()
+            .map(|m| Pubkey::from_str(m).map_err(|_| anyhow!("invalid start mint: {}", m)))
+            .collect()
+    }
+
+    pub fn value_mint(&self) -> Result<Pubkey> {
+        let mint = match &self.value_mint {
+            Some(mint) => mint,
+            None => self
+                .start_mints
+                .first()
+                .ok_or_else(|| anyhow!("no start mints configured"))?,
+        };
+        Pubkey::from_str(mint).map_err(|_| anyhow!("invalid value mint: {}", mint))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.cluster()?;
+        self.start_mints()?;
+        self.value_mint()?;
+        if self.start_mints.is_empty() {
+            return Err(anyhow!("no start mints configured"));
+        }
+        if self.max_hops < 2 {
+            return Err(anyhow!("max_hops must be >= 2"));
+        }
+        if let Some(jito) = &self.jito {
+            if !(0.0..=1.0).contains(&jito.tip_fraction) {
+                return Err(anyhow!("jito tip_fraction must be within [0, 1]"));
+            }
+        }
+        if self.compute.unit_margin < 1.0 {
+            return Err(anyhow!("compute unit_margin must be >= 1"));
+        }
+        if self.dedup.slot_window == 0 {
+            return Err(anyhow!("dedup slot_window must be > 0"));
+        }
+        if let Some(max) = self.max_swap_amount {
+            if max < self.min_swap_amount {
+                return Err(anyhow!("max_swap_amount < min_swap_amount"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_example_config() {
+        let config = Config::from_file("arb.example.toml").unwrap();
+        config.validate().unwrap();
+        assert!(!config.pools.is_empty());
+        assert_eq!(config.cluster().unwrap(), Cluster::Mainnet);
+    }
+
+    #[test]
+    fn custom_cluster_urls() {
+        let config = Config {
+            cluster: "http://127.0.0.1:8899".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(config.rpc_url().unwrap(), "http://127.0.0.1:8899");
+
+        let config = Config {
+            cluster: "devnet".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(config.cluster().unwrap(), Cluster::Devnet);
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let config: Config = toml::from_str("cluster = \"mainnet\"\nmax_hops = 4").unwrap();
+        assert_eq!(config.max_hops, 4);
+        assert_eq!(config.pools.len(), Config::default().pools.len());
+        assert!(config.jito.is_none());
+    }
+
+    #[test]
+    fn jito_section_fills_defaults() {
+        let config: Config = toml::from_str("[jito]\ntip_fraction = 0.25").unwrap();
+        let jito = config.jito.unwrap();
+        assert_eq!(jito.tip_fraction, 0.25);
+        assert_eq!(jito.block_engines.len(), 6);
+    }
+
+    #[test]
+    fn dedup_section_fills_defaults() {
+        let config: Config = toml::from_str("[dedup]\nttl_secs = 30").unwrap();
+        assert_eq!(config.dedup.ttl_secs, 30);
+        assert_eq!(config.dedup.path, DedupConfig::default().path);
+    }
+
+    #[test]
+    fn svm_section_fills_defaults() {
+        let config: Config = toml::from_str("[svm]\naccounts_dir = \"snapshots/1\"").unwrap();
+        let svm = config.svm.unwrap();
+        assert_eq!(svm.accounts_dir, "snapshots/1");
+        assert_eq!(svm.programs_dir, SvmConfig::default().programs_dir);
+    }
+}
@@ -0,0 +1,165 @@
+//! Dedup of sent arbs
+//!
+//! an arb is keyed by its route (the mint path + each pool's address, so the
+//! direction counts). a route sent less than `slot_window` slots ago isnt
+//! sent again unless its size moved enough to mean the pools did -- once the
+//! window passes it's retried. entries expire after `ttl_secs` and are saved
+//! after every send, so `arbitrage.sh` restarting the bot doesnt re-fire
+//! what it just sent.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::{Context, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::unix_now;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct DedupConfig {
+    /// json file of the arbs sent (loaded on start)
+    pub path: String,
+    /// entries older than this are dropped
+    pub ttl_secs: i64,
+    /// slots a route is held for after it's sent
+    pub slot_window: u64,
+    /// re-send within the window when the size moves by this fraction
+    pub resend_size_change: f64,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            path: "sent_arbs.json".to_string(),
+            ttl_secs: 120,
+            slot_window: 25, // ~10s
+            resend_size_change: 0.2,
+        }
+    }
+}
+
+/// mint path + pool addresses, in swap order
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RouteKey {
+    pub mints: Vec<Pubkey>,
+    pub pools: Vec<Pubkey>,
+}
+
+impl RouteKey {
+    // "{mint0}>{pool0}>{mint1}>...>{mint0}"
+    fn encode(&self) -> String {
+        let mut parts = vec![self.mints[0].to_string()];
+        for (pool, mint) in self.pools.iter().zip(self.mints.iter().skip(1)) {
+            parts.push(pool.to_string());
+            parts.push(mint.to_string());
+        }
+        parts.join(">")
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct SentArb {
+    pub sent_at: i64,
+    pub slot: u64,
+    pub amount_in: u128,
+}
+
+#[derive(Debug, Clone)]
+pub struct SentArbs {
+    pub config: DedupConfig,
+    // route -> its last send
+    pub sent: BTreeMap<String, SentArb>,
+}
+
+impl SentArbs {
+    pub fn new(config: DedupConfig) -> Self {
+        Self { config, sent: BTreeMap::new() }
+    }
+
+    /// the saved arbs which havent expired (none if the file doesnt exist)
+    pub fn load(config: &DedupConfig) -> Result<Self> {
+        let mut arbs = Self::new(config.clone());
+        if Path::new(&config.path).exists() {
+            let json = std::fs::read_to_string(&config.path).with_context(|| format!("reading {}", config.path))?;
+            arbs.sent = serde_json::from_str(&json).with_context(|| format!("parsing {}", config.path))?;
+        }
+        arbs.expire(unix_now());
+        Ok(arbs)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let tmp = format!("{}.tmp", self.config.path);
+        std::fs::write(&tmp, serde_json::to_string_pretty(&self.sent)?).with_context(|| format!("writing {}", tmp))?;
+        std::fs::rename(&tmp, &self.config.path).with_context(|| format!("replacing {}", self.config.path))
+    }
+
+    pub fn expire(&mut self, now: i64) {
+        let ttl = self.config.ttl_secs;
+        self.sent.retain(|_, arb| now - arb.sent_at <= ttl);
+    }
+
+    /// false = the route was sent within the window at about this size
+    pub fn should_send(&self, route: &RouteKey, amount_in: u128, slot: u64, now: i64) -> bool {
+        let sent = match self.sent.get(&route.encode()) {
+            Some(sent)
+                if now - sent.sent_at <= self.config.ttl_secs
+                    && slot.saturating_sub(sent.slot) < self.config.slot_window =>
+            {
+                sent
+            }
+            _ => return true,
+        };
+        let change = (amount_in as f64 - sent.amount_in as f64).abs() / (sent.amount_in.max(1) as f64);
+        change >= self.config.resend_size_change
+    }
+
+    pub fn insert(&mut self, route: &RouteKey, amount_in: u128, slot: u64, now: i64) {
+        self.expire(now);
+        self.sent.insert(route.encode(), SentArb { sent_at: now, slot, amount_in });
+        if let Err(err) = self.save() {
+            warn!("failed to save the sent arbs: {:?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(mints: &[Pubkey], pools: &[Pubkey]) -> RouteKey {
+        RouteKey { mints: mints.to_vec(), pools: pools.to_vec() }
+    }
+
+    #[test]
+    fn routes_are_held_per_window_and_size() {
+        let path = std::env::temp_dir().join(format!("sent-arbs-{}.json", Pubkey::new_unique()));
+        let config = DedupConfig { path: path.to_str().unwrap().to_string(), ..DedupConfig::default() };
+        let [a, b, pool0, pool1] = [0; 4].map(|_| Pubkey::new_unique());
+        let forward = route(&[a, b, a], &[pool0, pool1]);
+        let backward = route(&[a, b, a], &[pool1, pool0]);
+
+        let mut arbs = SentArbs::new(config.clone());
+        let now = unix_now();
+        arbs.insert(&forward, 1_000_000, 100, now);
+        assert!(!arbs.should_send(&forward, 1_000_000, 110, now + 1));
+        assert!(!arbs.should_send(&forward, 1_100_000, 110, now + 1));
+        // the pools moved enough to change the size
+        assert!(arbs.should_send(&forward, 1_500_000, 110, now + 1));
+        // same pools, other direction
+        assert!(arbs.should_send(&backward, 1_000_000, 110, now + 1));
+        // held for the window after the send, wherever it falls
+        assert!(!arbs.should_send(&forward, 1_000_000, 124, now + 10));
+        // then retried
+        assert!(arbs.should_send(&forward, 1_000_000, 125, now + 10));
+
+        // survives a restart, until it expires
+        let loaded = SentArbs::load(&config).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.sent, arbs.sent);
+        assert!(!loaded.should_send(&forward, 1_000_000, 110, now + 1));
+        assert!(loaded.should_send(&forward, 1_000_000, 110, now + config.ttl_secs + 1));
+    }
+}
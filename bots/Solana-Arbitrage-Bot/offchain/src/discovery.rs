@@ -0,0 +1,524 @@
+//! On-chain pool discovery
+//!
+//! each venue's program is scanned with getProgramAccounts (filtered on the
+//! pool account's discriminator / size, and sliced down to the keys we read),
+//! the pools are decoded into their `pool_factory` json, and the mints are
+//! fetched for their decimals + token program. pools touching a denied mint
+//! (or denied themselves) are dropped, as are ones whose vaults hold less
+//! than the configured minimum of a priced mint.
+//!
+//! only venues whose pool account names everything a swap needs are
+//! covered -- the legacy token-swap pools (orca v1, saber, mercurial, aldrin)
+//! carry fee structures from their sdk configs, and pump.fun curves are
+//! listed per token instead.
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use anchor_client::solana_client::rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType};
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::{anyhow, Context, Result};
+use arrayref::array_ref;
+use log::{debug, info};
+use serde_json::{json, Map, Value};
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_sdk::account::Account;
+
+use crate::constants::*;
+use crate::pool::{pool_factory, PoolType};
+use crate::pool_utils::phoenix::PhoenixMarket;
+use crate::refresh::AccountSource;
+use crate::serialize::token::unpack_token_account;
+use crate::utils::anchor_discriminator;
+
+// spl mint
+const MINT_LEN: usize = 82;
+const MINT_DECIMALS: usize = 44;
+
+// raydium amm v4 AmmInfo
+const AMM_NONCE: usize = 8;
+const AMM_AUTHORITY_SEED: &[u8] = b"amm authority";
+// serum / openbook v1 market (after the 5 byte "serum" padding)
+const MARKET_VAULT_SIGNER_NONCE: usize = 45;
+const MARKET_KEYS: [(&str, usize); 5] = [
+    ("marketBaseVault", 117),
+    ("marketQuoteVault", 165),
+    ("marketEventQueue", 253),
+    ("marketBids", 285),
+    ("marketAsks", 317),
+];
+
+/// where a venue's pool account keeps what its json needs
+#[derive(Debug, Clone)]
+pub struct VenueLayout {
+    pub tipe: PoolType,
+    pub program: Pubkey,
+    // anchor account name (filtered on its discriminator)
+    pub account: Option<&'static str>,
+    pub data_len: Option<u64>,
+    pub address_field: &'static str,
+    // (json field, offset)
+    pub mints: [(&'static str, usize); 2],
+    // vaults are read for the liquidity filter -- not every pool keeps them
+    pub vaults: [(Option<&'static str>, usize); 2],
+    pub keys: &'static [(&'static str, usize)],
+    pub decimals: [&'static str; 2],
+    pub programs: Option<[&'static str; 2]>,
+    // false = the whole account is decoded
+    pub sliced: bool,
+}
+
+impl VenueLayout {
+    // bytes up to the last key we read
+    fn slice_len(&self) -> usize {
+        let offsets = self
+            .mints
+            .iter()
+            .map(|(_, offset)| *offset)
+            .chain(self.vaults.iter().map(|(_, offset)| *offset))
+            .chain(self.keys.iter().map(|(_, offset)| *offset));
+        offsets.max().unwrap_or(0) + 32
+    }
+}
+
+/// every venue discovery covers
+pub fn venue_layouts() -> Vec<VenueLayout> {
+    vec![
+        VenueLayout {
+            tipe: PoolType::RaydiumCpmmPoolType,
+            program: *RAYDIUM_CPMM_PROGRAM_ID,
+            account: Some("PoolState"),
+            data_len: Some(637),
+            address_field: "id",
+            mints: [("token0Mint", 168), ("token1Mint", 200)],
+            vaults: [(Some("token0Vault"), 72), (Some("token1Vault"), 104)],
+            keys: &[("ammConfig", 8), ("observationKey", 296)],
+            decimals: ["mint0Decimals", "mint1Decimals"],
+            programs: Some(["token0Program", "token1Program"]),
+            sliced: true,
+        },
+        VenueLayout {
+            tipe: PoolType::RaydiumClmmPoolType,
+            program: *RAYDIUM_CLMM_PROGRAM_ID,
+            account: Some("PoolState"),
+            data_len: Some(1544),
+            address_field: "id",
+            mints: [("token0Mint", 73), ("token1Mint", 105)],
+            vaults: [(Some("token0Vault"), 137), (Some("token1Vault"), 169)],
+            keys: &[("ammConfig", 9), ("observationKey", 201)],
+            decimals: ["mint0Decimals", "mint1Decimals"],
+            programs: Some(["token0Program", "token1Program"]),
+            sliced: true,
+        },
+        VenueLayout {
+            tipe: PoolType::RaydiumAmmV4PoolType,
+            program: *RAYDIUM_AMM_V4_PROGRAM_ID,
+            account: None,
+            data_len: Some(752),
+            address_field: "id",
+            mints: [("baseMint", 400), ("quoteMint", 432)],
+            vaults: [(Some("baseVault"), 336), (Some("quoteVault"), 368)],
+            keys: &[("openOrders", 496), ("marketId", 528), ("marketProgramId", 560), ("targetOrders", 592)],
+            decimals: ["baseDecimals", "quoteDecimals"],
+            programs: None,
+            sliced: true,
+        },
+        VenueLayout {
+            tipe: PoolType::OrcaWhirlpoolPoolType,
+            program: *ORCA_WHIRLPOOL_PROGRAM_ID,
+            account: Some("Whirlpool"),
+            data_len: Some(653),
+            address_field: "address",
+            mints: [("tokenMintA", 101), ("tokenMintB", 181)],
+            vaults: [(Some("tokenVaultA"), 133), (Some("tokenVaultB"), 213)],
+            keys: &[],
+            decimals: ["decimalsA", "decimalsB"],
+            programs: Some(["tokenProgramA", "tokenProgramB"]),
+            sliced: true,
+        },
+        VenueLayout {
+            tipe: PoolType::MeteoraDlmmPoolType,
+            program: *METEORA_DLMM_PROGRAM_ID,
+            account: Some("LbPair"),
+            data_len: Some(904),
+            address_field: "address",
+            mints: [("tokenXMint", 88), ("tokenYMint", 120)],
+            vaults: [(Some("reserveX"), 152), (Some("reserveY"), 184)],
+            keys: &[("oracle", 552)],
+            decimals: ["decimalsX", "decimalsY"],
+            programs: Some(["tokenXProgram", "tokenYProgram"]),
+            sliced: true,
+        },
+        VenueLayout {
+            tipe: PoolType::MeteoraDammV2PoolType,
+            program: *METEORA_DAMM_V2_PROGRAM_ID,
+            account: Some("Pool"),
+            data_len: Some(1112),
+            address_field: "pool",
+            mints: [("tokenAMint", 168), ("tokenBMint", 200)],
+            vaults: [(Some("tokenAVault"), 232), (Some("tokenBVault"), 264)],
+            keys: &[],
+            decimals: ["decimalsA", "decimalsB"],
+            programs: Some(["tokenAProgram", "tokenBProgram"]),
+            sliced: true,
+        },
+        VenueLayout {
+            tipe: PoolType::PumpSwapPoolType,
+            program: *PUMPSWAP_PROGRAM_ID,
+            account: Some("Pool"),
+            data_len: Some(243),
+            address_field: "address",
+            mints: [("baseMint", 43), ("quoteMint", 75)],
+            vaults: [(Some("poolBaseTokenAccount"), 139), (Some("poolQuoteTokenAccount"), 171)],
+            keys: &[],
+            decimals: ["baseDecimals", "quoteDecimals"],
+            programs: Some(["baseTokenProgram", "quoteTokenProgram"]),
+            sliced: true,
+        },
+        VenueLayout {
+            tipe: PoolType::OpenBookV2PoolType,
+            program: *OPENBOOK_V2_PROGRAM_ID,
+            account: Some("Market"),
+            data_len: Some(848),
+            address_field: "address",
+            mints: [("baseMint", 576), ("quoteMint", 608)],
+            vaults: [(None, 640), (None, 680)],
+            keys: &[("bids", 200), ("asks", 232)],
+            decimals: ["baseDecimals", "quoteDecimals"],
+            programs: None,
+            sliced: true,
+        },
+        // markets vary in size (+ have no anchor discriminator) -- every
+        // account is decoded and the ones which arent markets dropped
+        VenueLayout {
+            tipe: PoolType::PhoenixPoolType,
+            program: *PHOENIX_PROGRAM_ID,
+            account: None,
+            data_len: None,
+            address_field: "address",
+            mints: [("baseMint", 48), ("quoteMint", 128)],
+            vaults: [(None, 80), (None, 160)],
+            keys: &[],
+            decimals: ["baseDecimals", "quoteDecimals"],
+            programs: None,
+            sliced: false,
+        },
+    ]
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(*array_ref![data, offset, 32])
+}
+
+/// a decoded pool account, before its mints are resolved
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub tipe: PoolType,
+    pub address: Pubkey,
+    pub mints: [Pubkey; 2],
+    pub vaults: [Pubkey; 2],
+    pub json: Map<String, Value>,
+    // raydium amm v4: the (market, market program) it sits on
+    pub market: Option<(Pubkey, Pubkey)>,
+}
+
+/// the pool's keys out of its (possibly sliced) account data
+pub fn decode_pool(layout: &VenueLayout, address: &Pubkey, data: &[u8]) -> Option<Candidate> {
+    if data.len() < layout.slice_len() {
+        return None;
+    }
+    if layout.tipe == PoolType::PhoenixPoolType && PhoenixMarket::unpack(data, 0).is_none() {
+        return None;
+    }
+
+    let mut json = Map::new();
+    json.insert(layout.address_field.to_string(), json!(address.to_string()));
+    let mints = [read_pubkey(data, layout.mints[0].1), read_pubkey(data, layout.mints[1].1)];
+    let vaults = [read_pubkey(data, layout.vaults[0].1), read_pubkey(data, layout.vaults[1].1)];
+    for (i, (field, _)) in layout.mints.iter().enumerate() {
+        json.insert(field.to_string(), json!(mints[i].to_string()));
+    }
+    for (i, (field, _)) in layout.vaults.iter().enumerate() {
+        if let Some(field) = field {
+            json.insert(field.to_string(), json!(vaults[i].to_string()));
+        }
+    }
+    for (field, offset) in layout.keys {
+        json.insert(field.to_string(), json!(read_pubkey(data, *offset).to_string()));
+    }
+    if mints[0] == mints[1] || mints.contains(&Pubkey::default()) {
+        return None;
+    }
+
+    let mut market = None;
+    if layout.tipe == PoolType::RaydiumAmmV4PoolType {
+        let nonce = data[AMM_NONCE];
+        let authority = Pubkey::create_program_address(&[AMM_AUTHORITY_SEED, &[nonce]], &layout.program).ok()?;
+        json.insert("authority".to_string(), json!(authority.to_string()));
+        market = Some((read_pubkey(data, 528), read_pubkey(data, 560)));
+    }
+
+    Some(Candidate { tipe: layout.tipe.clone(), address: *address, mints, vaults, json, market })
+}
+
+/// the market keys a raydium amm v4 pool swaps through
+pub fn resolve_market(candidate: &mut Candidate, market_data: &[u8]) -> Option<()> {
+    let (market, market_program) = candidate.market?;
+    if market_data.len() < MARKET_KEYS[4].1 + 32 {
+        return None;
+    }
+    for (field, offset) in MARKET_KEYS {
+        candidate.json.insert(field.to_string(), json!(read_pubkey(market_data, offset).to_string()));
+    }
+    let nonce = u64::from_le_bytes(*array_ref![market_data, MARKET_VAULT_SIGNER_NONCE, 8]);
+    let authority =
+        Pubkey::create_program_address(&[market.as_ref(), &nonce.to_le_bytes()], &market_program).ok()?;
+    candidate.json.insert("marketAuthority".to_string(), json!(authority.to_string()));
+    Some(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MintInfo {
+    pub decimals: u8,
+    pub program: Pubkey,
+}
+
+pub fn unpack_mint_info(account: &Account) -> Option<MintInfo> {
+    if account.data.len() < MINT_LEN {
+        return None;
+    }
+    if account.owner != *TOKEN_PROGRAM_ID && account.owner != *TOKEN_2022_PROGRAM_ID {
+        return None;
+    }
+    Some(MintInfo { decimals: account.data[MINT_DECIMALS], program: account.owner })
+}
+
+/// the json with the mints' decimals + programs filled in
+pub fn finish_pool(layout: &VenueLayout, candidate: &Candidate, mints: &HashMap<Pubkey, MintInfo>) -> Option<Value> {
+    let mut json = candidate.json.clone();
+    for (i, mint) in candidate.mints.iter().enumerate() {
+        let info = mints.get(mint)?;
+        json.insert(layout.decimals[i].to_string(), json!(info.decimals as u64));
+        if let Some(programs) = layout.programs {
+            json.insert(programs[i].to_string(), json!(info.program.to_string()));
+        }
+    }
+    Some(Value::Object(json))
+}
+
+/// which pools are kept
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryFilter {
+    // pool addresses + mints
+    pub denylist: HashSet<Pubkey>,
+    // mint -> least (ui) amount its vault must hold. when set, pools without
+    // one of these mints are dropped
+    pub min_liquidity: HashMap<Pubkey, f64>,
+}
+
+impl DiscoveryFilter {
+    pub fn denies(&self, candidate: &Candidate) -> bool {
+        self.denylist.contains(&candidate.address) || candidate.mints.iter().any(|mint| self.denylist.contains(mint))
+    }
+
+    pub fn is_liquid(
+        &self,
+        candidate: &Candidate,
+        amounts: &HashMap<Pubkey, u64>,
+        mints: &HashMap<Pubkey, MintInfo>,
+    ) -> bool {
+        if self.min_liquidity.is_empty() {
+            return true;
+        }
+        candidate.mints.iter().zip(candidate.vaults.iter()).any(|(mint, vault)| {
+            let (min, info, amount) = match (self.min_liquidity.get(mint), mints.get(mint), amounts.get(vault)) {
+                (Some(min), Some(info), Some(amount)) => (min, info, amount),
+                _ => return false,
+            };
+            *amount as f64 / 10_f64.powi(info.decimals as i32) >= *min
+        })
+    }
+}
+
+/// pubkeys (one per line, `#` comments) of pools or mints to skip
+pub fn parse_denylist(contents: &str) -> Result<HashSet<Pubkey>> {
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+        .map(|key| Pubkey::from_str(key).map_err(|_| anyhow!("invalid denylist entry: {}", key)))
+        .collect()
+}
+
+/// `<mint>=<ui amount>`
+pub fn parse_min_liquidity(arg: &str) -> Result<(Pubkey, f64)> {
+    let (mint, amount) = arg
+        .split_once('=')
+        .ok_or_else(|| anyhow!("min liquidity should be <mint>=<amount>: {}", arg))?;
+    let mint = Pubkey::from_str(mint.trim()).map_err(|_| anyhow!("invalid mint: {}", mint))?;
+    let amount = amount.trim().parse::<f64>().map_err(|_| anyhow!("invalid amount: {}", amount))?;
+    Ok((mint, amount))
+}
+
+fn program_accounts(connection: &RpcClient, layout: &VenueLayout) -> Result<Vec<(Pubkey, Account)>> {
+    let mut filters = vec![];
+    if let Some(len) = layout.data_len {
+        filters.push(RpcFilterType::DataSize(len));
+    }
+    if let Some(name) = layout.account {
+        filters.push(RpcFilterType::Memcmp(Memcmp {
+            offset: 0,
+            bytes: MemcmpEncodedBytes::Bytes(anchor_discriminator("account", name).to_vec()),
+            encoding: None,
+        }));
+    }
+    let data_slice = layout.sliced.then(|| UiDataSliceConfig { offset: 0, length: layout.slice_len() });
+    let config = RpcProgramAccountsConfig {
+        filters: if filters.is_empty() { None } else { Some(filters) },
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            data_slice,
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+    connection
+        .get_program_accounts_with_config(&layout.program, config)
+        .with_context(|| format!("scanning {}", layout.program))
+}
+
+/// every pool of the venue which passes the filter, as (address, json)
+pub fn discover(connection: &RpcClient, layout: &VenueLayout, filter: &DiscoveryFilter) -> Result<Vec<(Pubkey, String)>> {
+    let accounts = program_accounts(connection, layout)?;
+    let scanned = accounts.len();
+    let mut candidates: Vec<Candidate> = accounts
+        .iter()
+        .filter_map(|(address, account)| decode_pool(layout, address, &account.data))
+        .filter(|candidate| !filter.denies(candidate))
+        .collect();
+    info!("{:?}: {} accounts, {} pools", layout.tipe, scanned, candidates.len());
+
+    // the markets raydium amm v4 pools sit on
+    let markets: Vec<Pubkey> = candidates.iter().filter_map(|c| c.market.map(|(market, _)| market)).collect();
+    if !markets.is_empty() {
+        let market_accounts: HashMap<Pubkey, Option<Account>> =
+            markets.iter().cloned().zip(connection.get_accounts(&markets)?).collect();
+        candidates.retain_mut(|candidate| {
+            let (market, _) = candidate.market.unwrap();
+            match &market_accounts[&market] {
+                Some(account) => resolve_market(candidate, &account.data).is_some(),
+                None => false,
+            }
+        });
+    }
+
+    let mut mint_keys: Vec<Pubkey> = candidates.iter().flat_map(|c| c.mints).collect();
+    mint_keys.sort_unstable();
+    mint_keys.dedup();
+    let mints: HashMap<Pubkey, MintInfo> = mint_keys
+        .iter()
+        .zip(connection.get_accounts(&mint_keys)?)
+        .filter_map(|(mint, account)| Some((*mint, unpack_mint_info(&account?)?)))
+        .collect();
+
+    let mut amounts = HashMap::new();
+    if !filter.min_liquidity.is_empty() {
+        let vaults: Vec<Pubkey> = candidates.iter().flat_map(|c| c.vaults).collect();
+        for (vault, account) in vaults.iter().zip(connection.get_accounts(&vaults)?) {
+            if let Some(account) = account.filter(|account| account.data.len() >= 165) {
+                amounts.insert(*vault, unpack_token_account(&account.data).amount);
+            }
+        }
+    }
+
+    let mut pools = vec![];
+    for candidate in candidates.iter().filter(|c| filter.is_liquid(c, &amounts, &mints)) {
+        let json = match finish_pool(layout, candidate, &mints) {
+            Some(json) => serde_json::to_string_pretty(&json)?,
+            None => continue,
+        };
+        // only write what the bot can load
+        if let Err(err) = pool_factory(&layout.tipe, &json) {
+            debug!("dropping {}: {}", candidate.address, err);
+            continue;
+        }
+        pools.push((candidate.address, json));
+    }
+    info!("{:?}: kept {} pools", layout.tipe, pools.len());
+    Ok(pools)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cpmm_layout() -> VenueLayout {
+        venue_layouts()
+            .into_iter()
+            .find(|layout| layout.tipe == PoolType::RaydiumCpmmPoolType)
+            .unwrap()
+    }
+
+    fn cpmm_account(layout: &VenueLayout, keys: &[(usize, Pubkey)]) -> Vec<u8> {
+        let mut data = vec![0; layout.slice_len()];
+        for (offset, key) in keys {
+            data[*offset..*offset + 32].copy_from_slice(key.as_ref());
+        }
+        data
+    }
+
+    #[test]
+    fn decoded_pools_load_in_the_factory() {
+        let layout = cpmm_layout();
+        let [mint0, mint1, vault0, vault1] = [0; 4].map(|_| Pubkey::new_unique());
+        let data = cpmm_account(
+            &layout,
+            &[(8, Pubkey::new_unique()), (72, vault0), (104, vault1), (168, mint0), (200, mint1), (296, Pubkey::new_unique())],
+        );
+        let address = Pubkey::new_unique();
+        let candidate = decode_pool(&layout, &address, &data).unwrap();
+        assert_eq!(candidate.mints, [mint0, mint1]);
+        assert_eq!(candidate.vaults, [vault0, vault1]);
+
+        let mints = HashMap::from([
+            (mint0, MintInfo { decimals: 6, program: *TOKEN_PROGRAM_ID }),
+            (mint1, MintInfo { decimals: 9, program: *TOKEN_2022_PROGRAM_ID }),
+        ]);
+        let json = finish_pool(&layout, &candidate, &mints).unwrap();
+        assert_eq!(json["mint1Decimals"], 9);
+        let pool = pool_factory(&layout.tipe, &json.to_string()).unwrap();
+        assert_eq!(pool.get_own_addr(), address);
+        assert_eq!(pool.get_mints(), vec![mint0, mint1]);
+
+        // unresolved mints arent written
+        assert!(finish_pool(&layout, &candidate, &HashMap::new()).is_none());
+        // nor pools with an empty side
+        let empty = cpmm_account(&layout, &[(168, mint0)]);
+        assert!(decode_pool(&layout, &address, &empty).is_none());
+    }
+
+    #[test]
+    fn denylist_and_liquidity_filters() {
+        let layout = cpmm_layout();
+        let [mint0, mint1, vault0, vault1] = [0; 4].map(|_| Pubkey::new_unique());
+        let data = cpmm_account(&layout, &[(72, vault0), (104, vault1), (168, mint0), (200, mint1)]);
+        let candidate = decode_pool(&layout, &Pubkey::new_unique(), &data).unwrap();
+
+        let denylist = parse_denylist(&format!("# cashio\n{}  # CASH\n\n", mint1)).unwrap();
+        let filter = DiscoveryFilter { denylist, ..DiscoveryFilter::default() };
+        assert!(filter.denies(&candidate));
+
+        let mints = HashMap::from([(mint0, MintInfo { decimals: 6, program: *TOKEN_PROGRAM_ID })]);
+        let filter = DiscoveryFilter {
+            min_liquidity: HashMap::from([parse_min_liquidity(&format!("{}=1000", mint0)).unwrap()]),
+            ..DiscoveryFilter::default()
+        };
+        assert!(!filter.denies(&candidate));
+        assert!(filter.is_liquid(&candidate, &HashMap::from([(vault0, 1_000_000_000)]), &mints));
+        assert!(!filter.is_liquid(&candidate, &HashMap::from([(vault0, 999_000_000)]), &mints));
+        // only mint1 is unpriced -- its vault doesnt count
+        assert!(!filter.is_liquid(&candidate, &HashMap::from([(vault1, u64::MAX)]), &mints));
+    }
+}
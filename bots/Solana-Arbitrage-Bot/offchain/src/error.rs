@@ -1,5 +1,7 @@
 //! Error types
 
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::ClientError;
 use num_derive::FromPrimitive;
 use solana_program::{decode_error::DecodeError, program_error::ProgramError};
 use thiserror::Error;
@@ -114,5 +116,60 @@ impl<T> DecodeError<T> for SwapError {
     }
 }
 
+/// Errors from quoting, updating or building swaps for an off-chain pool.
+/// the searcher flags + skips the pool instead of going down with it
+#[derive(Debug, Error)]
+pub enum PoolError {
+    /// The pool json doesnt match the pool type
+    #[error("Invalid pool json: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    /// The pool doesnt trade this mint
+    #[error("Mint {0} is not in the pool")]
+    UnknownMint(Pubkey),
+    /// The pool's vault amounts / accounts havent been loaded yet
+    #[error("Pool state for {0} has not been loaded")]
+    NotLoaded(String),
+    /// An update account doesnt exist on chain
+    #[error("Account {0} is missing")]
+    MissingAccount(Pubkey),
+    /// An update account couldnt be decoded
+    #[error("Account {0} has invalid data: {1}")]
+    InvalidAccountData(Pubkey, String),
+    /// The pool json has a curve type we cant quote
+    #[error("Curve type {0} is not supported")]
+    UnsupportedCurveType(u8),
+    /// The cluster isnt supported by this pool type
+    #[error("Cluster {0} is not supported")]
+    UnsupportedCluster(String),
+    /// The quote overflowed / underflowed or the curve rejected the trade
+    #[error("Quote failed: {0}")]
+    QuoteFailed(String),
+    /// Missing swap account (eg, no serum open orders for the market)
+    #[error("Missing swap account: {0}")]
+    MissingSwapAccount(String),
+    /// The route cant be built into one transaction
+    #[error("Unsupported route: {0}")]
+    UnsupportedRoute(String),
+    /// Anchor failed to build the swap instruction
+    #[error("Failed to build swap instruction: {0}")]
+    InstructionBuild(#[from] ClientError),
+}
 
+impl PoolError {
+    /// the pool's own state is bad (json / accounts) -- vs a quote or swap
+    /// which only fails for this size or route. only these take the pool
+    /// out of the search until its accounts update
+    pub fn is_pool_state(&self) -> bool {
+        matches!(
+            self,
+            PoolError::InvalidJson(_)
+                | PoolError::NotLoaded(_)
+                | PoolError::MissingAccount(_)
+                | PoolError::InvalidAccountData(..)
+                | PoolError::UnsupportedCurveType(_)
+                | PoolError::UnsupportedCluster(_)
+        )
+    }
+}
 
+pub type PoolResult<T> = std::result::Result<T, PoolError>;
@@ -0,0 +1,300 @@
+//! Jito bundle submission
+//!
+//! an arb goes out as a single-tx bundle whose last instruction tips a jito
+//! tip account. profit_or_revert runs before the tip, so a cycle that doesnt
+//! pay reverts the tip with it (and bundles with failing txs are dropped
+//! instead of landing, so failed arbs cost nothing). each bundle is fanned
+//! out to every configured block engine and its status polled until it
+//! lands or times out.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::VersionedTransaction;
+
+pub const DEFAULT_BLOCK_ENGINES: [&str; 6] = [
+    "https://frankfurt.mainnet.block-engine.jito.wtf",
+    "https://amsterdam.mainnet.block-engine.jito.wtf",
+    "https://london.mainnet.block-engine.jito.wtf",
+    "https://ny.mainnet.block-engine.jito.wtf",
+    "https://tokyo.mainnet.block-engine.jito.wtf",
+    "https://slc.mainnet.block-engine.jito.wtf",
+];
+
+const TIP_ACCOUNTS: [&str; 8] = [
+    "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+    "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
+    "Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY",
+    "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
+    "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
+    "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
+    "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
+    "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+];
+
+// the block engines dont accept smaller tips
+pub const MIN_TIP_LAMPORTS: u64 = 1_000;
+// getBundleStatuses takes at most this many ids per request
+const MAX_STATUS_IDS: usize = 5;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+// bundles which havent landed by then are given up on
+const BUNDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    static ref TIP_ACCOUNT_KEYS: Vec<Pubkey> = TIP_ACCOUNTS
+        .iter()
+        .map(|key| Pubkey::from_str(key).unwrap())
+        .collect();
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct JitoConfig {
+    /// every bundle is sent to all of these
+    pub block_engines: Vec<String>,
+    /// auth uuid (for higher rate limits)
+    pub uuid: Option<String>,
+    /// share of an arb's expected profit paid as its tip
+    pub tip_fraction: f64,
+    pub min_tip_lamports: u64,
+    pub max_tip_lamports: Option<u64>,
+}
+
+impl Default for JitoConfig {
+    fn default() -> Self {
+        Self {
+            block_engines: DEFAULT_BLOCK_ENGINES.iter().map(|url| url.to_string()).collect(),
+            uuid: None,
+            tip_fraction: 0.5,
+            min_tip_lamports: MIN_TIP_LAMPORTS,
+            max_tip_lamports: None,
+        }
+    }
+}
+
+impl JitoConfig {
+    /// tip for an arb worth `profit_value` (value mint units), where one
+    /// lamport is worth `sol_rate` value units (None = sol cant be priced)
+    pub fn tip_lamports(&self, profit_value: f64, sol_rate: Option<f64>) -> u64 {
+        let tip = match sol_rate {
+            Some(rate) if rate > 0.0 => (profit_value * self.tip_fraction / rate) as u64,
+            _ => 0,
+        };
+        let tip = tip.max(self.min_tip_lamports).max(MIN_TIP_LAMPORTS);
+        match self.max_tip_lamports {
+            Some(max) => tip.min(max),
+            None => tip,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PendingBundle {
+    // engine which accepted it first -- statuses are asked there
+    engine: String,
+    sent_at: Instant,
+}
+
+pub struct JitoClient {
+    pub config: JitoConfig,
+    http: reqwest::blocking::Client,
+    next_tip_account: AtomicUsize,
+    // bundle id -> where + when it was sent
+    pending: Mutex<HashMap<String, PendingBundle>>,
+}
+
+impl JitoClient {
+    pub fn new(config: JitoConfig) -> Result<Self> {
+        if config.block_engines.is_empty() {
+            return Err(anyhow!("no jito block engines configured"));
+        }
+        let http = reqwest::blocking::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            config,
+            http,
+            next_tip_account: AtomicUsize::new(0),
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// transfer to a tip account (rotated to spread write locks)
+    pub fn tip_ix(&self, payer: &Pubkey, lamports: u64) -> Instruction {
+        let idx = self.next_tip_account.fetch_add(1, Ordering::Relaxed) % TIP_ACCOUNT_KEYS.len();
+        system_instruction::transfer(payer, &TIP_ACCOUNT_KEYS[idx], lamports)
+    }
+
+    fn url(&self, engine: &str) -> String {
+        match &self.config.uuid {
+            Some(uuid) => format!("{}/api/v1/bundles?uuid={}", engine, uuid),
+            None => format!("{}/api/v1/bundles", engine),
+        }
+    }
+
+    fn post(&self, engine: &str, request: &Value) -> Result<Value> {
+        let res = self.http.post(self.url(engine)).json(request).send()?;
+        let status = res.status();
+        let body: Value = res.json()?;
+        if let Some(error) = body.get("error") {
+            return Err(anyhow!("{} ({}): {}", engine, status, error));
+        }
+        body.get("result")
+            .cloned()
+            .ok_or_else(|| anyhow!("{} ({}): no result", engine, status))
+    }
+
+    /// send to every block engine at once, returning the bundle id
+    pub fn send_bundle(&self, txs: &[VersionedTransaction]) -> Result<String> {
+        let request = bundle_request(txs)?;
+        let results: Vec<(String, Result<Value>)> = std::thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .config
+                .block_engines
+                .iter()
+                .map(|engine| scope.spawn(|| (engine.clone(), self.post(engine, &request))))
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        let mut accepted = None;
+        for (engine, result) in results {
+            match result {
+                Ok(id) => {
+                    debug!("bundle accepted by {}: {}", engine, id);
+                    accepted.get_or_insert((engine, id));
+                }
+                Err(err) => debug!("bundle rejected: {}", err),
+            }
+        }
+        let (engine, id) = accepted.ok_or_else(|| anyhow!("no block engine accepted the bundle"))?;
+        let id = id.as_str().map(|id| id.to_string()).unwrap_or_else(|| id.to_string());
+        self.pending.lock().unwrap().insert(
+            id.clone(),
+            PendingBundle { engine, sent_at: Instant::now() },
+        );
+        Ok(id)
+    }
+
+    /// bundles sent which havent landed or timed out yet
+    pub fn pending(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// check on the pending bundles -- landed ones are logged + dropped, as
+    /// are ones past the timeout. returns the number which landed.
+    pub fn poll_statuses(&self) -> usize {
+        let mut by_engine: HashMap<String, Vec<String>> = HashMap::new();
+        {
+            let mut pending = self.pending.lock().unwrap();
+            pending.retain(|id, bundle| {
+                let alive = bundle.sent_at.elapsed() < BUNDLE_TIMEOUT;
+                if !alive {
+                    info!("bundle {} didnt land", id);
+                }
+                alive
+            });
+            for (id, bundle) in pending.iter() {
+                by_engine.entry(bundle.engine.clone()).or_default().push(id.clone());
+            }
+        }
+
+        let mut landed = 0;
+        for (engine, ids) in by_engine {
+            for chunk in ids.chunks(MAX_STATUS_IDS) {
+                let request = json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "getBundleStatuses",
+                    "params": [chunk],
+                });
+                let statuses = match self.post(&engine, &request) {
+                    Ok(result) => result,
+                    Err(err) => {
+                        warn!("bundle status request failed: {}", err);
+                        continue;
+                    }
+                };
+                for status in statuses["value"].as_array().into_iter().flatten() {
+                    let id = match status["bundle_id"].as_str() {
+                        Some(id) => id,
+                        None => continue,
+                    };
+                    // err = { "Ok": null } when the txs succeeded
+                    let err = &status["err"];
+                    if err.is_null() || err.get("Ok").is_some() {
+                        info!(
+                            "bundle {} landed in slot {} ({})",
+                            id, status["slot"], status["confirmation_status"]
+                        );
+                    } else {
+                        warn!("bundle {} landed with an error: {}", id, err);
+                    }
+                    self.pending.lock().unwrap().remove(id);
+                    landed += 1;
+                }
+            }
+        }
+        landed
+    }
+}
+
+/// sendBundle request for the txs (base64 encoded)
+pub fn bundle_request(txs: &[VersionedTransaction]) -> Result<Value> {
+    let encoded = txs
+        .iter()
+        .map(|tx| Ok(BASE64.encode(bincode::serialize(tx)?)))
+        .collect::<Result<Vec<String>>>()?;
+    Ok(json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "sendBundle",
+        "params": [encoded, { "encoding": "base64" }],
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::{v0, VersionedMessage};
+    use solana_sdk::signature::{Keypair, Signer};
+
+    #[test]
+    fn tips_are_a_share_of_profit() {
+        let config = JitoConfig { tip_fraction: 0.5, max_tip_lamports: Some(1_000_000), ..JitoConfig::default() };
+        // 1 lamport = 0.25 value units
+        assert_eq!(config.tip_lamports(10_000.0, Some(0.25)), 20_000);
+        assert_eq!(config.tip_lamports(1_000_000.0, Some(0.25)), 1_000_000);
+        // too small / unpriced -> the minimum
+        assert_eq!(config.tip_lamports(1.0, Some(0.25)), MIN_TIP_LAMPORTS);
+        assert_eq!(config.tip_lamports(10_000.0, None), MIN_TIP_LAMPORTS);
+    }
+
+    #[test]
+    fn tips_rotate_and_encode() {
+        let client = JitoClient::new(JitoConfig::default()).unwrap();
+        let payer = Keypair::new();
+        let first = client.tip_ix(&payer.pubkey(), 5_000);
+        let second = client.tip_ix(&payer.pubkey(), 5_000);
+        assert_ne!(first.accounts[1].pubkey, second.accounts[1].pubkey);
+
+        let message = v0::Message::try_compile(&payer.pubkey(), &[first], &[], Hash::default()).unwrap();
+        let tx = VersionedTransaction::try_new(VersionedMessage::V0(message), &[&payer]).unwrap();
+        let request = bundle_request(&[tx.clone()]).unwrap();
+        let encoded = request["params"][0][0].as_str().unwrap();
+        let decoded: VersionedTransaction = bincode::deserialize(&BASE64.decode(encoded).unwrap()).unwrap();
+        assert_eq!(decoded, tx);
+    }
+}
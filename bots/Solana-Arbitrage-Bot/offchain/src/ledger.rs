@@ -0,0 +1,467 @@
+//! Ledger of sent arbs + what they realized
+//!
+//! every arb sent is appended (as a json line) with what it was quoted to do.
+//! reconciling fetches the landed tx (`jsonParsed`) and appends a status line
+//! for it (the file is never rewritten, so the running bot can keep
+//! appending) with:
+//! - the start mint profit, from the owner's pre/post token balances
+//! - the sol it cost (tx fee + tip), from the owner's pre/post lamports
+//! - each hop's output, from the token transfers into the owner's account of
+//!   the hop's output mint (hops out of pools which pay in native sol have none)
+//!
+//! comparing the hops' outputs to their quotes gives each venue's quote error.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_request::RpcRequest;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::utils::unix_now;
+
+// blockhashes expire after ~150 slots -- a tx still missing this long after
+// it was sent never landed
+const DROPPED_AFTER_SECS: i64 = 120;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LedgerHop {
+    pub venue: String,
+    pub pool: String,
+    pub mint_in: String,
+    pub mint_out: String,
+    // owner's account the hop pays into
+    pub destination: String,
+    pub quoted_out: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TxStatus {
+    Landed,
+    // landed but errored (eg, profit_or_revert)
+    Reverted,
+    Dropped,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Reconciled {
+    pub status: TxStatus,
+    pub slot: Option<u64>,
+    pub error: Option<String>,
+    // start mint (scaled) units
+    pub realized_profit: i128,
+    // owner's lamports spent (tx fee + tip)
+    pub cost_lamports: i64,
+    pub hop_outs: Vec<Option<u64>>,
+    // (realized - quoted) / quoted of each hop, in bps
+    pub slippage_bps: Vec<Option<f64>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LedgerEntry {
+    pub signature: String,
+    pub sent_at: i64,
+    pub owner: String,
+    // start -> ... -> start
+    pub mints: Vec<String>,
+    pub hops: Vec<LedgerHop>,
+    pub amount_in: u64,
+    pub expected_out: u64,
+    pub fee_lamports: u64,
+    pub tip_lamports: u64,
+    pub reconciled: Option<Reconciled>,
+}
+
+/// an entry's reconciliation, appended to the ledger after it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StatusLine {
+    pub signature: String,
+    pub reconciled: Reconciled,
+}
+
+// entries come first -- a status line is missing their fields
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LedgerLine {
+    Entry(LedgerEntry),
+    Status(StatusLine),
+}
+
+impl LedgerEntry {
+    pub fn expected_profit(&self) -> i128 {
+        self.expected_out as i128 - self.amount_in as i128
+    }
+
+    /// what the landed tx (a `getTransaction` result, `jsonParsed`) did
+    pub fn reconcile_tx(&self, tx: &Value) -> Reconciled {
+        let meta = &tx["meta"];
+        let slot = tx["slot"].as_u64();
+        let error = match &meta["err"] {
+            Value::Null => None,
+            err => Some(err.to_string()),
+        };
+        let status = if error.is_some() { TxStatus::Reverted } else { TxStatus::Landed };
+
+        // the owner pays, so it's the first account
+        let lamports = |key: &str| meta[key][0].as_i64().unwrap_or(0);
+        let cost_lamports = lamports("preBalances") - lamports("postBalances");
+
+        let start_mint = &self.mints[0];
+        let balance = |key: &str| -> i128 {
+            meta[key]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|b| b["owner"] == self.owner.as_str() && b["mint"] == start_mint.as_str())
+                .filter_map(|b| b["uiTokenAmount"]["amount"].as_str()?.parse::<i128>().ok())
+                .sum()
+        };
+        let realized_profit = balance("postTokenBalances") - balance("preTokenBalances");
+
+        let received = if status == TxStatus::Landed { token_transfers_in(tx) } else { HashMap::new() };
+        let hop_outs: Vec<Option<u64>> = self
+            .hops
+            .iter()
+            .map(|hop| received.get(&hop.destination.as_str()).copied())
+            .collect();
+        let slippage_bps = self
+            .hops
+            .iter()
+            .zip(hop_outs.iter())
+            .map(|(hop, out)| match out {
+                Some(out) if hop.quoted_out > 0 => {
+                    Some((*out as f64 - hop.quoted_out as f64) / hop.quoted_out as f64 * 10_000.0)
+                }
+                _ => None,
+            })
+            .collect();
+
+        Reconciled { status, slot, error, realized_profit, cost_lamports, hop_outs, slippage_bps }
+    }
+}
+
+/// token account -> amount the tx transferred into it (top-level + inner ixs)
+fn token_transfers_in(tx: &Value) -> HashMap<&str, u64> {
+    let outer = tx["transaction"]["message"]["instructions"].as_array().into_iter().flatten();
+    let inner = tx["meta"]["innerInstructions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|ixs| ixs["instructions"].as_array().into_iter().flatten());
+
+    let mut received = HashMap::new();
+    for ix in outer.chain(inner) {
+        let parsed = &ix["parsed"];
+        let info = &parsed["info"];
+        let amount = match parsed["type"].as_str() {
+            Some("transfer") => info["amount"].as_str(),
+            Some("transferChecked") | Some("transferCheckedWithFee") => info["tokenAmount"]["amount"].as_str(),
+            _ => None,
+        };
+        let (destination, amount) = match (info["destination"].as_str(), amount.and_then(|a| a.parse::<u64>().ok())) {
+            (Some(destination), Some(amount)) => (destination, amount),
+            _ => continue,
+        };
+        // token-2022 fees are withheld in the destination
+        let fee = info["feeAmount"]["amount"].as_str().and_then(|a| a.parse::<u64>().ok()).unwrap_or(0);
+        *received.entry(destination).or_insert(0) += amount.saturating_sub(fee);
+    }
+    received
+}
+
+/// json lines file of every arb sent
+#[derive(Debug, Clone)]
+pub struct Ledger {
+    pub path: String,
+}
+
+impl Ledger {
+    pub fn new(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+
+    // one write per line (appends dont interleave)
+    fn append<T: Serialize>(&self, line: &T) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("opening ledger {}", self.path))?;
+        let line = format!("{}\n", serde_json::to_string(line)?);
+        file.write_all(line.as_bytes()).with_context(|| format!("writing ledger {}", self.path))
+    }
+
+    pub fn record(&self, entry: &LedgerEntry) -> Result<()> {
+        self.append(entry)
+    }
+
+    pub fn record_status(&self, status: &StatusLine) -> Result<()> {
+        self.append(status)
+    }
+
+    /// the entries, with their status lines applied
+    pub fn load(&self) -> Result<Vec<LedgerEntry>> {
+        if !Path::new(&self.path).exists() {
+            return Ok(vec![]);
+        }
+        let contents = std::fs::read_to_string(&self.path).with_context(|| format!("reading ledger {}", self.path))?;
+        let mut entries: Vec<LedgerEntry> = vec![];
+        let mut by_signature = HashMap::new();
+        for (i, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let line = serde_json::from_str(line)
+                .with_context(|| format!("parsing line {} of ledger {}", i + 1, self.path))?;
+            match line {
+                LedgerLine::Entry(entry) => {
+                    by_signature.insert(entry.signature.clone(), entries.len());
+                    entries.push(entry);
+                }
+                LedgerLine::Status(status) => {
+                    if let Some(idx) = by_signature.get(&status.signature) {
+                        entries[*idx].reconciled = Some(status.reconciled);
+                    }
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// reconciles the entries which havent been yet -- returns how many were
+    pub fn reconcile(&self, connection: &RpcClient) -> Result<usize> {
+        let entries = self.load()?;
+        let mut reconciled = 0;
+        for entry in entries.iter().filter(|entry| entry.reconciled.is_none()) {
+            let tx: Value = connection.send(
+                RpcRequest::GetTransaction,
+                json!([entry.signature, {
+                    "encoding": "jsonParsed",
+                    "commitment": "confirmed",
+                    "maxSupportedTransactionVersion": 0,
+                }]),
+            )?;
+            let status = if !tx.is_null() {
+                entry.reconcile_tx(&tx)
+            } else if unix_now() - entry.sent_at > DROPPED_AFTER_SECS {
+                Reconciled {
+                    status: TxStatus::Dropped,
+                    slot: None,
+                    error: None,
+                    realized_profit: 0,
+                    cost_lamports: 0,
+                    hop_outs: vec![None; entry.hops.len()],
+                    slippage_bps: vec![None; entry.hops.len()],
+                }
+            } else {
+                continue;
+            };
+            self.record_status(&StatusLine { signature: entry.signature.clone(), reconciled: status })?;
+            reconciled += 1;
+        }
+        Ok(reconciled)
+    }
+}
+
+/// quote error of one venue's landed hops
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VenueStats {
+    pub hops: usize,
+    pub mean_bps: f64,
+    pub mean_abs_bps: f64,
+    pub worst_bps: f64,
+}
+
+/// venue -> its quote error (over the hops with a realized output)
+pub fn quote_error_stats(entries: &[LedgerEntry]) -> BTreeMap<String, VenueStats> {
+    let mut errors: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for entry in entries {
+        let reconciled = match &entry.reconciled {
+            Some(reconciled) => reconciled,
+            None => continue,
+        };
+        for (hop, bps) in entry.hops.iter().zip(reconciled.slippage_bps.iter()) {
+            if let Some(bps) = bps {
+                errors.entry(hop.venue.clone()).or_default().push(*bps);
+            }
+        }
+    }
+    errors
+        .into_iter()
+        .map(|(venue, bps)| {
+            let n = bps.len() as f64;
+            let stats = VenueStats {
+                hops: bps.len(),
+                mean_bps: bps.iter().sum::<f64>() / n,
+                mean_abs_bps: bps.iter().map(|b| b.abs()).sum::<f64>() / n,
+                worst_bps: bps.iter().cloned().fold(f64::INFINITY, f64::min),
+            };
+            (venue, stats)
+        })
+        .collect()
+}
+
+/// totals over the reconciled entries
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LedgerSummary {
+    pub sent: usize,
+    pub landed: usize,
+    pub reverted: usize,
+    pub dropped: usize,
+    pub pending: usize,
+    pub cost_lamports: i64,
+    // start mint -> (expected, realized) profit of the landed arbs
+    pub profits: BTreeMap<String, (i128, i128)>,
+    pub venues: BTreeMap<String, VenueStats>,
+}
+
+impl LedgerSummary {
+    pub fn new(entries: &[LedgerEntry]) -> Self {
+        let mut summary = Self { sent: entries.len(), venues: quote_error_stats(entries), ..Self::default() };
+        for entry in entries {
+            let reconciled = match &entry.reconciled {
+                Some(reconciled) => reconciled,
+                None => {
+                    summary.pending += 1;
+                    continue;
+                }
+            };
+            summary.cost_lamports += reconciled.cost_lamports;
+            match reconciled.status {
+                TxStatus::Landed => {
+                    summary.landed += 1;
+                    let profits = summary.profits.entry(entry.mints[0].clone()).or_default();
+                    profits.0 += entry.expected_profit();
+                    profits.1 += reconciled.realized_profit;
+                }
+                TxStatus::Reverted => summary.reverted += 1,
+                TxStatus::Dropped => summary.dropped += 1,
+            }
+        }
+        summary
+    }
+}
+
+impl fmt::Display for LedgerSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} sent: {} landed, {} reverted, {} dropped, {} pending ({} lamports spent)",
+            self.sent, self.landed, self.reverted, self.dropped, self.pending, self.cost_lamports
+        )?;
+        for (mint, (expected, realized)) in &self.profits {
+            writeln!(f, "  {}: expected {:+} realized {:+}", mint, expected, realized)?;
+        }
+        writeln!(f, "quote error by venue (bps):")?;
+        for (venue, stats) in &self.venues {
+            writeln!(
+                f,
+                "  {:<16} {:>5} hops  mean {:+8.1}  mean abs {:8.1}  worst {:+8.1}",
+                venue, stats.hops, stats.mean_bps, stats.mean_abs_bps, stats.worst_bps
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> LedgerEntry {
+        let hop = |venue: &str, mint_in: &str, mint_out: &str, destination: &str, quoted_out| LedgerHop {
+            venue: venue.to_string(),
+            pool: format!("{}-pool", venue),
+            mint_in: mint_in.to_string(),
+            mint_out: mint_out.to_string(),
+            destination: destination.to_string(),
+            quoted_out,
+        };
+        LedgerEntry {
+            signature: "sig".to_string(),
+            sent_at: 0,
+            owner: "owner".to_string(),
+            mints: vec!["usdc".to_string(), "sol".to_string(), "usdc".to_string()],
+            hops: vec![
+                hop("Orca", "usdc", "sol", "owner-sol", 10_000),
+                hop("Phoenix", "sol", "usdc", "owner-usdc", 1_010_000),
+            ],
+            amount_in: 1_000_000,
+            expected_out: 1_010_000,
+            fee_lamports: 5_000,
+            tip_lamports: 10_000,
+            reconciled: None,
+        }
+    }
+
+    #[test]
+    fn landed_txs_reconcile_against_their_quotes() {
+        let token_balance = |amount: &str| {
+            json!([{ "accountIndex": 1, "mint": "usdc", "owner": "owner", "uiTokenAmount": { "amount": amount } }])
+        };
+        let tx = json!({
+            "slot": 42,
+            "meta": {
+                "err": null,
+                "preBalances": [1_000_000_000_u64],
+                "postBalances": [999_985_000_u64],
+                "preTokenBalances": token_balance("5000000"),
+                "postTokenBalances": token_balance("5005000"),
+                "innerInstructions": [
+                    { "index": 1, "instructions": [
+                        { "parsed": { "type": "transfer", "info": { "destination": "orca-vault", "amount": "1000000" } } },
+                        { "parsed": { "type": "transfer", "info": { "destination": "owner-sol", "amount": "9950" } } },
+                    ] },
+                    { "index": 2, "instructions": [
+                        { "parsed": { "type": "transferChecked", "info": {
+                            "destination": "owner-usdc", "tokenAmount": { "amount": "1005000" } } } },
+                    ] },
+                ],
+            },
+            "transaction": { "message": { "instructions": [] } },
+        });
+
+        let mut entry = entry();
+        let reconciled = entry.reconcile_tx(&tx);
+        assert_eq!(reconciled.status, TxStatus::Landed);
+        assert_eq!(reconciled.slot, Some(42));
+        assert_eq!(reconciled.realized_profit, 5_000);
+        assert_eq!(reconciled.cost_lamports, 15_000);
+        assert_eq!(reconciled.hop_outs, vec![Some(9_950), Some(1_005_000)]);
+        assert_eq!(reconciled.slippage_bps[0], Some(-50.0));
+
+        entry.reconciled = Some(reconciled);
+        let summary = LedgerSummary::new(&[entry.clone(), LedgerEntry { reconciled: None, ..entry }]);
+        assert_eq!((summary.landed, summary.pending), (1, 1));
+        assert_eq!(summary.profits["usdc"], (10_000, 5_000));
+        assert_eq!(summary.venues["Orca"].hops, 1);
+        assert_eq!(summary.venues["Orca"].worst_bps, -50.0);
+    }
+
+    #[test]
+    fn entries_round_trip_through_the_file() {
+        let path = std::env::temp_dir().join(format!("ledger-{}.jsonl", unix_now() ^ std::process::id() as i64));
+        let ledger = Ledger::new(path.to_str().unwrap());
+        ledger.record(&entry()).unwrap();
+        let reconciled = Reconciled {
+            status: TxStatus::Dropped,
+            slot: None,
+            error: None,
+            realized_profit: 0,
+            cost_lamports: 0,
+            hop_outs: vec![None; 2],
+            slippage_bps: vec![None; 2],
+        };
+        ledger.record_status(&StatusLine { signature: "sig".to_string(), reconciled: reconciled.clone() }).unwrap();
+        // the bot appending while the ledger is reconciled
+        ledger.record(&LedgerEntry { signature: "sig2".to_string(), ..entry() }).unwrap();
+        let entries = ledger.load().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], LedgerEntry { reconciled: Some(reconciled), ..entry() });
+        assert_eq!(entries[1], LedgerEntry { signature: "sig2".to_string(), ..entry() });
+    }
+}
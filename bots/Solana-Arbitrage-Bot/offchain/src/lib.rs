@@ -7,6 +7,20 @@ pub mod pool;
 pub mod constants; 
 pub mod tests;
 pub mod pools; 
+pub mod search;
+pub mod sizing;
+pub mod refresh;
+pub mod config;
+pub mod lookup;
+pub mod jito;
+pub mod compute;
+pub mod svm;
+pub mod snapshot;
+pub mod discovery;
+pub mod mints;
+pub mod ledger;
+pub mod dedup;
+pub mod swap_fixture;
 
 #[macro_use]
 extern crate lazy_static;
@@ -0,0 +1,279 @@
+//! Address lookup tables for the v0 arbitrage txs
+//!
+//! the accounts a route touches (pools, vaults, programs, the owner's atas)
+//! are kept in lookup tables owned by the arb keypair, so each one costs a
+//! 1 byte index in the message instead of a 32 byte key. tables are created
+//! + extended on demand and their addresses cached in a json file so later
+//! runs reuse them instead of paying rent for new ones.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::{Keypair, Signer};
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use solana_address_lookup_table_program::instruction::{create_lookup_table, extend_lookup_table};
+use solana_address_lookup_table_program::state::AddressLookupTable;
+use solana_sdk::address_lookup_table_account::AddressLookupTableAccount;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::{v0, VersionedMessage};
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::transaction::{Transaction, VersionedTransaction};
+
+pub const MAX_TABLE_ADDRESSES: usize = 256;
+// each address is 32 bytes of the packet -- leave room for the rest of the tx
+const EXTEND_CHUNK: usize = 20;
+// a tx can lock at most this many accounts (static keys + looked up ones)
+pub const MAX_TX_ACCOUNTS: usize = 64;
+const WARMUP_POLL_MS: u64 = 400;
+
+pub struct LookupTables {
+    pub path: String,
+    pub tables: Vec<AddressLookupTableAccount>,
+    // tables we can extend (the others are only read)
+    owned: HashSet<Pubkey>,
+    // route accounts which werent in any table when the route was compiled
+    pending: Mutex<HashSet<Pubkey>>,
+}
+
+impl LookupTables {
+    /// the tables cached at `path` (a missing file = no tables yet)
+    pub fn load(connection: &RpcClient, path: &str, authority: &Pubkey) -> Result<Self> {
+        let keys: Vec<Pubkey> = match std::fs::read_to_string(path) {
+            Ok(json) => {
+                let keys: Vec<String> = serde_json::from_str(&json)
+                    .with_context(|| format!("parsing lookup tables {}", path))?;
+                keys.iter()
+                    .map(|key| Pubkey::from_str(key).map_err(|_| anyhow!("invalid lookup table: {}", key)))
+                    .collect::<Result<_>>()?
+            }
+            Err(_) => vec![],
+        };
+        let mut tables = Self::empty(path);
+        tables.fetch(connection, &keys, authority)?;
+        Ok(tables)
+    }
+
+    /// no tables yet (the first extend creates them)
+    pub fn empty(path: &str) -> Self {
+        LookupTables {
+            path: path.to_string(),
+            tables: vec![],
+            owned: HashSet::new(),
+            pending: Mutex::new(HashSet::new()),
+        }
+    }
+
+    // (re)load the given tables from the chain
+    fn fetch(&mut self, connection: &RpcClient, keys: &[Pubkey], authority: &Pubkey) -> Result<()> {
+        let accounts = connection.get_multiple_accounts(keys)?;
+        let mut tables = vec![];
+        self.owned.clear();
+        for (key, account) in keys.iter().zip(accounts) {
+            let account = match account {
+                Some(account) => account,
+                None => {
+                    warn!("lookup table {} doesnt exist -- dropping it", key);
+                    continue;
+                }
+            };
+            let table = AddressLookupTable::deserialize(&account.data)
+                .map_err(|err| anyhow!("lookup table {}: {}", key, err))?;
+            // deactivated (or frozen) tables cant be extended
+            if table.meta.authority == Some(*authority) && table.meta.deactivation_slot == u64::MAX {
+                self.owned.insert(*key);
+            }
+            tables.push(AddressLookupTableAccount {
+                key: *key,
+                addresses: table.addresses.to_vec(),
+            });
+        }
+        self.tables = tables;
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        let keys: Vec<String> = self.tables.iter().map(|table| table.key.to_string()).collect();
+        std::fs::write(&self.path, serde_json::to_string_pretty(&keys)?)
+            .with_context(|| format!("writing lookup tables {}", self.path))
+    }
+
+    pub fn contains(&self, address: &Pubkey) -> bool {
+        self.tables.iter().any(|table| table.addresses.contains(address))
+    }
+
+    /// remember route accounts which arent in a table yet (extended later)
+    pub fn note_missing(&self, ixs: &[Instruction], payer: &Pubkey) {
+        let mut pending = self.pending.lock().unwrap();
+        for key in route_accounts(ixs, payer) {
+            if !self.contains(&key) {
+                pending.insert(key);
+            }
+        }
+    }
+
+    /// extend the tables with the accounts routes were missing
+    pub fn flush_pending(&mut self, connection: &RpcClient, owner: &Keypair) -> Result<usize> {
+        let pending: Vec<Pubkey> = self.pending.lock().unwrap().drain().collect();
+        self.extend(connection, owner, &pending)
+    }
+
+    /// add every address not already in a table -- filling our tables before
+    /// creating new ones -- and wait until the new entries can be used
+    pub fn extend(&mut self, connection: &RpcClient, owner: &Keypair, addresses: &[Pubkey]) -> Result<usize> {
+        let mut missing = vec![];
+        for address in addresses {
+            if !self.contains(address) && !missing.contains(address) {
+                missing.push(*address);
+            }
+        }
+        if missing.is_empty() {
+            return Ok(0);
+        }
+
+        let authority = owner.pubkey();
+        let mut keys: Vec<Pubkey> = self.tables.iter().map(|table| table.key).collect();
+        let mut remaining = &missing[..];
+        // room left in the tables we own
+        let mut targets: Vec<(Pubkey, usize)> = self
+            .tables
+            .iter()
+            .filter(|table| self.owned.contains(&table.key))
+            .map(|table| (table.key, MAX_TABLE_ADDRESSES - table.addresses.len()))
+            .filter(|(_, room)| *room > 0)
+            .collect();
+        while !remaining.is_empty() {
+            let (table, room) = match targets.pop() {
+                Some(target) => target,
+                None => {
+                    let recent_slot = connection.get_slot_with_commitment(CommitmentConfig::finalized())?;
+                    let (ix, table) = create_lookup_table(authority, authority, recent_slot);
+                    self.send(connection, owner, &[ix])?;
+                    info!("created lookup table {}", table);
+                    keys.push(table);
+                    (table, MAX_TABLE_ADDRESSES)
+                }
+            };
+            let (batch, rest) = remaining.split_at(room.min(remaining.len()));
+            for chunk in batch.chunks(EXTEND_CHUNK) {
+                let ix = extend_lookup_table(table, authority, Some(authority), chunk.to_vec());
+                self.send(connection, owner, &[ix])?;
+            }
+            remaining = rest;
+        }
+
+        // entries are only usable the slot after they were added
+        let extended_slot = connection.get_slot()?;
+        while connection.get_slot()? <= extended_slot {
+            std::thread::sleep(Duration::from_millis(WARMUP_POLL_MS));
+        }
+        self.fetch(connection, &keys, &authority)?;
+        self.save()?;
+        info!("added {} addresses to lookup tables", missing.len());
+        Ok(missing.len())
+    }
+
+    fn send(&self, connection: &RpcClient, owner: &Keypair, ixs: &[Instruction]) -> Result<()> {
+        let tx = Transaction::new_signed_with_payer(
+            ixs,
+            Some(&owner.pubkey()),
+            &[owner],
+            connection.get_latest_blockhash()?,
+        );
+        connection.send_and_confirm_transaction(&tx)?;
+        Ok(())
+    }
+
+    /// a signed v0 tx using the tables, or an error if it wont fit
+    pub fn compile(&self, owner: &Keypair, ixs: &[Instruction], blockhash: Hash) -> Result<VersionedTransaction> {
+        let message = v0::Message::try_compile(&owner.pubkey(), ixs, &self.tables, blockhash)?;
+        let n_accounts = message.account_keys.len()
+            + message
+                .address_table_lookups
+                .iter()
+                .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+                .sum::<usize>();
+        if n_accounts > MAX_TX_ACCOUNTS {
+            return Err(anyhow!("tx locks {} accounts (max {})", n_accounts, MAX_TX_ACCOUNTS));
+        }
+        let tx = VersionedTransaction::try_new(VersionedMessage::V0(message), &[owner])?;
+        let size = bincode::serialized_size(&tx)? as usize;
+        if size > PACKET_DATA_SIZE {
+            return Err(anyhow!("tx is {} bytes (max {})", size, PACKET_DATA_SIZE));
+        }
+        Ok(tx)
+    }
+}
+
+/// every non-signer account the ixs touch (signers must be static keys)
+pub fn route_accounts(ixs: &[Instruction], payer: &Pubkey) -> Vec<Pubkey> {
+    let mut keys = vec![];
+    for ix in ixs {
+        let metas = ix.accounts.iter().filter(|meta| !meta.is_signer).map(|meta| meta.pubkey);
+        for key in std::iter::once(ix.program_id).chain(metas) {
+            if key != *payer && !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::AccountMeta;
+
+    fn tables(addresses: Vec<Pubkey>) -> LookupTables {
+        LookupTables {
+            path: String::new(),
+            tables: vec![AddressLookupTableAccount { key: Pubkey::new_unique(), addresses }],
+            owned: HashSet::new(),
+            pending: Mutex::new(HashSet::new()),
+        }
+    }
+
+    // a hop touching `n` fresh accounts
+    fn swap_ix(owner: &Pubkey, program_id: Pubkey, n: usize) -> Instruction {
+        let mut accounts = vec![AccountMeta::new(*owner, true)];
+        accounts.extend((0..n).map(|_| AccountMeta::new(Pubkey::new_unique(), false)));
+        Instruction { program_id, accounts, data: vec![0; 24] }
+    }
+
+    #[test]
+    fn tables_fit_more_hops() {
+        let owner = Keypair::new();
+        let program_id = Pubkey::new_unique();
+        let ixs: Vec<Instruction> = (0..5).map(|_| swap_ix(&owner.pubkey(), program_id, 10)).collect();
+
+        // 51 static keys dont fit in a packet
+        let empty = tables(vec![]);
+        assert!(empty.compile(&owner, &ixs, Hash::default()).is_err());
+
+        let covered = tables(route_accounts(&ixs, &owner.pubkey()));
+        let tx = covered.compile(&owner, &ixs, Hash::default()).unwrap();
+        assert!(bincode::serialized_size(&tx).unwrap() as usize <= PACKET_DATA_SIZE);
+        // only the payer stays a static key
+        match tx.message {
+            VersionedMessage::V0(message) => assert_eq!(message.account_keys, vec![owner.pubkey()]),
+            _ => panic!("not a v0 message"),
+        }
+    }
+
+    #[test]
+    fn notes_uncovered_accounts() {
+        let owner = Keypair::new();
+        let ix = swap_ix(&owner.pubkey(), Pubkey::new_unique(), 2);
+        let covered = ix.accounts[1].pubkey;
+        let tables = tables(vec![covered, ix.program_id]);
+        tables.note_missing(&[ix.clone()], &owner.pubkey());
+        let pending = tables.pending.lock().unwrap();
+        assert_eq!(*pending, HashSet::from([ix.accounts[2].pubkey]));
+    }
+}
@@ -1,37 +1,112 @@
 use anchor_client::solana_client::rpc_client::RpcClient;
 use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
 use anchor_client::solana_sdk::pubkey::Pubkey;
-use anchor_client::solana_sdk::signature::read_keypair_file;
 use anchor_client::solana_sdk::signature::{Keypair, Signer};
 
-use anchor_client::{Client, Cluster};
+use anchor_client::Client;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::rc::Rc;
-use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
-use std::borrow::Borrow;
 use std::vec;
 
 use clap::Parser;
 
 use log::{debug, info, warn};
-use solana_sdk::account::Account;
 
 use client::arb::*;
 use client::constants::*;
-use client::pool::{pool_factory, PoolDir, PoolOperations, PoolType};
+use client::config::Config;
+use client::dedup::SentArbs;
+use client::jito::JitoClient;
+use client::ledger::Ledger;
+use client::lookup::LookupTables;
+use client::mints::MintRegistry;
+use client::pool::{pool_factory, PoolDir};
+use client::refresh::{AccountSource, PoolRefresher};
 use client::serialize::token::unpack_token_account;
+use client::snapshot::{Recorder, Snapshot};
+use client::svm::Simulator;
 use client::utils::{
-    derive_token_address, read_json_dir, PoolEdge, PoolGraph, PoolIndex, PoolQuote,
+    pin_unix_time, read_json_dir, unix_now, PoolEdge, PoolGraph, PoolIndex, PoolQuote,
 };
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 pub struct Args {
+    /// toml config (default = ./arb.toml if it exists)
+    #[clap(long)]
+    pub config: Option<String>,
+    /// localnet | devnet | testnet | mainnet | any http(s) url
     #[clap(short, long)]
-    pub cluster: String,
+    pub cluster: Option<String>,
+    #[clap(long)]
+    pub rpc_url: Option<String>,
+    #[clap(long)]
+    pub send_rpc_url: Option<String>,
+    #[clap(short, long)]
+    pub keypair: Option<String>,
+    /// repeat for each start mint
+    #[clap(long)]
+    pub start_mint: Vec<String>,
+    #[clap(long)]
+    pub value_mint: Option<String>,
+    #[clap(long)]
+    pub max_hops: Option<usize>,
+    #[clap(long)]
+    pub min_profit: Option<u64>,
+    /// simulate txs instead of sending them
+    #[clap(long)]
+    pub dry_run: bool,
+    /// keep polling pool accounts + re-searching the cycles that changed
+    #[clap(short, long)]
+    pub watch: bool,
+    #[clap(long, default_value = "400")]
+    pub poll_ms: u64,
+    /// search threads (default = all cores)
+    #[clap(long)]
+    pub threads: Option<usize>,
+    /// save the accounts each search read to <dir>/<slot>
+    #[clap(long)]
+    pub record: Option<String>,
+    /// search a recorded snapshot dir + print the arbs found (as json lines)
+    #[clap(long)]
+    pub replay: Option<String>,
+}
+
+// cli flags override the config file + env
+fn apply_args(config: &mut Config, args: &Args) {
+    if let Some(cluster) = &args.cluster {
+        config.cluster = cluster.clone();
+    }
+    if let Some(url) = &args.rpc_url {
+        config.rpc_url = Some(url.clone());
+    }
+    if let Some(url) = &args.send_rpc_url {
+        config.send_rpc_url = Some(url.clone());
+    }
+    if let Some(path) = &args.keypair {
+        config.keypair_path = Some(path.clone());
+        config.keypair_env = None;
+    }
+    if !args.start_mint.is_empty() {
+        config.start_mints = args.start_mint.clone();
+    }
+    if let Some(mint) = &args.value_mint {
+        config.value_mint = Some(mint.clone());
+    }
+    if let Some(max_hops) = args.max_hops {
+        config.max_hops = max_hops;
+    }
+    if let Some(min_profit) = args.min_profit {
+        config.min_profit = min_profit;
+    }
+    if args.dry_run {
+        config.dry_run = true;
+    }
 }
 
 fn add_pool_to_graph<'a>(
@@ -49,40 +124,55 @@ fn add_pool_to_graph<'a>(
     quotes.push(quote.clone());
 }
 
+// save what the recorder has read so far as <dir>/<slot> (the slot the
+// last read was served at)
+fn save_snapshot(recorder: &Recorder, dir: &str) {
+    let slot = match recorder.slot() {
+        Some(slot) => slot,
+        None => {
+            warn!("the reads didnt report a slot -- not recording");
+            return;
+        }
+    };
+    let path = format!("{}/{}", dir, slot);
+    match recorder.snapshot(slot, unix_now()).save(&path) {
+        Ok(()) => info!("recorded snapshot {}", path),
+        Err(err) => warn!("failed to record snapshot {}: {:?}", path, err),
+    }
+}
+
 fn main() {
     let args = Args::parse();
-    let cluster = match args.cluster.as_str() {
-        "localnet" => Cluster::Localnet,
-        "mainnet" => Cluster::Mainnet,
-        _ => panic!("invalid cluster type"),
-    };
 
     env_logger::init();
 
-    let owner_kp_path = match cluster {
-        Cluster::Localnet => "../../mainnet_fork/localnet_owner.key",
-        Cluster::Mainnet => {
-            "/Users/edgar/.config/solana/uwuU3qc2RwN6CpzfBAhg6wAxiEx138jy5wB3Xvx18Rw.json"
-        }
-        _ => panic!("shouldnt get here"),
-    };
+    let mut config = Config::load(args.config.as_deref()).unwrap();
+    apply_args(&mut config, &args);
+    config.validate().unwrap();
+    debug!("config: {:#?}", config);
+
+    let cluster = config.cluster().unwrap();
+
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .unwrap();
+    }
 
     // ** setup RPC connection
-    let connection_url = match cluster {
-        Cluster::Mainnet => {
-            "https://mainnet.rpc.jito.wtf/?access-token=746bee55-1b6f-4130-8347-5e1ea373333f"
-        }
-        _ => cluster.url(),
-    };
+    let connection_url = config.rpc_url().unwrap();
+    let send_tx_url = config.send_rpc_url().unwrap();
     info!("using connection: {}", connection_url);
 
     let connection = RpcClient::new_with_commitment(connection_url, CommitmentConfig::confirmed());
     let send_tx_connection =
-        RpcClient::new_with_commitment(cluster.url(), CommitmentConfig::confirmed());
+        RpcClient::new_with_commitment(send_tx_url, CommitmentConfig::confirmed());
 
     // setup anchor things
-    let owner = read_keypair_file(owner_kp_path.clone()).unwrap();
-    let rc_owner = Rc::new(owner);
+    let owner = Arc::new(config.keypair().unwrap());
+    // anchor's client wants an Rc signer -- give it its own copy
+    let rc_owner = Rc::new(Keypair::from_bytes(&owner.to_bytes()).unwrap());
     let provider = Client::new_with_options(
         cluster.clone(),
         rc_owner.clone(),
@@ -91,36 +181,14 @@ fn main() {
     let program = provider.program(*ARB_PROGRAM_ID);
 
     // ** define pool JSONs
-    let mut pool_dirs = vec![];
-
-    let orca_dir = PoolDir {
-        tipe: PoolType::OrcaPoolType,
-        dir_path: "../pools/orca".to_string(),
-    };
-    pool_dirs.push(orca_dir);
-
-    let mercurial_dir = PoolDir {
-        tipe: PoolType::MercurialPoolType,
-        dir_path: "../pools/mercurial".to_string(),
-    };
-    pool_dirs.push(mercurial_dir);
-
-    let saber_dir = PoolDir {
-        tipe: PoolType::SaberPoolType,
-        dir_path: "../pools/saber/".to_string(),
-    };
-    pool_dirs.push(saber_dir);
+    let pool_dirs: Vec<PoolDir> = config.pools.iter().filter(|d| d.enabled).cloned().collect();
 
     // ** json pool -> pool object
     let mut token_mints = vec![];
     let mut pools = vec![];
-
-    let mut update_pks = vec![];
-    let mut update_pks_lengths = vec![];
     let mut all_mint_idxs = vec![];
 
     let mut mint2idx = HashMap::new();
-    let mut graph_edges = vec![];
 
     info!("extracting pool + mints...");
     for pool_dir in pool_dirs {
@@ -129,7 +197,13 @@ fn main() {
 
         for pool_path in pool_paths {
             let json_str = std::fs::read_to_string(&pool_path).unwrap();
-            let pool = pool_factory(&pool_dir.tipe, &json_str);
+            let pool = match pool_factory(&pool_dir.tipe, &json_str) {
+                Ok(pool) => pool,
+                Err(err) => {
+                    warn!("skipping pool {:?}: {}", pool_path, err);
+                    continue;
+                }
+            };
 
             let pool_mints = pool.get_mints();
             if pool_mints.len() != 2 {
@@ -147,128 +221,242 @@ fn main() {
                     idx = token_mints.len();
                     mint2idx.insert(mint, idx);
                     token_mints.push(mint);
-                    // graph_edges[idx] will always exist :)
-                    graph_edges.push(HashSet::new());
                 } else {
                     idx = *mint2idx.get(&mint).unwrap();
                 }
                 mint_idxs.push(idx);
             }
 
-            // get accounts which need account info to be updated (e.g. pool src/dst amounts for xy=k)
-            let update_accounts = pool.get_update_accounts();
-            update_pks_lengths.push(update_accounts.len());
-            update_pks.push(update_accounts);
-
-            let mint0_idx = mint_idxs[0];
-            let mint1_idx = mint_idxs[1];
-
-            all_mint_idxs.push(mint0_idx);
-            all_mint_idxs.push(mint1_idx);
-
-            // record graph edges if they dont already exist
-            if !graph_edges[mint0_idx].contains(&mint1_idx) {
-                graph_edges[mint0_idx].insert(mint1_idx);
-            }
-            if !graph_edges[mint1_idx].contains(&mint0_idx) {
-                graph_edges[mint1_idx].insert(mint0_idx);
-            }
+            all_mint_idxs.push(mint_idxs[0]);
+            all_mint_idxs.push(mint_idxs[1]);
 
             pools.push(pool);
         }
     }
-    let mut update_pks = update_pks.concat();
 
     info!("added {:?} mints", token_mints.len());
     info!("added {:?} pools", pools.len());
 
-    // !
-    let usdc_mint = Pubkey::from_str("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap();
-    let start_mint = usdc_mint;
-    let start_mint_idx = *mint2idx.get(&start_mint).unwrap();
+    info!("setting up exchange graph...");
+    let mut graph = PoolGraph::new();
+    let mut pool_quotes = vec![];
 
-    let owner: &Keypair = rc_owner.borrow();
-    let owner_start_addr = derive_token_address(&owner.pubkey(), &start_mint);
+    for (pool_count, pool) in pools.into_iter().enumerate() {
+        let idxs = &all_mint_idxs[pool_count * 2..(pool_count + 1) * 2].to_vec();
+        let idx0 = PoolIndex(idxs[0]);
+        let idx1 = PoolIndex(idxs[1]);
 
-    // slide it in there
-    update_pks.push(owner_start_addr);
+        let pool_ptr = PoolQuote::new(pool);
+        add_pool_to_graph(&mut graph, idx0, idx1, &pool_ptr);
+        add_pool_to_graph(&mut graph, idx1, idx0, &pool_ptr);
 
-    info!("getting pool amounts...");
-    let mut update_accounts = vec![];
-    for token_addr_chunk in update_pks.chunks(99) {
-        let accounts = connection.get_multiple_accounts(token_addr_chunk).unwrap();
-        update_accounts.push(accounts);
+        pool_quotes.push(pool_ptr);
     }
-    let mut update_accounts = update_accounts
-        .concat()
-        .into_iter()
-        .filter(|s| s.is_some())
-        .collect::<Vec<Option<Account>>>();
-
-    info!("update accounts is {:?}", update_accounts.len());
-    // slide it out here
-    println!("accounts: {:#?}", update_accounts.clone());
-    let init_token_acc = update_accounts.pop().unwrap().unwrap();
-    let init_token_balance = unpack_token_account(&init_token_acc.data).amount as u128;
-    info!(
-        "init token acc: {:?}, balance: {:#}",
-        init_token_acc, init_token_balance
-    );
-    info!("starting balance = {}", init_token_balance);
-
-    info!("setting up exchange graph...");
-    let mut graph = PoolGraph::new();
-    let mut pool_count = 0;
-    let mut account_ptr = 0;
 
-    for pool in pools.into_iter() {
-        // update pool
-        let length = update_pks_lengths[pool_count];
-        let _account_slice = &update_accounts[account_ptr..account_ptr + length].to_vec();
-        account_ptr += length;
+    // offline: pools + balances come from the dumped accounts, arbs run in a local bank
+    let simulator = config.svm.as_ref().map(|svm| Simulator::load(svm).unwrap());
+    let dumped_accounts = simulator.as_ref().map(|simulator| simulator.accounts().clone());
+    // replays read a recorded snapshot, at the time it was recorded
+    let snapshot = args.replay.as_ref().map(|dir| Snapshot::load(dir).unwrap());
+    if let Some(snapshot) = &snapshot {
+        info!("replaying slot {} ({} accounts)", snapshot.slot, snapshot.accounts.len());
+        pin_unix_time(Some(snapshot.unix_timestamp));
+    } else if simulator.is_some() {
+        // the dumped accounts dont move -- quote them (and run the bank) at one time
+        pin_unix_time(Some(unix_now()));
+    }
+    let source: &dyn AccountSource = match (&snapshot, &dumped_accounts) {
+        (Some(snapshot), _) => snapshot,
+        (None, Some(accounts)) => accounts,
+        (None, None) => &connection,
+    };
+    let recorder = args.record.as_ref().map(|_| Recorder::new(source));
+    let source: &dyn AccountSource = match &recorder {
+        Some(recorder) => recorder,
+        None => source,
+    };
 
-        // pool.set_update_accounts(*account_slice);
+    // get accounts which need account info to be updated (e.g. pool src/dst amounts for xy=k)
+    info!("getting pool amounts...");
+    let mut refresher = PoolRefresher::new(pool_quotes, cluster.clone());
+    refresher.refresh(source).unwrap();
+    info!("update accounts is {:?}", refresher.index.pubkeys.len());
+
+    // token programs + transfer fees of every mint (before any ata is derived)
+    let offline = simulator.is_some() || snapshot.is_some();
+    let epoch = if offline { None } else { Some(connection.get_epoch_info().unwrap().epoch) };
+    let registry = MintRegistry::load(source, &token_mints, epoch).unwrap();
+    let token_2022_mints = registry.mints.values().filter(|info| info.program == *TOKEN_2022_PROGRAM_ID).count();
+    info!("{} token-2022 mints", token_2022_mints);
+
+    // ** inventory: every start mint we hold + can reach through a pool
+    let mut start_mint_idxs = vec![];
+    for start_mint in config.start_mints().unwrap() {
+        match mint2idx.get(&start_mint) {
+            Some(idx) => start_mint_idxs.push(*idx),
+            None => warn!("start mint {} isnt in any pool -- skipping", start_mint),
+        }
+    }
+    assert!(!start_mint_idxs.is_empty(), "no start mints are in any pool");
+
+    let value_mint = config.value_mint().unwrap();
+    let value_mint_idx = *mint2idx
+        .get(&value_mint)
+        .expect("value mint isnt in any pool");
+
+    let owner_start_addrs: Vec<Pubkey> = start_mint_idxs
+        .iter()
+        .map(|idx| registry.token_address(&owner.pubkey(), &token_mints[*idx]))
+        .collect();
+    let get_inventory = || -> anyhow::Result<Inventory> {
+        let token_accs = source.get_accounts(&owner_start_addrs)?;
+        let mut inventory = Inventory::new();
+        for ((idx, addr), token_acc) in start_mint_idxs
+            .iter()
+            .zip(owner_start_addrs.iter())
+            .zip(token_accs)
+        {
+            match token_acc {
+                Some(token_acc) => {
+                    let balance = unpack_token_account(&token_acc.data).amount as u128;
+                    inventory.insert(*idx, balance);
+                }
+                None => warn!("no token account {} -- skipping its mint", addr),
+            }
+        }
+        Ok(inventory)
+    };
+    let init_inventory = get_inventory().unwrap();
+    for (idx, balance) in &init_inventory {
+        info!("starting balance of {} = {}", token_mints[*idx], balance);
+    }
 
-        // add pool to graph
-        let idxs = &all_mint_idxs[pool_count * 2..(pool_count + 1) * 2].to_vec();
-        let idx0 = PoolIndex(idxs[0]);
-        let idx1 = PoolIndex(idxs[1]);
+    if let (Some(recorder), Some(dir)) = (&recorder, &args.record) {
+        save_snapshot(recorder, dir);
+    }
 
-        let mut pool_ptr = PoolQuote::new(Rc::new(pool));
-        add_pool_to_graph(&mut graph, idx0, idx1, &mut pool_ptr.clone());
-        add_pool_to_graph(&mut graph, idx1, idx0, &mut pool_ptr);
+    let lookup_tables = if offline {
+        LookupTables::empty(&config.lookup_tables_path)
+    } else {
+        LookupTables::load(&send_tx_connection, &config.lookup_tables_path, &owner.pubkey()).unwrap()
+    };
+    info!("using {:?} lookup tables", lookup_tables.tables.len());
 
-        pool_count += 1;
+    let jito = config.jito.clone().map(|jito| JitoClient::new(jito).unwrap());
+    if let Some(jito) = &jito {
+        info!("sending bundles to {:?}", jito.config.block_engines);
     }
 
-    let arbitrager = Arbitrager {
+    let mut arbitrager = Arbitrager {
         token_mints,
-        graph_edges,
+        registry,
         graph,
         cluster,
-        owner: rc_owner,
+        max_hops: config.max_hops,
+        value_mint_idx,
+        min_swap_value: config.min_swap_amount as u128,
+        max_swap_value: config.max_swap_amount.map(|max| max as u128),
+        min_profit: config.min_profit as u128,
+        dry_run: config.dry_run,
+        lookup_tables,
+        jito,
+        compute: config.compute.clone(),
+        simulator,
+        ledger: Ledger::new(&config.ledger_path),
+        owner,
         program,
         connection: send_tx_connection,
     };
 
+    // cover every pool's swap accounts up front (dry runs only read the tables)
+    if !config.dry_run && !offline {
+        let addresses = arbitrager.route_addresses();
+        let owner = arbitrager.owner.clone();
+        arbitrager
+            .lookup_tables
+            .extend(&arbitrager.connection, &owner, &addresses)
+            .unwrap();
+    }
+
     info!("searching for arbitrages...");
-    let min_swap_amount = 10_u128.pow(6_u32); // scaled! -- 1 USDC
-    let mut swap_start_amount = init_token_balance; // scaled!
-    let mut sent_arbs = HashSet::new(); // track what arbs we did with a larger size
-
-    for _ in 0..4 {
-        arbitrager.brute_force_search(
-            start_mint_idx,
-            swap_start_amount,
-            swap_start_amount,
-            vec![start_mint_idx],
-            vec![],
-            &mut sent_arbs,
-        );
-
-        swap_start_amount /= 2; // half input amount and search again
-        if swap_start_amount < min_swap_amount {
-            break;
-        } // dont get too small
+    let mut edges = arbitrager.build_edges();
+    let cycles = arbitrager.find_cycles(&edges, &start_mint_idxs);
+    let rates = arbitrager.value_rates(&edges);
+
+    if snapshot.is_some() {
+        for arb in arbitrager.find_arbs(&cycles, &init_inventory, &rates) {
+            println!("{}", serde_json::to_string(&arbitrager.arb_record(&arb)).unwrap());
+        }
+        return;
+    }
+
+    // track what arbs we already sent (across restarts)
+    let mut sent_arbs = SentArbs::load(&config.dedup).unwrap();
+
+    // each cycle is sized between the min swap and its start mint's balance
+    arbitrager.search_cycles(&cycles, &init_inventory, &rates, &mut sent_arbs);
+
+    // the dumped accounts dont change -- one pass is all there is to see
+    if !args.watch || offline {
+        // see how the pass's bundles did before exiting (each is dropped
+        // once it lands or times out)
+        if let Some(jito) = &arbitrager.jito {
+            while jito.pending() > 0 {
+                std::thread::sleep(Duration::from_millis(args.poll_ms));
+                jito.poll_statuses();
+            }
+        }
+        return;
+    }
+
+    // ** long-running: poll the update accounts + re-search what changed
+    info!("watching pool accounts every {}ms...", args.poll_ms);
+    loop {
+        std::thread::sleep(Duration::from_millis(args.poll_ms));
+
+        let mut dirty = match refresher.refresh(source) {
+            Ok(dirty) => dirty,
+            Err(err) => {
+                warn!("account refresh failed: {:?}", err);
+                continue;
+            }
+        };
+        // a new epoch can switch mints to their newer transfer fee -- every
+        // pool's quotes change with it
+        match connection.get_epoch_info() {
+            Ok(info) if arbitrager.registry.epoch != Some(info.epoch) => {
+                info!("epoch {} -- re-quoting every pool", info.epoch);
+                arbitrager.registry.epoch = Some(info.epoch);
+                dirty = refresher.pools.clone();
+            }
+            Ok(_) => {}
+            Err(err) => warn!("epoch lookup failed: {}", err),
+        }
+        if dirty.is_empty() {
+            continue;
+        }
+
+        let cycles = arbitrager.research_dirty_cycles(&mut edges, &start_mint_idxs, &dirty);
+        if cycles.is_empty() {
+            continue;
+        }
+        let rates = arbitrager.value_rates(&edges);
+        let inventory = match get_inventory() {
+            Ok(inventory) => inventory,
+            Err(err) => {
+                warn!("inventory refresh failed: {:?}", err);
+                continue;
+            }
+        };
+        if let (Some(recorder), Some(dir)) = (&recorder, &args.record) {
+            save_snapshot(recorder, dir);
+        }
+        arbitrager.search_cycles(&cycles, &inventory, &rates, &mut sent_arbs);
+        if !config.dry_run {
+            arbitrager.extend_lookup_tables();
+        }
+        if let Some(jito) = &arbitrager.jito {
+            jito.poll_statuses();
+        }
     }
 }
@@ -0,0 +1,128 @@
+//! Per-mint token program registry
+//!
+//! token-2022 mints need their atas derived (+ created) under the token-2022
+//! program, and transfer-fee mints withhold part of every transfer -- both
+//! into the pool and back out of it. the registry is loaded from the mints'
+//! accounts and passed to wherever the owner's atas are derived or pools
+//! quoted; mints it hasnt seen are treated as legacy spl tokens.
+
+use std::collections::HashMap;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::Result;
+use log::warn;
+
+use crate::constants::*;
+use crate::error::{PoolError, PoolResult};
+use crate::refresh::AccountSource;
+use crate::serialize::token::{try_unpack_mint, Mint};
+use crate::utils::derive_token_address_with_program;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MintInfo {
+    pub program: Pubkey,
+    pub mint: Mint,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MintRegistry {
+    pub mints: HashMap<Pubkey, MintInfo>,
+    // picks between a mint's older / newer transfer fee (None = the newer,
+    // eg, for snapshots which dont record the epoch)
+    pub epoch: Option<u64>,
+}
+
+impl MintRegistry {
+    pub fn load<S: AccountSource + ?Sized>(source: &S, mints: &[Pubkey], epoch: Option<u64>) -> Result<Self> {
+        let mut registry = Self { mints: HashMap::new(), epoch };
+        for (pk, account) in mints.iter().zip(source.get_accounts(mints)?) {
+            let program = match &account {
+                Some(account) if account.owner == *TOKEN_2022_PROGRAM_ID => *TOKEN_2022_PROGRAM_ID,
+                _ => *TOKEN_PROGRAM_ID,
+            };
+            match try_unpack_mint(pk, &account) {
+                Ok(mint) => {
+                    registry.mints.insert(*pk, MintInfo { program, mint });
+                }
+                Err(err) => warn!("cant load mint {}: {}", pk, err),
+            }
+        }
+        Ok(registry)
+    }
+
+    /// the token program owning the mint (+ its token accounts)
+    pub fn program(&self, mint: &Pubkey) -> Pubkey {
+        self.mints.get(mint).map_or(*TOKEN_PROGRAM_ID, |info| info.program)
+    }
+
+    /// the owner's ata of the mint, under its token program
+    pub fn token_address(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        derive_token_address_with_program(owner, mint, &self.program(mint))
+    }
+
+    /// what's left of `amount` after the mint's transfer fee
+    pub fn after_fee(&self, mint: &Pubkey, amount: u128) -> u128 {
+        let config = match self.mints.get(mint).and_then(|info| info.mint.transfer_fee) {
+            Some(config) => config,
+            None => return amount,
+        };
+        let fee = config.at_epoch(self.epoch).fee(amount.min(u64::MAX as u128) as u64);
+        amount.saturating_sub(fee as u128)
+    }
+
+    /// errors for mints the swaps cant move
+    pub fn check_tradeable(&self, mint: &Pubkey) -> PoolResult<()> {
+        match self.mints.get(mint) {
+            Some(info) if info.mint.non_transferable => {
+                Err(PoolError::UnsupportedRoute(format!("{} is non-transferable", mint)))
+            }
+            Some(info) if info.mint.transfer_hook => {
+                Err(PoolError::UnsupportedRoute(format!("{} has a transfer hook", mint)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialize::token::{TransferFee, TransferFeeConfig};
+
+    #[test]
+    fn fees_come_out_of_registered_mints() {
+        let [legacy, fee_mint, hooked] = [0; 3].map(|_| Pubkey::new_unique());
+        let fee = TransferFee { epoch: 0, maximum_fee: u64::MAX, basis_points: 100 };
+        let registry = MintRegistry {
+            mints: HashMap::from([
+                (
+                    fee_mint,
+                    MintInfo {
+                        program: *TOKEN_2022_PROGRAM_ID,
+                        mint: Mint { transfer_fee: Some(TransferFeeConfig { older: fee, newer: fee }), ..Mint::default() },
+                    },
+                ),
+                (
+                    hooked,
+                    MintInfo {
+                        program: *TOKEN_2022_PROGRAM_ID,
+                        mint: Mint { transfer_hook: true, ..Mint::default() },
+                    },
+                ),
+            ]),
+            epoch: Some(500),
+        };
+
+        assert_eq!(registry.after_fee(&fee_mint, 10_000), 9_900);
+        assert_eq!(registry.after_fee(&legacy, 10_000), 10_000);
+        assert_eq!(registry.program(&fee_mint), *TOKEN_2022_PROGRAM_ID);
+        assert_eq!(registry.program(&legacy), *TOKEN_PROGRAM_ID);
+        let owner = Pubkey::new_unique();
+        assert_eq!(
+            registry.token_address(&owner, &fee_mint),
+            derive_token_address_with_program(&owner, &fee_mint, &TOKEN_2022_PROGRAM_ID)
+        );
+        assert!(registry.check_tradeable(&fee_mint).is_ok());
+        assert!(registry.check_tradeable(&hooked).is_err());
+    }
+}
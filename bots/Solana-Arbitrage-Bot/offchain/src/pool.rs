@@ -3,55 +3,144 @@ use anchor_client::Program;
 use solana_sdk::account::Account;
 use solana_sdk::instruction::Instruction;
 
+use crate::error::PoolResult;
 use crate::pools::*;
 use std::fmt::Debug;
 
 use anchor_client::Cluster;
+use dyn_clone::DynClone;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PoolDir {
     pub tipe: PoolType,
     pub dir_path: String,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
 }
 
-#[derive(Debug)]
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum PoolType {
+    #[serde(rename = "orca")]
     OrcaPoolType,
+    #[serde(rename = "mercurial")]
     MercurialPoolType,
+    #[serde(rename = "saber")]
     SaberPoolType,
+    #[serde(rename = "aldrin")]
     AldrinPoolType,
+    #[serde(rename = "serum")]
     SerumPoolType,
+    #[serde(rename = "raydium_amm_v4")]
+    RaydiumAmmV4PoolType,
+    #[serde(rename = "raydium_cpmm")]
+    RaydiumCpmmPoolType,
+    #[serde(rename = "raydium_clmm")]
+    RaydiumClmmPoolType,
+    #[serde(rename = "orca_whirlpool")]
+    OrcaWhirlpoolPoolType,
+    #[serde(rename = "meteora_dlmm")]
+    MeteoraDlmmPoolType,
+    #[serde(rename = "meteora_amm")]
+    MeteoraDynamicAmmPoolType,
+    #[serde(rename = "meteora_damm_v2")]
+    MeteoraDammV2PoolType,
+    #[serde(rename = "pumpswap")]
+    PumpSwapPoolType,
+    #[serde(rename = "pump_fun")]
+    PumpFunPoolType,
+    #[serde(rename = "openbook_v2")]
+    OpenBookV2PoolType,
+    #[serde(rename = "phoenix")]
+    PhoenixPoolType,
 }
 
-pub fn pool_factory(tipe: &PoolType, json_str: &String) -> Box<dyn PoolOperations> {
-    match tipe {
+pub fn pool_factory(tipe: &PoolType, json_str: &String) -> PoolResult<Box<dyn PoolOperations>> {
+    let pool: Box<dyn PoolOperations> = match tipe {
         PoolType::OrcaPoolType => {
-            let pool: OrcaPool = serde_json::from_str(json_str).unwrap();
+            let pool: OrcaPool = serde_json::from_str(json_str)?;
             Box::new(pool)
         }
         PoolType::MercurialPoolType => {
-            let pool: MercurialPool = serde_json::from_str(json_str).unwrap();
+            let pool: MercurialPool = serde_json::from_str(json_str)?;
             Box::new(pool)
         }
         PoolType::SaberPoolType => {
-            let pool: SaberPool = serde_json::from_str(json_str).unwrap();
+            let pool: SaberPool = serde_json::from_str(json_str)?;
             Box::new(pool)
         }
         PoolType::AldrinPoolType => {
-            let pool: AldrinPool = serde_json::from_str(json_str).unwrap();
+            let pool: AldrinPool = serde_json::from_str(json_str)?;
             Box::new(pool)
         }
         PoolType::SerumPoolType => {
-            let pool: SerumPool = serde_json::from_str(json_str).unwrap();
+            let pool: SerumPool = serde_json::from_str(json_str)?;
             Box::new(pool)
         }
-    }
+        PoolType::RaydiumAmmV4PoolType => {
+            let pool: RaydiumAmmV4Pool = serde_json::from_str(json_str)?;
+            Box::new(pool)
+        }
+        PoolType::RaydiumCpmmPoolType => {
+            let pool: RaydiumCpmmPool = serde_json::from_str(json_str)?;
+            Box::new(pool)
+        }
+        PoolType::RaydiumClmmPoolType => {
+            let pool: RaydiumClmmPool = serde_json::from_str(json_str)?;
+            Box::new(pool)
+        }
+        PoolType::OrcaWhirlpoolPoolType => {
+            let pool: OrcaWhirlpoolPool = serde_json::from_str(json_str)?;
+            Box::new(pool)
+        }
+        PoolType::MeteoraDlmmPoolType => {
+            let pool: MeteoraDlmmPool = serde_json::from_str(json_str)?;
+            Box::new(pool)
+        }
+        PoolType::MeteoraDynamicAmmPoolType => {
+            let pool: MeteoraDynamicAmmPool = serde_json::from_str(json_str)?;
+            Box::new(pool)
+        }
+        PoolType::MeteoraDammV2PoolType => {
+            let pool: MeteoraDammV2Pool = serde_json::from_str(json_str)?;
+            Box::new(pool)
+        }
+        PoolType::PumpSwapPoolType => {
+            let pool: PumpSwapPool = serde_json::from_str(json_str)?;
+            Box::new(pool)
+        }
+        PoolType::PumpFunPoolType => {
+            let pool: PumpFunPool = serde_json::from_str(json_str)?;
+            Box::new(pool)
+        }
+        PoolType::OpenBookV2PoolType => {
+            let pool: OpenBookV2Pool = serde_json::from_str(json_str)?;
+            Box::new(pool)
+        }
+        PoolType::PhoenixPoolType => {
+            let pool: PhoenixPool = serde_json::from_str(json_str)?;
+            Box::new(pool)
+        }
+    };
+    Ok(pool)
 }
 
-pub trait PoolOperations: Debug {
+// Send + Sync so the graph can be searched across threads,
+// DynClone so the refresher can update a copy and swap it in
+pub trait PoolOperations: Debug + DynClone + Send + Sync {
     fn get_name(&self) -> String;
+    // the pool's own account (its state / market) -- names arent unique
+    fn get_own_addr(&self) -> Pubkey;
     fn get_update_accounts(&self) -> Vec<Pubkey>;
-    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, cluster: Cluster);
+    fn set_update_accounts(
+        &mut self,
+        accounts: Vec<Option<Account>>,
+        cluster: Cluster,
+    ) -> PoolResult<()>;
 
     fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey;
     fn get_mints(&self) -> Vec<Pubkey>;
@@ -62,16 +151,26 @@ pub trait PoolOperations: Debug {
         amount_in: u128,
         mint_in: &Pubkey,
         mint_out: &Pubkey,
-    ) -> u128;
+    ) -> PoolResult<u128>;
+    // amount_in = quoted input for this hop, min_amount_out = what the next
+    // hop pulls (only used by direct swaps, the tmp program swaps read
+    // theirs from swap_state)
     fn swap_ix(
         &self,
         program: &Program,
         owner: &Pubkey,
         mint_in: &Pubkey,
         mint_out: &Pubkey,
-    ) -> Vec<Instruction>;
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>>;
+    // false = the venue's own swap ix (not wrapped by the tmp program), so
+    // it cant pass its output on through swap_state
+    fn uses_swap_state(&self) -> bool {
+        true
+    }
 
     fn can_trade(&self, mint_in: &Pubkey, mint_out: &Pubkey) -> bool; // used for tests
 }
 
-// clone_trait_object!(PoolOperations);
+dyn_clone::clone_trait_object!(PoolOperations);
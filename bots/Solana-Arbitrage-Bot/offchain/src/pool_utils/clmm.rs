@@ -0,0 +1,388 @@
+//! Concentrated liquidity (uniswap v3 style) math, shared by raydium clmm
+//! and orca whirlpools: sqrt prices are Q64.64, fees are out of 1e6 and
+//! liquidity changes by `liquidity_net` whenever an initialized tick is crossed.
+
+use spl_math::uint::U256;
+
+pub const MIN_TICK: i32 = -443636;
+pub const MAX_TICK: i32 = 443636;
+pub const MIN_SQRT_PRICE_X64: u128 = 4295048016;
+pub const MAX_SQRT_PRICE_X64: u128 = 79226673521066979257578248091;
+
+pub const FEE_RATE_DENOMINATOR: u128 = 1_000_000;
+
+lazy_static! {
+    // sqrt(1.0001)^-(2^i) as Q128.128 (uniswap's TickMath)
+    static ref TICK_RATIOS: Vec<U256> = [
+        "fffcb933bd6fad37aa2d162d1a594001",
+        "fff97272373d413259a46990580e213a",
+        "fff2e50f5f656932ef12357cf3c7fdcc",
+        "ffe5caca7e10e4e61c3624eaa0941cd0",
+        "ffcb9843d60f6159c9db58835c926644",
+        "ff973b41fa98c081472e6896dfb254c0",
+        "ff2ea16466c96a3843ec78b326b52861",
+        "fe5dee046a99a2a811c461f1969c3053",
+        "fcbe86c7900a88aedcffc83b479aa3a4",
+        "f987a7253ac413176f2b074cf7815e54",
+        "f3392b0822b70005940c7a398e4b70f3",
+        "e7159475a2c29b7443b29c7fa6e889d9",
+        "d097f3bdfd2022b8845ad8f792aa5825",
+        "a9f746462d870fdf8a65dc1f90e061e5",
+        "70d869a156d2a1b890bb3df62baf32f7",
+        "31be135f97d08fd981231505542fcfa6",
+        "9aa508b5b7a84e1c677de54f3e99bc9",
+        "5d6af8dedb81196699c329225ee604",
+        "2216e584f5fa1ea926041bedfe98",
+    ]
+    .iter()
+    .map(|ratio| U256::from_str_radix(ratio, 16).unwrap())
+    .collect();
+}
+
+fn q64() -> U256 {
+    U256::one() << 64
+}
+
+fn to_u128(x: U256) -> Option<u128> {
+    if x > U256::from(u128::MAX) {
+        None
+    } else {
+        Some(x.as_u128())
+    }
+}
+
+fn div_ceil(a: U256, b: U256) -> Option<U256> {
+    if b.is_zero() {
+        return None;
+    }
+    let q = a / b;
+    if (a % b).is_zero() { Some(q) } else { q.checked_add(U256::one()) }
+}
+
+/// sqrt(1.0001^tick) as Q64.64
+pub fn sqrt_price_at_tick(tick: i32) -> Option<u128> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return None;
+    }
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = U256::one() << 128;
+    for (bit, tick_ratio) in TICK_RATIOS.iter().enumerate() {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = (ratio * *tick_ratio) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::max_value() / ratio;
+    }
+    // Q128.128 -> Q64.64
+    to_u128(ratio >> 64)
+}
+
+/// amount of token 0 between two prices: L * (sb - sa) / (sa * sb)
+pub fn amount_0_delta(sqrt_a: u128, sqrt_b: u128, liquidity: u128, round_up: bool) -> Option<u128> {
+    let (sqrt_a, sqrt_b) = if sqrt_a > sqrt_b { (sqrt_b, sqrt_a) } else { (sqrt_a, sqrt_b) };
+    if sqrt_a == 0 {
+        return None;
+    }
+    let numerator = (U256::from(liquidity) << 64) * U256::from(sqrt_b - sqrt_a);
+    let amount = if round_up {
+        div_ceil(div_ceil(numerator, U256::from(sqrt_b))?, U256::from(sqrt_a))?
+    } else {
+        numerator / U256::from(sqrt_b) / U256::from(sqrt_a)
+    };
+    to_u128(amount)
+}
+
+/// amount of token 1 between two prices: L * (sb - sa)
+pub fn amount_1_delta(sqrt_a: u128, sqrt_b: u128, liquidity: u128, round_up: bool) -> Option<u128> {
+    let (sqrt_a, sqrt_b) = if sqrt_a > sqrt_b { (sqrt_b, sqrt_a) } else { (sqrt_a, sqrt_b) };
+    let product = U256::from(liquidity) * U256::from(sqrt_b - sqrt_a);
+    let amount = if round_up { div_ceil(product, q64())? } else { product >> 64 };
+    to_u128(amount)
+}
+
+/// price after adding `amount_in` of the input token (rounded against the trader)
+pub fn next_sqrt_price_from_input(sqrt_price: u128, liquidity: u128, amount_in: u128, zero_for_one: bool) -> Option<u128> {
+    if amount_in == 0 {
+        return Some(sqrt_price);
+    }
+    if liquidity == 0 {
+        return None;
+    }
+    if zero_for_one {
+        // L * sqrtP / (L + amount * sqrtP), rounded up
+        let numerator = U256::from(liquidity) << 64;
+        let denominator = numerator.checked_add(U256::from(amount_in) * U256::from(sqrt_price))?;
+        to_u128(div_ceil(numerator.checked_mul(U256::from(sqrt_price))?, denominator)?)
+    } else {
+        // sqrtP + amount / L, rounded down
+        let delta = to_u128((U256::from(amount_in) << 64) / U256::from(liquidity))?;
+        sqrt_price.checked_add(delta)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SwapStep {
+    pub sqrt_price_next: u128,
+    pub amount_in: u128,
+    pub amount_out: u128,
+    pub fee_amount: u128,
+}
+
+/// one exact-in step from `sqrt_current` towards `sqrt_target` within a single liquidity range
+pub fn compute_swap_step(
+    sqrt_current: u128,
+    sqrt_target: u128,
+    liquidity: u128,
+    amount_remaining: u128,
+    fee_rate: u32,
+) -> Option<SwapStep> {
+    let zero_for_one = sqrt_current >= sqrt_target;
+    let fee_complement = FEE_RATE_DENOMINATOR.checked_sub(fee_rate as u128)?;
+    let amount_remaining_less_fee = to_u128(
+        U256::from(amount_remaining) * U256::from(fee_complement) / U256::from(FEE_RATE_DENOMINATOR),
+    )?;
+
+    let amount_to_target = if zero_for_one {
+        amount_0_delta(sqrt_target, sqrt_current, liquidity, true)?
+    } else {
+        amount_1_delta(sqrt_current, sqrt_target, liquidity, true)?
+    };
+    let sqrt_price_next = if amount_remaining_less_fee >= amount_to_target {
+        sqrt_target
+    } else {
+        next_sqrt_price_from_input(sqrt_current, liquidity, amount_remaining_less_fee, zero_for_one)?
+    };
+    let reached_target = sqrt_price_next == sqrt_target;
+
+    let (amount_in, amount_out) = if zero_for_one {
+        (
+            if reached_target { amount_to_target } else { amount_0_delta(sqrt_price_next, sqrt_current, liquidity, true)? },
+            amount_1_delta(sqrt_price_next, sqrt_current, liquidity, false)?,
+        )
+    } else {
+        (
+            if reached_target { amount_to_target } else { amount_1_delta(sqrt_current, sqrt_price_next, liquidity, true)? },
+            amount_0_delta(sqrt_current, sqrt_price_next, liquidity, false)?,
+        )
+    };
+
+    // a partial step keeps whatever is left as fee
+    let fee_amount = if reached_target {
+        to_u128(div_ceil(
+            U256::from(amount_in) * U256::from(fee_rate),
+            U256::from(fee_complement),
+        )?)?
+    } else {
+        amount_remaining.checked_sub(amount_in)?
+    };
+
+    Some(SwapStep {
+        sqrt_price_next,
+        amount_in,
+        amount_out,
+        fee_amount,
+    })
+}
+
+/// an initialized tick from one of the loaded tick arrays
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TickLiquidity {
+    pub tick: i32,
+    pub liquidity_net: i128,
+}
+
+/// everything a quote walks over
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClmmState {
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+    pub fee_rate: u32,
+    // sorted by tick
+    pub ticks: Vec<TickLiquidity>,
+    // [lower, upper) ticks the loaded tick arrays tell us about --
+    // the price cant be walked past these
+    pub tick_range: (i32, i32),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SwapResult {
+    pub amount_in: u128, // incl. fees
+    pub amount_out: u128,
+    pub sqrt_price_x64: u128,
+}
+
+impl ClmmState {
+    /// exact-in swap across as many ticks as needed. if the loaded tick arrays
+    /// run out first, `amount_in` is less than what was asked for
+    pub fn swap_exact_in(&self, amount_in: u128, zero_for_one: bool) -> Option<SwapResult> {
+        let mut sqrt_price = self.sqrt_price_x64;
+        let mut tick = self.tick_current;
+        let mut liquidity = self.liquidity;
+        let mut remaining = amount_in;
+        let mut amount_out = 0_u128;
+
+        let boundary = if zero_for_one {
+            self.tick_range.0.max(MIN_TICK)
+        } else {
+            self.tick_range.1.min(MAX_TICK)
+        };
+
+        while remaining > 0 {
+            // next initialized tick in the swap direction (the current tick counts going down)
+            let next = if zero_for_one {
+                self.ticks.iter().rev().find(|t| t.tick <= tick && t.tick >= boundary)
+            } else {
+                self.ticks.iter().find(|t| t.tick > tick && t.tick <= boundary)
+            };
+            let target_tick = next.map(|t| t.tick).unwrap_or(boundary);
+            let sqrt_target = sqrt_price_at_tick(target_tick)?;
+            let behind = if zero_for_one { sqrt_target > sqrt_price } else { sqrt_target < sqrt_price };
+            if behind || (sqrt_price == sqrt_target && next.is_none()) {
+                break; // sitting on the edge of what we know
+            }
+
+            let step = compute_swap_step(sqrt_price, sqrt_target, liquidity, remaining, self.fee_rate)?;
+            remaining = remaining.checked_sub(step.amount_in.checked_add(step.fee_amount)?)?;
+            amount_out = amount_out.checked_add(step.amount_out)?;
+            sqrt_price = step.sqrt_price_next;
+
+            if sqrt_price != sqrt_target {
+                break; // input used up inside the range
+            }
+            match next {
+                Some(crossed) => {
+                    liquidity = if zero_for_one {
+                        apply_liquidity_net(liquidity, -crossed.liquidity_net)?
+                    } else {
+                        apply_liquidity_net(liquidity, crossed.liquidity_net)?
+                    };
+                    tick = if zero_for_one { crossed.tick - 1 } else { crossed.tick };
+                }
+                None => break,
+            }
+        }
+
+        Some(SwapResult {
+            amount_in: amount_in - remaining,
+            amount_out,
+            sqrt_price_x64: sqrt_price,
+        })
+    }
+}
+
+fn apply_liquidity_net(liquidity: u128, net: i128) -> Option<u128> {
+    if net >= 0 {
+        liquidity.checked_add(net as u128)
+    } else {
+        liquidity.checked_sub(net.unsigned_abs())
+    }
+}
+
+/// start tick of the array holding `tick` (arrays hold `ticks_per_array` ticks, spaced out)
+pub fn tick_array_start(tick: i32, tick_spacing: u16, ticks_per_array: i32) -> i32 {
+    let span = tick_spacing as i32 * ticks_per_array;
+    tick.div_euclid(span) * span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tick_math_bounds() {
+        assert_eq!(sqrt_price_at_tick(0), Some(1 << 64));
+        let min = sqrt_price_at_tick(MIN_TICK).unwrap();
+        let max = sqrt_price_at_tick(MAX_TICK).unwrap();
+        assert!(min >= MIN_SQRT_PRICE_X64 - 1 && min <= MIN_SQRT_PRICE_X64 + 1);
+        let max_err = MAX_SQRT_PRICE_X64 / 1_000_000_000_000;
+        assert!(max >= MAX_SQRT_PRICE_X64 - max_err && max <= MAX_SQRT_PRICE_X64 + max_err);
+        assert!(sqrt_price_at_tick(MAX_TICK + 1).is_none());
+
+        // 1.0001^(100 / 2) = 1.0050124...
+        let p = sqrt_price_at_tick(100).unwrap() as f64 / 2_f64.powi(64);
+        assert!((p - 1.0001_f64.powf(50.)).abs() < 1e-12);
+        // monotonic
+        assert!(sqrt_price_at_tick(-1).unwrap() < sqrt_price_at_tick(0).unwrap());
+        assert!(sqrt_price_at_tick(1).unwrap() > sqrt_price_at_tick(0).unwrap());
+    }
+
+    #[test]
+    fn array_starts_round_down() {
+        assert_eq!(tick_array_start(0, 1, 60), 0);
+        assert_eq!(tick_array_start(59, 1, 60), 0);
+        assert_eq!(tick_array_start(-1, 1, 60), -60);
+        assert_eq!(tick_array_start(-1000, 64, 88), -5632);
+    }
+
+    fn single_range(liquidity: u128, fee_rate: u32) -> ClmmState {
+        ClmmState {
+            sqrt_price_x64: 1 << 64,
+            tick_current: 0,
+            liquidity,
+            fee_rate,
+            ticks: vec![],
+            tick_range: (MIN_TICK, MAX_TICK),
+        }
+    }
+
+    #[test]
+    fn single_range_matches_virtual_reserves() {
+        // price 1, no ticks crossed -> x * y = L^2 with x = y = L
+        let liquidity = 1_000_000_000_000_u128;
+        let state = single_range(liquidity, 0);
+        let amount_in = 1_000_000_u128;
+        let expected = liquidity * amount_in / (liquidity + amount_in);
+
+        let result = state.swap_exact_in(amount_in, true).unwrap();
+        assert_eq!(result.amount_in, amount_in);
+        assert!(result.amount_out <= expected && expected - result.amount_out <= 1);
+
+        let result = state.swap_exact_in(amount_in, false).unwrap();
+        assert!(result.amount_out <= expected && expected - result.amount_out <= 1);
+        assert!(result.sqrt_price_x64 > 1 << 64);
+    }
+
+    #[test]
+    fn fee_comes_off_the_input() {
+        let liquidity = 1_000_000_000_000_u128;
+        let no_fee = single_range(liquidity, 0).swap_exact_in(1_000_000, true).unwrap();
+        let fee = single_range(liquidity, 3_000).swap_exact_in(1_000_000, true).unwrap();
+        // 0.3% less in, ~0.3% less out
+        assert_eq!(fee.amount_in, 1_000_000);
+        assert!(fee.amount_out < no_fee.amount_out);
+        assert!(no_fee.amount_out - fee.amount_out >= 2_990);
+        assert!(no_fee.amount_out - fee.amount_out <= 3_010);
+    }
+
+    #[test]
+    fn crossing_a_tick_changes_liquidity() {
+        let liquidity = 1_000_000_000_u128;
+        let mut state = single_range(liquidity, 0);
+        // the position ends at tick -10, below it there is nothing
+        state.ticks = vec![TickLiquidity { tick: -10, liquidity_net: liquidity as i128 }];
+        let depth = amount_0_delta(sqrt_price_at_tick(-10).unwrap(), 1 << 64, liquidity, true).unwrap();
+
+        // fits in the range
+        let result = state.swap_exact_in(depth / 2, true).unwrap();
+        assert_eq!(result.amount_in, depth / 2);
+
+        // more than the range holds -> only the range is filled, the price
+        // then falls through the empty ticks below it
+        let result = state.swap_exact_in(depth * 2, true).unwrap();
+        assert_eq!(result.amount_in, depth);
+        assert!(result.sqrt_price_x64 < sqrt_price_at_tick(-10).unwrap());
+        let range_out = amount_1_delta(sqrt_price_at_tick(-10).unwrap(), 1 << 64, liquidity, false).unwrap();
+        assert_eq!(result.amount_out, range_out);
+    }
+
+    #[test]
+    fn stops_at_the_loaded_arrays() {
+        let mut state = single_range(1_000_000_000, 0);
+        state.tick_range = (-60, 60);
+        let result = state.swap_exact_in(u64::MAX as u128, false).unwrap();
+        assert!(result.amount_in < u64::MAX as u128);
+        assert_eq!(result.sqrt_price_x64, sqrt_price_at_tick(60).unwrap());
+    }
+}
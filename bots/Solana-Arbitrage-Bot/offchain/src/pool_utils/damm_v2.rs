@@ -0,0 +1,364 @@
+//! Meteora DAMM v2 (cp-amm) account layout + swap math
+//! (anchor zero-copy account -- offsets include the 8 byte discriminator)
+//!
+//! a single concentrated position between sqrt_min_price and sqrt_max_price.
+//! liquidity is stored scaled by 2^64 (so a Q64.64 price * liquidity is Q128).
+//! the fee is a scheduled base fee (decaying after launch) plus an optional
+//! volatility based dynamic fee.
+
+use arrayref::array_ref;
+use spl_math::uint::U256;
+
+// Pool
+pub const POOL_LEN: usize = 1112;
+// base fee
+const CLIFF_FEE_NUMERATOR: usize = 8;
+const FEE_SCHEDULER_MODE: usize = 16;
+const NUMBER_OF_PERIOD: usize = 22;
+const PERIOD_FREQUENCY: usize = 24;
+const BASE_REDUCTION_FACTOR: usize = 32;
+// dynamic fee
+const DYNAMIC_FEE_INITIALIZED: usize = 56;
+const MAX_VOLATILITY_ACCUMULATOR: usize = 64;
+const VARIABLE_FEE_CONTROL: usize = 68;
+const BIN_STEP: usize = 72;
+const FILTER_PERIOD: usize = 74;
+const DECAY_PERIOD: usize = 76;
+const DYNAMIC_REDUCTION_FACTOR: usize = 78;
+const LAST_UPDATE_TIMESTAMP: usize = 80;
+const BIN_STEP_U128: usize = 88;
+const SQRT_PRICE_REFERENCE: usize = 104;
+const VOLATILITY_ACCUMULATOR: usize = 120;
+const VOLATILITY_REFERENCE: usize = 136;
+// pool
+const LIQUIDITY: usize = 360;
+const SQRT_MIN_PRICE: usize = 424;
+const SQRT_MAX_PRICE: usize = 440;
+const SQRT_PRICE: usize = 456;
+const ACTIVATION_POINT: usize = 472;
+const ACTIVATION_TYPE: usize = 480;
+const POOL_STATUS: usize = 481;
+const COLLECT_FEE_MODE: usize = 484;
+
+pub const FEE_DENOMINATOR: u128 = 1_000_000_000;
+pub const MAX_FEE_NUMERATOR: u128 = 500_000_000;
+const BASIS_POINT_MAX: u128 = 10_000;
+const ONE_Q64: u128 = 1 << 64;
+
+// activation_type
+const ACTIVATION_BY_TIMESTAMP: u8 = 1;
+// collect_fee_mode: 0 = fee taken from the output, 1 = fee always taken in token b
+const COLLECT_FEE_ONLY_B: u8 = 1;
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(*array_ref![data, offset, 2])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(*array_ref![data, offset, 4])
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*array_ref![data, offset, 8])
+}
+
+fn read_u128(data: &[u8], offset: usize) -> u128 {
+    u128::from_le_bytes(*array_ref![data, offset, 16])
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BaseFee {
+    pub cliff_fee_numerator: u64,
+    // 0 = linear, 1 = exponential
+    pub fee_scheduler_mode: u8,
+    pub number_of_period: u16,
+    pub period_frequency: u64,
+    pub reduction_factor: u64,
+}
+
+impl BaseFee {
+    /// fee numerator `elapsed` (slots or seconds) after activation
+    pub fn fee_numerator(&self, elapsed: Option<u64>) -> u128 {
+        let cliff = self.cliff_fee_numerator as u128;
+        let periods = match elapsed {
+            Some(elapsed) if self.period_frequency > 0 => {
+                (elapsed / self.period_frequency).min(self.number_of_period as u64)
+            }
+            // unknown -> the (highest) starting fee
+            _ => 0,
+        };
+        match self.fee_scheduler_mode {
+            0 => cliff.saturating_sub(periods as u128 * self.reduction_factor as u128),
+            _ => {
+                let keep = BASIS_POINT_MAX.saturating_sub(self.reduction_factor as u128);
+                (0..periods).fold(cliff, |fee, _| fee * keep / BASIS_POINT_MAX)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DynamicFee {
+    pub max_volatility_accumulator: u32,
+    pub variable_fee_control: u32,
+    pub bin_step: u16,
+    pub filter_period: u16,
+    pub decay_period: u16,
+    pub reduction_factor: u16,
+    pub last_update_timestamp: u64,
+    pub bin_step_u128: u128,
+    pub sqrt_price_reference: u128,
+    pub volatility_accumulator: u128,
+    pub volatility_reference: u128,
+}
+
+impl DynamicFee {
+    /// the accumulator the next swap will be charged with
+    pub fn update(&mut self, sqrt_price: u128, now: i64) -> Option<()> {
+        let elapsed = (now.max(0) as u64).saturating_sub(self.last_update_timestamp);
+        if elapsed >= self.filter_period as u64 {
+            self.sqrt_price_reference = sqrt_price;
+            self.volatility_reference = if elapsed < self.decay_period as u64 {
+                self.volatility_accumulator * self.reduction_factor as u128 / BASIS_POINT_MAX
+            } else {
+                0
+            };
+        }
+        let delta_bin_id = delta_bin_id(self.bin_step_u128, self.sqrt_price_reference, sqrt_price)?;
+        let accumulator = self.volatility_reference.checked_add(delta_bin_id.checked_mul(BASIS_POINT_MAX)?)?;
+        self.volatility_accumulator = accumulator.min(self.max_volatility_accumulator as u128);
+        Some(())
+    }
+
+    pub fn variable_fee_numerator(&self) -> Option<u128> {
+        let square = self.volatility_accumulator.checked_mul(self.bin_step as u128)?.checked_pow(2)?;
+        Some(square.checked_mul(self.variable_fee_control as u128)?.checked_add(99_999_999_999)? / 100_000_000_000)
+    }
+}
+
+// bins the price moved through (1 bin = bin_step bps of price)
+fn delta_bin_id(bin_step_u128: u128, sqrt_price_a: u128, sqrt_price_b: u128) -> Option<u128> {
+    let (upper, lower) = if sqrt_price_a > sqrt_price_b { (sqrt_price_a, sqrt_price_b) } else { (sqrt_price_b, sqrt_price_a) };
+    if lower == 0 || bin_step_u128 == 0 {
+        return None;
+    }
+    let price_ratio = to_u128((U256::from(upper) << 64) / U256::from(lower))?;
+    Some(price_ratio.checked_sub(ONE_Q64)? / bin_step_u128 * 2)
+}
+
+fn to_u128(x: U256) -> Option<u128> {
+    if x > U256::from(u128::MAX) { None } else { Some(x.as_u128()) }
+}
+
+fn mul_div(a: u128, b: u128, denominator: u128, round_up: bool) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+    let product = U256::from(a) * U256::from(b);
+    let mut result = product / U256::from(denominator);
+    if round_up && !(product % U256::from(denominator)).is_zero() {
+        result = result + U256::one();
+    }
+    to_u128(result)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DammV2State {
+    pub base_fee: BaseFee,
+    pub dynamic_fee: Option<DynamicFee>,
+    pub liquidity: u128,
+    pub sqrt_min_price: u128,
+    pub sqrt_max_price: u128,
+    pub sqrt_price: u128,
+    pub activation_point: u64,
+    pub activation_type: u8,
+    pub pool_status: u8,
+    pub collect_fee_mode: u8,
+}
+
+impl DammV2State {
+    pub fn unpack(data: &[u8], discriminator: &[u8; 8]) -> Option<Self> {
+        if data.len() < POOL_LEN || data[..8] != discriminator[..] {
+            return None;
+        }
+        let dynamic_fee = if data[DYNAMIC_FEE_INITIALIZED] != 0 {
+            Some(DynamicFee {
+                max_volatility_accumulator: read_u32(data, MAX_VOLATILITY_ACCUMULATOR),
+                variable_fee_control: read_u32(data, VARIABLE_FEE_CONTROL),
+                bin_step: read_u16(data, BIN_STEP),
+                filter_period: read_u16(data, FILTER_PERIOD),
+                decay_period: read_u16(data, DECAY_PERIOD),
+                reduction_factor: read_u16(data, DYNAMIC_REDUCTION_FACTOR),
+                last_update_timestamp: read_u64(data, LAST_UPDATE_TIMESTAMP),
+                bin_step_u128: read_u128(data, BIN_STEP_U128),
+                sqrt_price_reference: read_u128(data, SQRT_PRICE_REFERENCE),
+                volatility_accumulator: read_u128(data, VOLATILITY_ACCUMULATOR),
+                volatility_reference: read_u128(data, VOLATILITY_REFERENCE),
+            })
+        } else {
+            None
+        };
+        Some(Self {
+            base_fee: BaseFee {
+                cliff_fee_numerator: read_u64(data, CLIFF_FEE_NUMERATOR),
+                fee_scheduler_mode: data[FEE_SCHEDULER_MODE],
+                number_of_period: read_u16(data, NUMBER_OF_PERIOD),
+                period_frequency: read_u64(data, PERIOD_FREQUENCY),
+                reduction_factor: read_u64(data, BASE_REDUCTION_FACTOR),
+            },
+            dynamic_fee,
+            liquidity: read_u128(data, LIQUIDITY),
+            sqrt_min_price: read_u128(data, SQRT_MIN_PRICE),
+            sqrt_max_price: read_u128(data, SQRT_MAX_PRICE),
+            sqrt_price: read_u128(data, SQRT_PRICE),
+            activation_point: read_u64(data, ACTIVATION_POINT),
+            activation_type: data[ACTIVATION_TYPE],
+            pool_status: data[POOL_STATUS],
+            collect_fee_mode: data[COLLECT_FEE_MODE],
+        })
+    }
+
+    // 0 = enabled, and (for timestamp activations) past its start
+    pub fn can_swap(&self, now: i64) -> bool {
+        let activated = self.activation_type != ACTIVATION_BY_TIMESTAMP
+            || now.max(0) as u64 >= self.activation_point;
+        self.pool_status == 0 && activated
+    }
+
+    /// total fee numerator (out of FEE_DENOMINATOR) for a swap at unix time `now`
+    /// (slot activated schedules arent tracked -- they're charged the starting fee)
+    pub fn fee_numerator(&self, now: i64) -> Option<u128> {
+        let elapsed = if self.activation_type == ACTIVATION_BY_TIMESTAMP {
+            Some((now.max(0) as u64).saturating_sub(self.activation_point))
+        } else {
+            None
+        };
+        let mut fee = self.base_fee.fee_numerator(elapsed);
+        if let Some(mut dynamic_fee) = self.dynamic_fee {
+            dynamic_fee.update(self.sqrt_price, now)?;
+            fee = fee.checked_add(dynamic_fee.variable_fee_numerator()?)?;
+        }
+        Some(fee.min(MAX_FEE_NUMERATOR))
+    }
+
+    /// exact-in swap (None if it would push the price out of the pool's range)
+    pub fn swap_exact_in(&self, amount_in: u128, a_to_b: bool, now: i64) -> Option<u128> {
+        let fee_numerator = self.fee_numerator(now)?;
+        // fee on the input only when it is token b in OnlyB mode
+        let fee_on_input = self.collect_fee_mode == COLLECT_FEE_ONLY_B && !a_to_b;
+
+        let amount_in = if fee_on_input {
+            amount_in.checked_sub(mul_div(amount_in, fee_numerator, FEE_DENOMINATOR, true)?)?
+        } else {
+            amount_in
+        };
+
+        let out = if a_to_b {
+            // L * sqrtP / (L + amount * sqrtP), rounded up
+            let product = U256::from(amount_in) * U256::from(self.sqrt_price);
+            let denominator = U256::from(self.liquidity).checked_add(product)?;
+            if denominator.is_zero() {
+                return None;
+            }
+            let numerator = U256::from(self.liquidity) * U256::from(self.sqrt_price);
+            let mut next = numerator / denominator;
+            if !(numerator % denominator).is_zero() {
+                next = next + U256::one();
+            }
+            let next = to_u128(next)?;
+            if next < self.sqrt_min_price {
+                return None;
+            }
+            // L * (sqrtP - next) >> 128
+            to_u128((U256::from(self.liquidity) * U256::from(self.sqrt_price - next)) >> 128)?
+        } else {
+            if self.liquidity == 0 {
+                return None;
+            }
+            let delta = to_u128((U256::from(amount_in) << 128) / U256::from(self.liquidity))?;
+            let next = self.sqrt_price.checked_add(delta)?;
+            if next > self.sqrt_max_price {
+                return None;
+            }
+            // L * (next - sqrtP) / (sqrtP * next)
+            let numerator = U256::from(self.liquidity) * U256::from(next - self.sqrt_price);
+            let denominator = U256::from(self.sqrt_price) * U256::from(next);
+            if denominator.is_zero() {
+                return None;
+            }
+            to_u128(numerator / denominator)?
+        };
+
+        if fee_on_input {
+            Some(out)
+        } else {
+            out.checked_sub(mul_div(out, fee_numerator, FEE_DENOMINATOR, true)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(liquidity: u128, cliff_fee_numerator: u64) -> DammV2State {
+        DammV2State {
+            base_fee: BaseFee { cliff_fee_numerator, ..BaseFee::default() },
+            liquidity: liquidity << 64,
+            sqrt_min_price: 4295048016,
+            sqrt_max_price: 79226673521066979257578248091,
+            sqrt_price: ONE_Q64,
+            ..DammV2State::default()
+        }
+    }
+
+    #[test]
+    fn full_range_matches_constant_product() {
+        let liquidity = 1_000_000_000_000_u128;
+        let state = state(liquidity, 0);
+        let amount_in = 1_000_000;
+        let expected = liquidity * amount_in / (liquidity + amount_in);
+        for a_to_b in [true, false] {
+            let out = state.swap_exact_in(amount_in, a_to_b, 0).unwrap();
+            assert!(out <= expected && expected - out <= 1);
+        }
+    }
+
+    #[test]
+    fn fee_modes() {
+        let liquidity = 1_000_000_000_000_u128;
+        // 1%
+        let mut state = state(liquidity, 10_000_000);
+        let on_output = state.swap_exact_in(1_000_000, false, 0).unwrap();
+        assert!(on_output >= 989_998 && on_output <= 990_000);
+        state.collect_fee_mode = COLLECT_FEE_ONLY_B;
+        let on_input = state.swap_exact_in(1_000_000, false, 0).unwrap();
+        assert!(on_input >= 989_998 && on_input <= 990_000);
+    }
+
+    #[test]
+    fn base_fee_decays_after_activation() {
+        let base_fee = BaseFee {
+            cliff_fee_numerator: 500_000_000,
+            fee_scheduler_mode: 0,
+            number_of_period: 10,
+            period_frequency: 60,
+            reduction_factor: 40_000_000,
+        };
+        assert_eq!(base_fee.fee_numerator(None), 500_000_000);
+        assert_eq!(base_fee.fee_numerator(Some(0)), 500_000_000);
+        assert_eq!(base_fee.fee_numerator(Some(125)), 420_000_000);
+        // capped at number_of_period
+        assert_eq!(base_fee.fee_numerator(Some(100_000)), 100_000_000);
+
+        let exponential = BaseFee { fee_scheduler_mode: 1, reduction_factor: 5_000, ..base_fee };
+        assert_eq!(exponential.fee_numerator(Some(120)), 125_000_000);
+    }
+
+    #[test]
+    fn out_of_range_swaps_fail() {
+        let mut state = state(1_000, 0);
+        state.sqrt_max_price = ONE_Q64 + 1;
+        assert!(state.swap_exact_in(1_000_000, false, 0).is_none());
+    }
+}
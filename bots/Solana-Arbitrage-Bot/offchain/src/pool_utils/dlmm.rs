@@ -0,0 +1,504 @@
+//! Meteora DLMM (liquidity book) account layouts + swap math
+//! (anchor zero-copy accounts -- offsets include the 8 byte discriminator)
+//!
+//! liquidity sits in discrete bins, bin `id` trades at a fixed
+//! (1 + bin_step / 1e4)^id of Y per X. swaps empty the active bin then move
+//! to the next one, the fee grows with how far the price has moved recently
+//! (the volatility accumulator).
+
+use std::collections::BTreeMap;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use arrayref::array_ref;
+use spl_math::uint::U256;
+
+// LbPair
+pub const LB_PAIR_LEN: usize = 904;
+const BASE_FACTOR: usize = 8;
+const FILTER_PERIOD: usize = 10;
+const DECAY_PERIOD: usize = 12;
+const REDUCTION_FACTOR: usize = 14;
+const VARIABLE_FEE_CONTROL: usize = 16;
+const MAX_VOLATILITY_ACCUMULATOR: usize = 20;
+const BASE_FEE_POWER_FACTOR: usize = 34;
+const VOLATILITY_ACCUMULATOR: usize = 40;
+const VOLATILITY_REFERENCE: usize = 44;
+const INDEX_REFERENCE: usize = 48;
+const LAST_UPDATE_TIMESTAMP: usize = 56;
+const ACTIVE_ID: usize = 76;
+const BIN_STEP: usize = 80;
+const PAIR_STATUS: usize = 82;
+const BIN_ARRAY_BITMAP: usize = 584;
+
+// BinArray
+pub const MAX_BIN_PER_ARRAY: i32 = 70;
+pub const BIN_ARRAY_LEN: usize = 10136;
+const BIN_ARRAY_INDEX: usize = 8;
+const BINS: usize = 56;
+const BIN_LEN: usize = 144;
+const BIN_AMOUNT_Y: usize = 8;
+const BIN_PRICE: usize = 16;
+
+// the pair's own bitmap covers bin arrays [-512, 512)
+const BITMAP_BITS: i64 = 512;
+
+pub const FEE_PRECISION: u128 = 1_000_000_000;
+pub const MAX_FEE_RATE: u128 = 100_000_000;
+const BASIS_POINT_MAX: u128 = 10_000;
+
+pub const BIN_ARRAY_SEED: &[u8] = b"bin_array";
+pub const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(*array_ref![data, offset, 2])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(*array_ref![data, offset, 4])
+}
+
+fn read_i32(data: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(*array_ref![data, offset, 4])
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*array_ref![data, offset, 8])
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FeeParameters {
+    pub base_factor: u16,
+    pub filter_period: u16,
+    pub decay_period: u16,
+    pub reduction_factor: u16,
+    pub variable_fee_control: u32,
+    pub max_volatility_accumulator: u32,
+    pub base_fee_power_factor: u8,
+}
+
+/// moves with every swap (see `update_references`)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VolatilityState {
+    pub volatility_accumulator: u32,
+    pub volatility_reference: u32,
+    pub index_reference: i32,
+    pub last_update_timestamp: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LbPairState {
+    pub fee_parameters: FeeParameters,
+    pub volatility: VolatilityState,
+    pub active_id: i32,
+    pub bin_step: u16,
+    pub status: u8,
+    // bit i = bin array (i - 512) exists
+    pub bin_array_bitmap: Vec<u64>,
+}
+
+impl LbPairState {
+    pub fn unpack(data: &[u8], discriminator: &[u8; 8]) -> Option<Self> {
+        if data.len() < LB_PAIR_LEN || data[..8] != discriminator[..] {
+            return None;
+        }
+        Some(Self {
+            fee_parameters: FeeParameters {
+                base_factor: read_u16(data, BASE_FACTOR),
+                filter_period: read_u16(data, FILTER_PERIOD),
+                decay_period: read_u16(data, DECAY_PERIOD),
+                reduction_factor: read_u16(data, REDUCTION_FACTOR),
+                variable_fee_control: read_u32(data, VARIABLE_FEE_CONTROL),
+                max_volatility_accumulator: read_u32(data, MAX_VOLATILITY_ACCUMULATOR),
+                base_fee_power_factor: data[BASE_FEE_POWER_FACTOR],
+            },
+            volatility: VolatilityState {
+                volatility_accumulator: read_u32(data, VOLATILITY_ACCUMULATOR),
+                volatility_reference: read_u32(data, VOLATILITY_REFERENCE),
+                index_reference: read_i32(data, INDEX_REFERENCE),
+                last_update_timestamp: read_u64(data, LAST_UPDATE_TIMESTAMP) as i64,
+            },
+            active_id: read_i32(data, ACTIVE_ID),
+            bin_step: read_u16(data, BIN_STEP),
+            status: data[PAIR_STATUS],
+            bin_array_bitmap: (0..16).map(|i| read_u64(data, BIN_ARRAY_BITMAP + i * 8)).collect(),
+        })
+    }
+
+    // 0 = enabled
+    pub fn swap_enabled(&self) -> bool {
+        self.status == 0
+    }
+
+    /// indexes of every bin array the pair's bitmap knows about, sorted
+    pub fn initialized_bin_arrays(&self) -> Vec<i64> {
+        let mut indexes = vec![];
+        for (word_idx, word) in self.bin_array_bitmap.iter().enumerate() {
+            for bit in 0..64 {
+                if word & (1 << bit) != 0 {
+                    indexes.push((word_idx * 64 + bit) as i64 - BITMAP_BITS);
+                }
+            }
+        }
+        indexes
+    }
+
+    /// bin arrays a swap could walk into: up to `n` at/below the active array
+    /// and `n` above it. returns (indexes, inclusive [lower, upper] bin ids covered)
+    pub fn bin_arrays_around(&self, n: usize) -> (Vec<i64>, (i32, i32)) {
+        let initialized = self.initialized_bin_arrays();
+        let active = bin_array_index(self.active_id);
+        let below: Vec<i64> = initialized.iter().rev().filter(|i| **i <= active).take(n).cloned().collect();
+        let above: Vec<i64> = initialized.iter().filter(|i| **i > active).take(n).cloned().collect();
+
+        // past the pair's bitmap there may be arrays in the extension we dont load
+        let lower = if below.len() < n { -BITMAP_BITS } else { *below.last().unwrap() };
+        let upper = if above.len() < n { BITMAP_BITS } else { above.last().unwrap() + 1 };
+        let bin_range = (
+            (lower * MAX_BIN_PER_ARRAY as i64) as i32,
+            (upper * MAX_BIN_PER_ARRAY as i64 - 1) as i32,
+        );
+
+        let mut indexes = below;
+        indexes.reverse();
+        indexes.extend(above);
+        (indexes, bin_range)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Bin {
+    pub amount_x: u64,
+    pub amount_y: u64,
+    // Q64.64 Y per X (0 if the bin was never touched)
+    pub price: u128,
+}
+
+/// non-empty bins from a BinArray, (array index, bin id -> bin)
+pub fn unpack_bin_array(data: &[u8], discriminator: &[u8; 8]) -> Option<(i64, Vec<(i32, Bin)>)> {
+    if data.len() < BIN_ARRAY_LEN || data[..8] != discriminator[..] {
+        return None;
+    }
+    let index = read_u64(data, BIN_ARRAY_INDEX) as i64;
+    let first_bin = index.checked_mul(MAX_BIN_PER_ARRAY as i64)? as i32;
+    let bins = (0..MAX_BIN_PER_ARRAY as usize)
+        .map(|i| {
+            let offset = BINS + i * BIN_LEN;
+            let bin = Bin {
+                amount_x: read_u64(data, offset),
+                amount_y: read_u64(data, offset + BIN_AMOUNT_Y),
+                price: u128::from_le_bytes(*array_ref![data, offset + BIN_PRICE, 16]),
+            };
+            (first_bin + i as i32, bin)
+        })
+        .filter(|(_, bin)| bin.amount_x > 0 || bin.amount_y > 0)
+        .collect();
+    Some((index, bins))
+}
+
+pub fn bin_array_index(bin_id: i32) -> i64 {
+    (bin_id as i64).div_euclid(MAX_BIN_PER_ARRAY as i64)
+}
+
+pub fn bin_array_address(program_id: &Pubkey, lb_pair: &Pubkey, index: i64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[BIN_ARRAY_SEED, lb_pair.as_ref(), &index.to_le_bytes()],
+        program_id,
+    ).0
+}
+
+pub fn event_authority(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], program_id).0
+}
+
+/// (1 + bin_step / 1e4)^bin_id as Q64.64
+pub fn price_from_id(bin_id: i32, bin_step: u16) -> Option<u128> {
+    let one = U256::one() << 64;
+    let mut base = one + (U256::from(bin_step) << 64) / U256::from(BASIS_POINT_MAX);
+    let mut exp = bin_id.unsigned_abs();
+    let mut result = one;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = (result * base) >> 64;
+        }
+        base = (base * base) >> 64;
+        exp >>= 1;
+        if result.is_zero() || result.bits() > 192 {
+            return None;
+        }
+    }
+    if bin_id < 0 {
+        result = (U256::one() << 128) / result;
+    }
+    if result.is_zero() || result > U256::from(u128::MAX) {
+        return None;
+    }
+    Some(result.as_u128())
+}
+
+impl FeeParameters {
+    /// out of FEE_PRECISION
+    pub fn base_fee_rate(&self, bin_step: u16) -> u128 {
+        self.base_factor as u128
+            * bin_step as u128
+            * 10
+            * 10_u128.pow(self.base_fee_power_factor as u32)
+    }
+
+    pub fn variable_fee_rate(&self, volatility_accumulator: u32, bin_step: u16) -> u128 {
+        if self.variable_fee_control == 0 {
+            return 0;
+        }
+        let square = (volatility_accumulator as u128 * bin_step as u128).pow(2);
+        (square * self.variable_fee_control as u128 + 99_999_999_999) / 100_000_000_000
+    }
+
+    pub fn total_fee_rate(&self, volatility_accumulator: u32, bin_step: u16) -> u128 {
+        (self.base_fee_rate(bin_step) + self.variable_fee_rate(volatility_accumulator, bin_step))
+            .min(MAX_FEE_RATE)
+    }
+}
+
+impl VolatilityState {
+    /// start of a swap: decay the reference depending on how long ago the last one was
+    pub fn update_references(&mut self, params: &FeeParameters, active_id: i32, now: i64) {
+        let elapsed = now.saturating_sub(self.last_update_timestamp);
+        if elapsed >= params.filter_period as i64 {
+            self.index_reference = active_id;
+            self.volatility_reference = if elapsed < params.decay_period as i64 {
+                (self.volatility_accumulator as u128 * params.reduction_factor as u128
+                    / BASIS_POINT_MAX) as u32
+            } else {
+                0
+            };
+        }
+    }
+
+    /// every bin the swap reaches
+    pub fn update_accumulator(&mut self, params: &FeeParameters, active_id: i32) {
+        let delta_id = (self.index_reference as i64 - active_id as i64).unsigned_abs() as u128;
+        let accumulator = self.volatility_reference as u128 + delta_id * BASIS_POINT_MAX;
+        self.volatility_accumulator = accumulator.min(params.max_volatility_accumulator as u128) as u32;
+    }
+}
+
+// fee on an amount which includes it
+fn fee_from_amount(amount: u128, fee_rate: u128) -> Option<u128> {
+    Some(amount.checked_mul(fee_rate)?.checked_add(FEE_PRECISION - 1)? / FEE_PRECISION)
+}
+
+// fee to add on top of an amount
+fn fee_on_top(amount: u128, fee_rate: u128) -> Option<u128> {
+    let denominator = FEE_PRECISION.checked_sub(fee_rate)?;
+    Some(amount.checked_mul(fee_rate)?.checked_add(denominator - 1)? / denominator)
+}
+
+fn mul_shr_64(a: u128, b: u128, round_up: bool) -> Option<u128> {
+    let product = U256::from(a) * U256::from(b);
+    let mut result = product >> 64;
+    if round_up && !(product & ((U256::one() << 64) - 1)).is_zero() {
+        result = result + U256::one();
+    }
+    if result > U256::from(u128::MAX) { None } else { Some(result.as_u128()) }
+}
+
+fn shl_div_64(a: u128, b: u128, round_up: bool) -> Option<u128> {
+    if b == 0 {
+        return None;
+    }
+    let numerator = U256::from(a) << 64;
+    let mut result = numerator / U256::from(b);
+    if round_up && !(numerator % U256::from(b)).is_zero() {
+        result = result + U256::one();
+    }
+    if result > U256::from(u128::MAX) { None } else { Some(result.as_u128()) }
+}
+
+/// everything a quote walks over
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DlmmState {
+    pub pair: LbPairState,
+    pub bins: BTreeMap<i32, Bin>,
+    // inclusive bin ids the loaded bin arrays cover
+    pub bin_range: (i32, i32),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SwapResult {
+    pub amount_in: u128, // incl. fees
+    pub amount_out: u128,
+    pub fee: u128,
+}
+
+impl DlmmState {
+    /// exact-in swap at unix time `now`. if the loaded bins run out first,
+    /// `amount_in` is less than what was asked for
+    pub fn swap_exact_in(&self, amount_in: u128, swap_for_y: bool, now: i64) -> Option<SwapResult> {
+        let params = &self.pair.fee_parameters;
+        let bin_step = self.pair.bin_step;
+        let mut volatility = self.pair.volatility;
+        volatility.update_references(params, self.pair.active_id, now);
+
+        let mut active_id = self.pair.active_id;
+        let mut remaining = amount_in;
+        let mut amount_out = 0_u128;
+        let mut total_fee = 0_u128;
+
+        while remaining > 0 && active_id >= self.bin_range.0 && active_id <= self.bin_range.1 {
+            // next bin with something to sell (empty bins in between are skipped)
+            let next = if swap_for_y {
+                self.bins.range(self.bin_range.0..=active_id).rev().find(|(_, bin)| bin.amount_y > 0)
+            } else {
+                self.bins.range(active_id..=self.bin_range.1).find(|(_, bin)| bin.amount_x > 0)
+            };
+            let (bin_id, bin) = match next {
+                Some((bin_id, bin)) => (*bin_id, bin),
+                None => break,
+            };
+            active_id = bin_id;
+
+            volatility.update_accumulator(params, active_id);
+            let fee_rate = params.total_fee_rate(volatility.volatility_accumulator, bin_step);
+            let price = if bin.price > 0 { bin.price } else { price_from_id(bin_id, bin_step)? };
+
+            // input (before fee) that empties the bin
+            let (max_in, max_out) = if swap_for_y {
+                (shl_div_64(bin.amount_y as u128, price, true)?, bin.amount_y as u128)
+            } else {
+                (mul_shr_64(bin.amount_x as u128, price, true)?, bin.amount_x as u128)
+            };
+            let max_fee = fee_on_top(max_in, fee_rate)?;
+
+            if remaining >= max_in.checked_add(max_fee)? {
+                remaining -= max_in + max_fee;
+                amount_out = amount_out.checked_add(max_out)?;
+                total_fee = total_fee.checked_add(max_fee)?;
+                active_id = if swap_for_y { bin_id - 1 } else { bin_id + 1 };
+            } else {
+                let fee = fee_from_amount(remaining, fee_rate)?;
+                let amount_in_after_fee = remaining - fee;
+                let out = if swap_for_y {
+                    mul_shr_64(amount_in_after_fee, price, false)?
+                } else {
+                    shl_div_64(amount_in_after_fee, price, false)?
+                };
+                amount_out = amount_out.checked_add(out.min(max_out))?;
+                total_fee = total_fee.checked_add(fee)?;
+                remaining = 0;
+            }
+        }
+
+        Some(SwapResult {
+            amount_in: amount_in - remaining,
+            amount_out,
+            fee: total_fee,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(active_id: i32, bin_step: u16) -> LbPairState {
+        LbPairState {
+            fee_parameters: FeeParameters {
+                base_factor: 10_000,
+                filter_period: 30,
+                decay_period: 600,
+                reduction_factor: 5_000,
+                variable_fee_control: 40_000,
+                max_volatility_accumulator: 350_000,
+                base_fee_power_factor: 0,
+            },
+            active_id,
+            bin_step,
+            ..LbPairState::default()
+        }
+    }
+
+    #[test]
+    fn prices_from_bin_ids() {
+        assert_eq!(price_from_id(0, 10), Some(1 << 64));
+        let p = price_from_id(100, 10).unwrap() as f64 / 2_f64.powi(64);
+        assert!((p - 1.001_f64.powi(100)).abs() < 1e-9);
+        let p = price_from_id(-100, 10).unwrap() as f64 / 2_f64.powi(64);
+        assert!((p - 1.001_f64.powi(-100)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fees_grow_with_volatility() {
+        let params = pair(0, 10).fee_parameters;
+        // 10_000 * 10 * 10 = 1e6 / 1e9 = 0.1%
+        assert_eq!(params.base_fee_rate(10), 1_000_000);
+        assert_eq!(params.total_fee_rate(0, 10), 1_000_000);
+        // (10_000 * 10)^2 * 40_000 / 1e11 = 4_000
+        assert_eq!(params.variable_fee_rate(10_000, 10), 4_000);
+        assert!(params.total_fee_rate(350_000, 10) > params.total_fee_rate(10_000, 10));
+
+        // references decay (or reset) with time since the last swap
+        let mut volatility = VolatilityState {
+            volatility_accumulator: 100_000,
+            last_update_timestamp: 1_000,
+            ..VolatilityState::default()
+        };
+        volatility.update_references(&params, 5, 1_010); // inside the filter period
+        assert_eq!(volatility.volatility_reference, 0);
+        assert_eq!(volatility.index_reference, 0);
+        volatility.update_references(&params, 5, 1_100);
+        assert_eq!(volatility.volatility_reference, 50_000);
+        assert_eq!(volatility.index_reference, 5);
+        volatility.update_accumulator(&params, 2);
+        assert_eq!(volatility.volatility_accumulator, 80_000);
+        volatility.update_references(&params, 5, 10_000);
+        assert_eq!(volatility.volatility_reference, 0);
+    }
+
+    fn state(bins: Vec<(i32, Bin)>) -> DlmmState {
+        let mut pair = pair(0, 10);
+        pair.fee_parameters.base_factor = 0;
+        pair.fee_parameters.variable_fee_control = 0;
+        DlmmState {
+            pair,
+            bins: bins.into_iter().collect(),
+            bin_range: (-70, 139),
+        }
+    }
+
+    #[test]
+    fn swaps_within_the_active_bin() {
+        let state = state(vec![(0, Bin { amount_x: 1_000_000, amount_y: 1_000_000, price: 1 << 64 })]);
+        let result = state.swap_exact_in(1_000, true, 0).unwrap();
+        assert_eq!(result.amount_in, 1_000);
+        assert_eq!(result.amount_out, 1_000);
+        let result = state.swap_exact_in(1_000, false, 0).unwrap();
+        assert_eq!(result.amount_out, 1_000);
+    }
+
+    #[test]
+    fn crosses_into_the_next_bin() {
+        let price_1 = price_from_id(1, 10).unwrap();
+        let state = state(vec![
+            (0, Bin { amount_x: 0, amount_y: 1_000, price: 1 << 64 }),
+            (1, Bin { amount_x: 1_000, amount_y: 0, price: price_1 }),
+            (3, Bin { amount_x: 1_000_000, amount_y: 0, price: price_from_id(3, 10).unwrap() }),
+        ]);
+        // y -> x skips the empty active bin (no x in it), empties bin 1 then moves to bin 3
+        let result = state.swap_exact_in(2_000, false, 0).unwrap();
+        assert_eq!(result.amount_in, 2_000);
+        assert!(result.amount_out > 1_000 && result.amount_out < 2_000);
+
+        // x -> y: only 1_000 y on this side
+        let result = state.swap_exact_in(1_000_000, true, 0).unwrap();
+        assert_eq!(result.amount_out, 1_000);
+        assert_eq!(result.amount_in, 1_000);
+    }
+
+    #[test]
+    fn fee_comes_off_the_input() {
+        let mut state = state(vec![(0, Bin { amount_x: 1_000_000, amount_y: 1_000_000, price: 1 << 64 })]);
+        state.pair.fee_parameters.base_factor = 10_000; // 0.1%
+        let result = state.swap_exact_in(10_000, true, 0).unwrap();
+        assert_eq!(result.fee, 10);
+        assert_eq!(result.amount_out, 9_990);
+    }
+}
@@ -0,0 +1,273 @@
+//! Meteora Dynamic AMM account layouts + swap math
+//! (borsh anchor accounts -- offsets include the 8 byte discriminator)
+//!
+//! the pool doesnt hold its tokens: each side is deposited in a meteora
+//! vault and the pool holds the vault's lp token. reserves are the pool's
+//! share of what the vault can currently withdraw.
+
+use arrayref::array_ref;
+
+use crate::pool_utils::constant_product;
+use crate::pool_utils::fees::calculate_fee;
+use crate::pool_utils::stable::Stable;
+
+// Pool
+pub const POOL_LEN: usize = 944;
+const ENABLED: usize = 233;
+const TRADE_FEE_NUMERATOR: usize = 330;
+const TRADE_FEE_DENOMINATOR: usize = 338;
+const PROTOCOL_TRADE_FEE_NUMERATOR: usize = 346;
+const PROTOCOL_TRADE_FEE_DENOMINATOR: usize = 354;
+const CURVE_TYPE: usize = 874;
+const AMP: usize = 875;
+const TOKEN_A_MULTIPLIER: usize = 883;
+const TOKEN_B_MULTIPLIER: usize = 891;
+const DEPEG_TYPE: usize = 916;
+
+// Vault
+pub const VAULT_LEN: usize = 1227;
+const TOTAL_AMOUNT: usize = 11;
+const LAST_UPDATED_LOCKED_PROFIT: usize = 1203;
+const LAST_REPORT: usize = 1211;
+const LOCKED_PROFIT_DEGRADATION: usize = 1219;
+const LOCKED_PROFIT_DEGRADATION_DENOMINATOR: u128 = 1_000_000_000_000;
+
+// spl mint supply
+const MINT_LEN: usize = 82;
+const MINT_SUPPLY: usize = 36;
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*array_ref![data, offset, 8])
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AmmCurve {
+    ConstantProduct,
+    // multipliers = [a, b] (upscale both sides to the same decimals)
+    Stable { amp: u64, multipliers: [u64; 2], depeg: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmmPoolState {
+    pub enabled: bool,
+    pub trade_fee_numerator: u64,
+    pub trade_fee_denominator: u64,
+    pub protocol_fee_numerator: u64,
+    pub protocol_fee_denominator: u64,
+    pub curve: AmmCurve,
+}
+
+impl AmmPoolState {
+    pub fn unpack(data: &[u8], discriminator: &[u8; 8]) -> Option<Self> {
+        if data.len() < POOL_LEN || data[..8] != discriminator[..] {
+            return None;
+        }
+        let curve = match data[CURVE_TYPE] {
+            0 => AmmCurve::ConstantProduct,
+            1 => AmmCurve::Stable {
+                amp: read_u64(data, AMP),
+                multipliers: [read_u64(data, TOKEN_A_MULTIPLIER), read_u64(data, TOKEN_B_MULTIPLIER)],
+                depeg: data[DEPEG_TYPE] != 0,
+            },
+            _ => return None,
+        };
+        Some(Self {
+            enabled: data[ENABLED] != 0,
+            trade_fee_numerator: read_u64(data, TRADE_FEE_NUMERATOR),
+            trade_fee_denominator: read_u64(data, TRADE_FEE_DENOMINATOR),
+            protocol_fee_numerator: read_u64(data, PROTOCOL_TRADE_FEE_NUMERATOR),
+            protocol_fee_denominator: read_u64(data, PROTOCOL_TRADE_FEE_DENOMINATOR),
+            curve,
+        })
+    }
+}
+
+/// profit a vault has reported is unlocked linearly over time
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VaultState {
+    pub total_amount: u64,
+    pub last_updated_locked_profit: u64,
+    pub last_report: u64,
+    pub locked_profit_degradation: u64,
+}
+
+impl VaultState {
+    pub fn unpack(data: &[u8], discriminator: &[u8; 8]) -> Option<Self> {
+        if data.len() < VAULT_LEN || data[..8] != discriminator[..] {
+            return None;
+        }
+        Some(Self {
+            total_amount: read_u64(data, TOTAL_AMOUNT),
+            last_updated_locked_profit: read_u64(data, LAST_UPDATED_LOCKED_PROFIT),
+            last_report: read_u64(data, LAST_REPORT),
+            locked_profit_degradation: read_u64(data, LOCKED_PROFIT_DEGRADATION),
+        })
+    }
+
+    /// what the vault can withdraw at unix time `now`
+    pub fn unlocked_amount(&self, now: i64) -> Option<u128> {
+        let duration = (now.max(0) as u64).saturating_sub(self.last_report) as u128;
+        let ratio = duration.checked_mul(self.locked_profit_degradation as u128)?;
+        let locked = if ratio > LOCKED_PROFIT_DEGRADATION_DENOMINATOR {
+            0
+        } else {
+            (self.last_updated_locked_profit as u128)
+                .checked_mul(LOCKED_PROFIT_DEGRADATION_DENOMINATOR - ratio)?
+                / LOCKED_PROFIT_DEGRADATION_DENOMINATOR
+        };
+        (self.total_amount as u128).checked_sub(locked)
+    }
+}
+
+pub fn unpack_mint_supply(data: &[u8]) -> Option<u64> {
+    if data.len() < MINT_LEN {
+        return None;
+    }
+    Some(read_u64(data, MINT_SUPPLY))
+}
+
+/// the pool's share of one vault
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VaultShare {
+    pub unlocked: u128,
+    pub lp_supply: u128,
+    pub pool_lp: u128,
+}
+
+impl VaultShare {
+    // lp -> underlying (rounded down)
+    pub fn amount_by_share(&self, lp: u128) -> Option<u128> {
+        if self.lp_supply == 0 {
+            return Some(0);
+        }
+        lp.checked_mul(self.unlocked)?.checked_div(self.lp_supply)
+    }
+
+    // underlying -> lp (rounded down)
+    pub fn unmint_amount(&self, amount: u128) -> Option<u128> {
+        if self.unlocked == 0 {
+            return Some(0);
+        }
+        amount.checked_mul(self.lp_supply)?.checked_div(self.unlocked)
+    }
+
+    /// the pool's reserve on this side
+    pub fn reserve(&self) -> Option<u128> {
+        self.amount_by_share(self.pool_lp)
+    }
+}
+
+/// exact-in swap, following the program: protocol fee out, deposit into the
+/// input vault (rounded through its lp), lp fee out, curve, then withdraw
+/// from the output vault (rounded through its lp again)
+pub fn swap(
+    pool: &AmmPoolState,
+    vault_in: &VaultShare,
+    vault_out: &VaultShare,
+    a_to_b: bool,
+    amount_in: u128,
+) -> Option<u128> {
+    let trade_fee = calculate_fee(
+        amount_in,
+        pool.trade_fee_numerator as u128,
+        pool.trade_fee_denominator as u128,
+    )?;
+    let protocol_fee = calculate_fee(
+        trade_fee,
+        pool.protocol_fee_numerator as u128,
+        pool.protocol_fee_denominator.max(1) as u128,
+    )?;
+    let lp_fee = trade_fee.checked_sub(protocol_fee)?;
+    let amount_in = amount_in.checked_sub(protocol_fee)?;
+
+    // what actually lands in the pool's share of the input vault
+    let before_in = vault_in.reserve()?;
+    let minted = vault_in.unmint_amount(amount_in)?;
+    let after = VaultShare {
+        unlocked: vault_in.unlocked.checked_add(amount_in)?,
+        lp_supply: vault_in.lp_supply.checked_add(minted)?,
+        pool_lp: vault_in.pool_lp.checked_add(minted)?,
+    };
+    let actual_in = after.reserve()?.checked_sub(before_in)?;
+    let swap_in = actual_in.checked_sub(lp_fee)?;
+
+    let reserve_in = before_in;
+    let reserve_out = vault_out.reserve()?;
+    let out = match pool.curve {
+        AmmCurve::ConstantProduct => {
+            constant_product::swap(swap_in, reserve_in, reserve_out)?.destination_amount_swapped
+        }
+        AmmCurve::Stable { amp, multipliers, .. } => {
+            let multipliers = if a_to_b { multipliers } else { [multipliers[1], multipliers[0]] };
+            // fees were taken above
+            let curve = Stable { amp, fee_numerator: 0, fee_denominator: 1 };
+            curve.get_quote([reserve_in, reserve_out], multipliers, swap_in)?
+        }
+    };
+
+    // withdrawn through the output vault's lp
+    let out_lp = vault_out.unmint_amount(out)?;
+    vault_out.amount_by_share(out_lp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(curve: AmmCurve) -> AmmPoolState {
+        AmmPoolState {
+            enabled: true,
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10_000,
+            protocol_fee_numerator: 20,
+            protocol_fee_denominator: 100,
+            curve,
+        }
+    }
+
+    fn share(amount: u128) -> VaultShare {
+        // the pool owns half the vault, 1 lp = 2 tokens
+        VaultShare { unlocked: amount * 2, lp_supply: amount, pool_lp: amount / 2 }
+    }
+
+    #[test]
+    fn reserves_are_the_pools_share() {
+        let vault = share(1_000_000);
+        assert_eq!(vault.reserve(), Some(1_000_000));
+        assert_eq!(vault.unmint_amount(10), Some(5));
+        assert_eq!(vault.amount_by_share(5), Some(10));
+    }
+
+    #[test]
+    fn locked_profit_unlocks_over_time() {
+        let vault = VaultState {
+            total_amount: 1_000,
+            last_updated_locked_profit: 100,
+            last_report: 1_000,
+            // fully unlocked after 1e12 / 1e9 = 1000s
+            locked_profit_degradation: 1_000_000_000,
+        };
+        assert_eq!(vault.unlocked_amount(1_000), Some(900));
+        assert_eq!(vault.unlocked_amount(1_500), Some(950));
+        assert_eq!(vault.unlocked_amount(5_000), Some(1_000));
+    }
+
+    #[test]
+    fn constant_product_quote() {
+        let depth = 1_000_000_000_000;
+        let out = swap(&pool(AmmCurve::ConstantProduct), &share(depth), &share(depth), true, 1_000_000).unwrap();
+        // 0.25% fee, ~1:1, and a token or two lost to the vault lp rounding
+        assert!(out <= 997_500 && out >= 997_490);
+    }
+
+    #[test]
+    fn stable_quote_is_near_one_to_one() {
+        let depth = 1_000_000_000_000;
+        let curve = AmmCurve::Stable { amp: 100, multipliers: [1, 1], depeg: false };
+        let out = swap(&pool(curve), &share(depth), &share(depth), false, 1_000_000).unwrap();
+        assert!(out <= 997_500 && out >= 997_000);
+        let cp = swap(&pool(AmmCurve::ConstantProduct), &share(depth / 1_000), &share(depth / 1_000), false, 1_000_000).unwrap();
+        let stable = swap(&pool(curve), &share(depth / 1_000), &share(depth / 1_000), false, 1_000_000).unwrap();
+        assert!(stable > cp);
+    }
+}
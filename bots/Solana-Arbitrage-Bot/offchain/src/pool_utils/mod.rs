@@ -7,7 +7,19 @@ pub mod constant_product;
 pub mod fees;
 pub mod offset;
 pub mod stable;
+pub mod clmm;
 
 // pool specific details 
 pub mod orca;
-pub mod serum;
\ No newline at end of file
+pub mod serum;
+pub mod raydium;
+pub mod raydium_cpmm;
+pub mod raydium_clmm;
+pub mod whirlpool;
+pub mod dlmm;
+pub mod meteora_amm;
+pub mod damm_v2;
+pub mod pumpswap;
+pub mod pump_fun;
+pub mod openbook_v2;
+pub mod phoenix;
@@ -0,0 +1,248 @@
+//! OpenBook v2 account layouts + taker fees
+//! (anchor zero-copy accounts -- offsets include the 8 byte discriminator)
+//!
+//! prices are quote lots per base lot like serum, so the book is decoded
+//! into serum's OrderBook and walked the same way. only the fixed price
+//! tree is read -- oracle pegged orders need the oracle price.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use arrayref::array_ref;
+
+use crate::pool_utils::serum::{BookLevel, OrderBook};
+
+// Market
+pub const MARKET_LEN: usize = 848;
+const BIDS: usize = 200;
+const ASKS: usize = 232;
+const EVENT_HEAP: usize = 264;
+const ORACLE_A: usize = 296;
+const ORACLE_B: usize = 328;
+const QUOTE_LOT_SIZE: usize = 448;
+const BASE_LOT_SIZE: usize = 456;
+const TAKER_FEE: usize = 488;
+const MARKET_BASE_VAULT: usize = 640;
+const MARKET_QUOTE_VAULT: usize = 680;
+
+// BookSide: roots, reserved, then the node array
+pub const BOOK_SIDE_LEN: usize = 90_952;
+const FIXED_ROOT: usize = 8;
+const NODES: usize = 840;
+const NODE_LEN: usize = 88;
+const MAX_NODES: usize = 1024;
+const INNER_TAG: u8 = 1;
+const LEAF_TAG: u8 = 2;
+// inner node
+const CHILDREN: usize = 24;
+// leaf node
+const TIME_IN_FORCE: usize = 2;
+const KEY: usize = 8;
+const QUANTITY: usize = 56;
+const TIMESTAMP: usize = 64;
+
+// taker fees are in millionths, rounded up
+pub const FEES_SCALE_FACTOR: u128 = 1_000_000;
+
+pub const MARKET_AUTHORITY_SEED: &[u8] = b"Market";
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(*array_ref![data, offset, 4])
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*array_ref![data, offset, 8])
+}
+
+fn read_i64(data: &[u8], offset: usize) -> i64 {
+    i64::from_le_bytes(*array_ref![data, offset, 8])
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(*array_ref![data, offset, 32])
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MarketState {
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub event_heap: Pubkey,
+    // default = none
+    pub oracle_a: Pubkey,
+    pub oracle_b: Pubkey,
+    pub base_lot_size: u64,
+    pub quote_lot_size: u64,
+    pub taker_fee: i64,
+    pub market_base_vault: Pubkey,
+    pub market_quote_vault: Pubkey,
+}
+
+impl MarketState {
+    pub fn unpack(data: &[u8], discriminator: &[u8; 8]) -> Option<Self> {
+        if data.len() < MARKET_LEN || data[..8] != discriminator[..] {
+            return None;
+        }
+        let (base_lot_size, quote_lot_size) = (read_i64(data, BASE_LOT_SIZE), read_i64(data, QUOTE_LOT_SIZE));
+        if base_lot_size <= 0 || quote_lot_size <= 0 {
+            return None;
+        }
+        Some(Self {
+            bids: read_pubkey(data, BIDS),
+            asks: read_pubkey(data, ASKS),
+            event_heap: read_pubkey(data, EVENT_HEAP),
+            oracle_a: read_pubkey(data, ORACLE_A),
+            oracle_b: read_pubkey(data, ORACLE_B),
+            base_lot_size: base_lot_size as u64,
+            quote_lot_size: quote_lot_size as u64,
+            taker_fee: read_i64(data, TAKER_FEE),
+            market_base_vault: read_pubkey(data, MARKET_BASE_VAULT),
+            market_quote_vault: read_pubkey(data, MARKET_QUOTE_VAULT),
+        })
+    }
+
+    pub fn taker_fee(&self, native_quote: u64) -> u64 {
+        let rate = self.taker_fee.max(0) as u128;
+        ((native_quote as u128 * rate + FEES_SCALE_FACTOR - 1) / FEES_SCALE_FACTOR) as u64
+    }
+
+    /// quote lots a taker bid can spend out of `native_quote_in` (fees included)
+    pub fn remove_taker_fee(&self, native_quote_in: u64) -> u64 {
+        let rate = self.taker_fee.max(0) as u128;
+        let native = native_quote_in as u128 * FEES_SCALE_FACTOR / (FEES_SCALE_FACTOR + rate);
+        native as u64 / self.quote_lot_size
+    }
+
+    /// bid: native quote in (fees included) -> native base out
+    pub fn quote_bid(&self, book: &OrderBook, native_quote_in: u64) -> u64 {
+        book.fill_bid(self.remove_taker_fee(native_quote_in)) * self.base_lot_size
+    }
+
+    /// ask: native base in -> native quote out (fee taken)
+    pub fn quote_ask(&self, book: &OrderBook, native_base_in: u64) -> u64 {
+        let native_quote = book.fill_ask(native_base_in / self.base_lot_size) * self.quote_lot_size;
+        native_quote - self.taker_fee(native_quote)
+    }
+
+    /// lowest limit price (quote lots per base lot) at which selling all of
+    /// `base_lots` still nets `min_native_quote_out` after the fee
+    pub fn min_ask_price(&self, base_lots: u64, min_native_quote_out: u64) -> u64 {
+        let rate = (self.taker_fee.max(0) as u128).min(FEES_SCALE_FACTOR - 1);
+        let mut native = (min_native_quote_out as u128 * FEES_SCALE_FACTOR + FEES_SCALE_FACTOR - rate - 1)
+            / (FEES_SCALE_FACTOR - rate);
+        while native - self.taker_fee(native as u64) as u128 < min_native_quote_out as u128 {
+            native += 1;
+        }
+        let quote_lots = (native + self.quote_lot_size as u128 - 1) / self.quote_lot_size as u128;
+        let base_lots = base_lots.max(1) as u128;
+        ((quote_lots + base_lots - 1) / base_lots).min(i64::MAX as u128) as u64
+    }
+}
+
+/// live leaves of a book side's fixed price tree, best price first
+/// (orders past their time in force at `now` are skipped)
+pub fn unpack_book_side(data: &[u8], discriminator: &[u8; 8], is_bids: bool, now: u64) -> Option<Vec<BookLevel>> {
+    if data.len() < BOOK_SIDE_LEN || data[..8] != discriminator[..] {
+        return None;
+    }
+    let leaf_count = read_u32(data, FIXED_ROOT + 4);
+    let mut levels = vec![];
+    let mut stack = if leaf_count > 0 { vec![read_u32(data, FIXED_ROOT)] } else { vec![] };
+    while let Some(index) = stack.pop() {
+        let index = index as usize;
+        // a corrupt tree could loop -- a real one never holds more than MAX_NODES
+        if index >= MAX_NODES || levels.len() > MAX_NODES {
+            return None;
+        }
+        let node = &data[NODES + index * NODE_LEN..NODES + (index + 1) * NODE_LEN];
+        match node[0] {
+            INNER_TAG => {
+                stack.push(read_u32(node, CHILDREN));
+                stack.push(read_u32(node, CHILDREN + 4));
+            }
+            LEAF_TAG => {
+                let time_in_force = u16::from_le_bytes(*array_ref![node, TIME_IN_FORCE, 2]) as u64;
+                let expired = time_in_force > 0 && read_u64(node, TIMESTAMP) + time_in_force <= now;
+                let key = u128::from_le_bytes(*array_ref![node, KEY, 16]);
+                let quantity = read_i64(node, QUANTITY);
+                if !expired && quantity > 0 {
+                    levels.push(BookLevel { price: (key >> 64) as u64, quantity: quantity as u64 });
+                }
+            }
+            _ => return None,
+        }
+    }
+    if is_bids {
+        levels.sort_by(|a, b| b.price.cmp(&a.price));
+    } else {
+        levels.sort_by_key(|level| level.price);
+    }
+    Some(levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISCRIMINATOR: [u8; 8] = [1; 8];
+
+    fn book_side(nodes: &[(u8, Vec<u8>)], root: u32, leaf_count: u32) -> Vec<u8> {
+        let mut data = vec![0; BOOK_SIDE_LEN];
+        data[..8].copy_from_slice(&DISCRIMINATOR);
+        data[FIXED_ROOT..FIXED_ROOT + 4].copy_from_slice(&root.to_le_bytes());
+        data[FIXED_ROOT + 4..FIXED_ROOT + 8].copy_from_slice(&leaf_count.to_le_bytes());
+        for (i, (tag, body)) in nodes.iter().enumerate() {
+            let start = NODES + i * NODE_LEN;
+            data[start] = *tag;
+            data[start + 1..start + 1 + body.len()].copy_from_slice(body);
+        }
+        data
+    }
+
+    // node body (after the tag byte)
+    fn leaf(price: u64, quantity: i64, time_in_force: u16, timestamp: u64) -> (u8, Vec<u8>) {
+        let mut node = vec![0; NODE_LEN];
+        node[TIME_IN_FORCE..TIME_IN_FORCE + 2].copy_from_slice(&time_in_force.to_le_bytes());
+        node[KEY..KEY + 16].copy_from_slice(&(((price as u128) << 64) | 3).to_le_bytes());
+        node[QUANTITY..QUANTITY + 8].copy_from_slice(&quantity.to_le_bytes());
+        node[TIMESTAMP..TIMESTAMP + 8].copy_from_slice(&timestamp.to_le_bytes());
+        (LEAF_TAG, node[1..].to_vec())
+    }
+
+    fn inner(left: u32, right: u32) -> (u8, Vec<u8>) {
+        let mut node = vec![0; NODE_LEN];
+        node[CHILDREN..CHILDREN + 4].copy_from_slice(&left.to_le_bytes());
+        node[CHILDREN + 4..CHILDREN + 8].copy_from_slice(&right.to_le_bytes());
+        (INNER_TAG, node[1..].to_vec())
+    }
+
+    #[test]
+    fn walks_the_fixed_tree() {
+        // 0 = root, 5 = inner, 3 = a stale leaf not in the tree, 2 = expired at t=100
+        let data = book_side(
+            &[inner(5, 4), leaf(50, 3, 0, 0), leaf(55, 1, 10, 50), leaf(99, 9, 0, 0), leaf(52, 2, 0, 0), inner(1, 2)],
+            0,
+            3,
+        );
+        let asks = unpack_book_side(&data, &DISCRIMINATOR, false, 100).unwrap();
+        assert_eq!(asks, vec![BookLevel { price: 50, quantity: 3 }, BookLevel { price: 52, quantity: 2 }]);
+        let empty = book_side(&[], 0, 0);
+        assert_eq!(unpack_book_side(&empty, &DISCRIMINATOR, true, 100), Some(vec![]));
+    }
+
+    #[test]
+    fn taker_fee_rounds_up() {
+        let market = MarketState { base_lot_size: 100, quote_lot_size: 10, taker_fee: 400, ..MarketState::default() };
+        assert_eq!(market.taker_fee(10_000), 4);
+        assert_eq!(market.taker_fee(10_001), 5);
+        let book = OrderBook {
+            coin_lot_size: 100,
+            pc_lot_size: 10,
+            bids: vec![BookLevel { price: 10, quantity: 5 }],
+            asks: vec![BookLevel { price: 10, quantity: 5 }],
+        };
+        // 5 lots @ 10 = 50 quote lots = 500 native
+        assert_eq!(market.quote_ask(&book, 500), 500 - 1);
+        assert_eq!(market.quote_bid(&book, 501), 500);
+        // filling at the limit nets the min out
+        assert_eq!(market.min_ask_price(5, 499), 10);
+        assert_eq!(market.min_ask_price(5, 500), 11);
+    }
+}
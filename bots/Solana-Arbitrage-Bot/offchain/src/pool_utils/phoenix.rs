@@ -0,0 +1,275 @@
+//! Phoenix market layout + taker fill math
+//! (not anchor -- the market header starts with a u64 discriminant)
+//!
+//! both sides of the book live inside the market account as sokoban
+//! red-black trees (1-indexed node addresses). prices are ticks and
+//! `ticks * tick_size * base_lots / base_lots_per_base_unit` quote lots
+//! change hands, so fills are walked here rather than with serum's book.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use arrayref::array_ref;
+
+pub const BPS_DENOMINATOR: u128 = 10_000;
+
+// MarketHeader
+const STATUS: usize = 8;
+const BIDS_SIZE: usize = 16;
+const ASKS_SIZE: usize = 24;
+const BASE_MINT: usize = 48;
+const BASE_VAULT: usize = 80;
+const BASE_LOT_SIZE: usize = 112;
+const QUOTE_MINT: usize = 128;
+const QUOTE_VAULT: usize = 160;
+const QUOTE_LOT_SIZE: usize = 192;
+// FIFOMarket (after the 576 byte header + 256 bytes padding)
+const BASE_LOTS_PER_BASE_UNIT: usize = 832;
+const TICK_SIZE: usize = 840;
+const TAKER_FEE_BPS: usize = 856;
+const BIDS_TREE: usize = 880;
+
+// only active markets take orders
+const STATUS_ACTIVE: u64 = 1;
+
+// sokoban tree: root u32 + padding, allocator { size, bump, free head }, nodes
+const TREE_HEADER_LEN: usize = 32;
+const NODE_LEN: usize = 64;
+// registers [left, right, parent, color], then (FIFOOrderId, FIFORestingOrder)
+const LEFT: usize = 0;
+const RIGHT: usize = 4;
+const PRICE_IN_TICKS: usize = 16;
+const NUM_BASE_LOTS: usize = 40;
+const LAST_VALID_UNIX_TS: usize = 56;
+
+pub const LOG_AUTHORITY_SEED: &[u8] = b"log";
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(*array_ref![data, offset, 4])
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*array_ref![data, offset, 8])
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(*array_ref![data, offset, 32])
+}
+
+fn tree_len(size: u64) -> usize {
+    TREE_HEADER_LEN + size as usize * NODE_LEN
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Level {
+    pub price_in_ticks: u64,
+    pub base_lots: u64,
+}
+
+/// resting orders of one side, best price first (expired orders skipped)
+fn unpack_tree(data: &[u8], size: u64, is_bids: bool, now: u64) -> Option<Vec<Level>> {
+    let nodes = &data[TREE_HEADER_LEN..tree_len(size)];
+    let mut levels = vec![];
+    let mut stack = vec![read_u32(data, 0)];
+    while let Some(addr) = stack.pop() {
+        // 0 = nil
+        if addr == 0 {
+            continue;
+        }
+        let index = addr as usize - 1;
+        // a corrupt tree could loop -- a real one never holds more than size nodes
+        if index >= size as usize || levels.len() >= size as usize {
+            return None;
+        }
+        let node = &nodes[index * NODE_LEN..(index + 1) * NODE_LEN];
+        stack.push(read_u32(node, LEFT));
+        stack.push(read_u32(node, RIGHT));
+        let last_valid_ts = read_u64(node, LAST_VALID_UNIX_TS);
+        let base_lots = read_u64(node, NUM_BASE_LOTS);
+        if (last_valid_ts == 0 || last_valid_ts >= now) && base_lots > 0 {
+            levels.push(Level { price_in_ticks: read_u64(node, PRICE_IN_TICKS), base_lots });
+        }
+    }
+    if is_bids {
+        levels.sort_by(|a, b| b.price_in_ticks.cmp(&a.price_in_ticks));
+    } else {
+        levels.sort_by_key(|level| level.price_in_ticks);
+    }
+    Some(levels)
+}
+
+/// a decoded snapshot of one market -- quotes walk it without touching it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhoenixMarket {
+    pub active: bool,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub base_lot_size: u64,
+    pub quote_lot_size: u64,
+    pub base_lots_per_base_unit: u64,
+    pub tick_size: u64,
+    pub taker_fee_bps: u64,
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+}
+
+impl PhoenixMarket {
+    pub fn unpack(data: &[u8], now: u64) -> Option<Self> {
+        if data.len() < BIDS_TREE {
+            return None;
+        }
+        let (bids_size, asks_size) = (read_u64(data, BIDS_SIZE), read_u64(data, ASKS_SIZE));
+        let asks_tree = BIDS_TREE.checked_add(tree_len(bids_size))?;
+        if data.len() < asks_tree.checked_add(tree_len(asks_size))? {
+            return None;
+        }
+        let market = Self {
+            active: read_u64(data, STATUS) == STATUS_ACTIVE,
+            base_mint: read_pubkey(data, BASE_MINT),
+            quote_mint: read_pubkey(data, QUOTE_MINT),
+            base_vault: read_pubkey(data, BASE_VAULT),
+            quote_vault: read_pubkey(data, QUOTE_VAULT),
+            base_lot_size: read_u64(data, BASE_LOT_SIZE),
+            quote_lot_size: read_u64(data, QUOTE_LOT_SIZE),
+            base_lots_per_base_unit: read_u64(data, BASE_LOTS_PER_BASE_UNIT),
+            tick_size: read_u64(data, TICK_SIZE),
+            taker_fee_bps: read_u64(data, TAKER_FEE_BPS),
+            bids: unpack_tree(&data[BIDS_TREE..], bids_size, true, now)?,
+            asks: unpack_tree(&data[asks_tree..], asks_size, false, now)?,
+        };
+        let lot_sizes = [market.base_lot_size, market.quote_lot_size, market.base_lots_per_base_unit, market.tick_size];
+        if lot_sizes.contains(&0) {
+            return None;
+        }
+        Some(market)
+    }
+
+    // quote lots for base lots at a price -- rounded up for buys, down for sells
+    fn quote_lots(&self, price_in_ticks: u64, base_lots: u64, round_up: bool) -> u128 {
+        let numerator = price_in_ticks as u128 * self.tick_size as u128 * base_lots as u128;
+        let denominator = self.base_lots_per_base_unit as u128;
+        if round_up { (numerator + denominator - 1) / denominator } else { numerator / denominator }
+    }
+
+    /// base lots bought with up to `quote_lots` (fees excluded), walking the asks
+    pub fn fill_buy(&self, quote_lots: u128) -> u64 {
+        let mut remaining = quote_lots;
+        let mut base_lots = 0;
+        for ask in &self.asks {
+            let price = ask.price_in_ticks as u128 * self.tick_size as u128;
+            if price == 0 {
+                continue;
+            }
+            // most lots whose cost (rounded up) fits
+            let affordable = remaining * self.base_lots_per_base_unit as u128 / price;
+            let fill = ask.base_lots.min(affordable.min(u64::MAX as u128) as u64);
+            if fill == 0 {
+                break;
+            }
+            remaining -= self.quote_lots(ask.price_in_ticks, fill, true);
+            base_lots += fill;
+            if fill < ask.base_lots {
+                break;
+            }
+        }
+        base_lots
+    }
+
+    /// quote lots received for `base_lots` (fees excluded), walking the bids
+    pub fn fill_sell(&self, base_lots: u64) -> u128 {
+        let mut remaining = base_lots;
+        let mut quote_lots = 0;
+        for bid in &self.bids {
+            let fill = bid.base_lots.min(remaining);
+            if fill == 0 {
+                break;
+            }
+            remaining -= fill;
+            quote_lots += self.quote_lots(bid.price_in_ticks, fill, false);
+        }
+        quote_lots
+    }
+
+    fn taker_fee(&self, quote_lots: u128) -> u128 {
+        (quote_lots * self.taker_fee_bps as u128 + BPS_DENOMINATOR - 1) / BPS_DENOMINATOR
+    }
+
+    /// quote lots available to a buy after its fee is set aside
+    pub fn buy_budget(&self, native_quote_in: u64) -> u128 {
+        let quote_lots = (native_quote_in / self.quote_lot_size) as u128;
+        quote_lots * BPS_DENOMINATOR / (BPS_DENOMINATOR + self.taker_fee_bps as u128)
+    }
+
+    /// buy: native quote in (fees included) -> native base out
+    pub fn quote_buy(&self, native_quote_in: u64) -> u128 {
+        self.fill_buy(self.buy_budget(native_quote_in)) as u128 * self.base_lot_size as u128
+    }
+
+    /// sell: native base in -> native quote out (fee taken)
+    pub fn quote_sell(&self, native_base_in: u64) -> u128 {
+        let quote_lots = self.fill_sell(native_base_in / self.base_lot_size);
+        (quote_lots - self.taker_fee(quote_lots)) * self.quote_lot_size as u128
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market(bids: Vec<Level>, asks: Vec<Level>) -> PhoenixMarket {
+        PhoenixMarket {
+            active: true,
+            base_lot_size: 1_000,
+            quote_lot_size: 1,
+            base_lots_per_base_unit: 1_000,
+            tick_size: 10,
+            taker_fee_bps: 5,
+            bids,
+            asks,
+            ..PhoenixMarket::default()
+        }
+    }
+
+    #[test]
+    fn walks_the_tree_in_price_order() {
+        let size = 4;
+        let mut tree = vec![0; tree_len(size)];
+        // root = 2, left = 1, right = 3 (expired), 4 is a freed node
+        let nodes: [(u32, u32, u64, u64, u64); 4] = [(0, 0, 100, 5, 0), (1, 3, 102, 2, 0), (0, 0, 105, 7, 10), (0, 0, 99, 9, 0)];
+        tree[0..4].copy_from_slice(&2_u32.to_le_bytes());
+        for (i, (left, right, price, lots, ts)) in nodes.iter().enumerate() {
+            let node = TREE_HEADER_LEN + i * NODE_LEN;
+            tree[node + LEFT..node + LEFT + 4].copy_from_slice(&left.to_le_bytes());
+            tree[node + RIGHT..node + RIGHT + 4].copy_from_slice(&right.to_le_bytes());
+            tree[node + PRICE_IN_TICKS..node + PRICE_IN_TICKS + 8].copy_from_slice(&price.to_le_bytes());
+            tree[node + NUM_BASE_LOTS..node + NUM_BASE_LOTS + 8].copy_from_slice(&lots.to_le_bytes());
+            tree[node + LAST_VALID_UNIX_TS..node + LAST_VALID_UNIX_TS + 8].copy_from_slice(&ts.to_le_bytes());
+        }
+        let bids = unpack_tree(&tree, size, true, 50).unwrap();
+        assert_eq!(bids, vec![
+            Level { price_in_ticks: 102, base_lots: 2 },
+            Level { price_in_ticks: 100, base_lots: 5 },
+        ]);
+    }
+
+    #[test]
+    fn buys_fit_the_budget() {
+        // 100 ticks * 10 / 1000 = 1 quote lot per base lot
+        let market = market(vec![], vec![
+            Level { price_in_ticks: 100, base_lots: 1_000 },
+            Level { price_in_ticks: 200, base_lots: 1_000 },
+        ]);
+        assert_eq!(market.fill_buy(1_500), 1_250);
+        // 0.05% fee is set aside before matching
+        assert_eq!(market.buy_budget(10_005), 10_000);
+        assert_eq!(market.fill_buy(100_000), 2_000);
+    }
+
+    #[test]
+    fn sells_pay_the_fee() {
+        let market = market(vec![Level { price_in_ticks: 100, base_lots: 10_000 }], vec![]);
+        // 5000 base lots -> 5000 quote lots, less 3 (2.5 rounded up)
+        assert_eq!(market.quote_sell(5_000_000), 4_997);
+        assert_eq!(market.quote_sell(20_000_000), (10_000 - 5));
+    }
+}
@@ -0,0 +1,188 @@
+//! pump.fun bonding curve account layouts + swap math
+//! (anchor accounts -- offsets include the 8 byte discriminator)
+//!
+//! the curve is constant product over *virtual* reserves; the real token
+//! reserves cap what a buy can take out. once `complete` is set the curve
+//! has graduated (migrated to pumpswap) and no longer trades.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use arrayref::array_ref;
+
+pub const BPS_DENOMINATOR: u128 = 10_000;
+
+// BondingCurve
+pub const BONDING_CURVE_LEN: usize = 81;
+const VIRTUAL_TOKEN_RESERVES: usize = 8;
+const VIRTUAL_SOL_RESERVES: usize = 16;
+const REAL_TOKEN_RESERVES: usize = 24;
+const REAL_SOL_RESERVES: usize = 32;
+const COMPLETE: usize = 48;
+const CREATOR: usize = 49;
+
+// Global
+pub const GLOBAL_LEN: usize = 162;
+const FEE_RECIPIENT: usize = 41;
+const FEE_BASIS_POINTS: usize = 105;
+const CREATOR_FEE_BASIS_POINTS: usize = 154;
+
+pub const BONDING_CURVE_SEED: &[u8] = b"bonding-curve";
+pub const CREATOR_VAULT_SEED: &[u8] = b"creator-vault";
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*array_ref![data, offset, 8])
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(*array_ref![data, offset, 32])
+}
+
+// fees round up
+fn fee(amount: u128, basis_points: u64) -> Option<u128> {
+    let numerator = amount.checked_mul(basis_points as u128)?;
+    Some((numerator + BPS_DENOMINATOR - 1) / BPS_DENOMINATOR)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BondingCurve {
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub complete: bool,
+    // default = no creator fee
+    pub creator: Pubkey,
+}
+
+impl BondingCurve {
+    pub fn unpack(data: &[u8], discriminator: &[u8; 8]) -> Option<Self> {
+        if data.len() < BONDING_CURVE_LEN || data[..8] != discriminator[..] {
+            return None;
+        }
+        Some(Self {
+            virtual_token_reserves: read_u64(data, VIRTUAL_TOKEN_RESERVES),
+            virtual_sol_reserves: read_u64(data, VIRTUAL_SOL_RESERVES),
+            real_token_reserves: read_u64(data, REAL_TOKEN_RESERVES),
+            real_sol_reserves: read_u64(data, REAL_SOL_RESERVES),
+            complete: data[COMPLETE] != 0,
+            creator: read_pubkey(data, CREATOR),
+        })
+    }
+
+    /// tokens out for `sol_in` lamports (fees included), capped at the real reserves
+    pub fn buy_tokens_out(&self, sol_in: u128, fee_basis_points: u64) -> Option<u128> {
+        if self.complete {
+            return None;
+        }
+        // the program rounds the fee up -- largest cost whose cost + fee fits in sol_in
+        let mut sol_cost = sol_in.checked_mul(BPS_DENOMINATOR)? / (BPS_DENOMINATOR + fee_basis_points as u128);
+        while sol_cost > 0 && sol_cost + fee(sol_cost, fee_basis_points)? > sol_in {
+            sol_cost -= 1;
+        }
+        // and the most tokens that cost (rounded up) covers
+        let virtual_token = self.virtual_token_reserves as u128;
+        let virtual_sol = self.virtual_sol_reserves as u128;
+        let tokens_out = virtual_token.checked_mul(sol_cost)? / virtual_sol.checked_add(sol_cost)?;
+        Some(tokens_out.min(self.real_token_reserves as u128))
+    }
+
+    /// lamports (fees included) a buy of exactly `tokens_out` costs
+    pub fn buy_sol_cost(&self, tokens_out: u128, fee_basis_points: u64) -> Option<u128> {
+        let remaining = (self.virtual_token_reserves as u128).checked_sub(tokens_out)?;
+        if remaining == 0 {
+            return None;
+        }
+        let numerator = (self.virtual_sol_reserves as u128).checked_mul(tokens_out)?;
+        let sol_cost = (numerator + remaining - 1) / remaining;
+        sol_cost.checked_add(fee(sol_cost, fee_basis_points)?)
+    }
+
+    /// lamports out (fees taken) for selling `tokens_in`
+    pub fn sell_sol_out(&self, tokens_in: u128, fee_basis_points: u64) -> Option<u128> {
+        if self.complete {
+            return None;
+        }
+        let virtual_token = self.virtual_token_reserves as u128;
+        let virtual_sol = self.virtual_sol_reserves as u128;
+        let sol_out = virtual_sol.checked_mul(tokens_in)? / virtual_token.checked_add(tokens_in)?;
+        // the curve cant pay out more sol than was put in
+        if sol_out > self.real_sol_reserves as u128 {
+            return None;
+        }
+        sol_out.checked_sub(fee(sol_out, fee_basis_points)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PumpGlobal {
+    pub fee_recipient: Pubkey,
+    pub fee_basis_points: u64,
+    pub creator_fee_basis_points: u64,
+}
+
+impl PumpGlobal {
+    pub fn unpack(data: &[u8], discriminator: &[u8; 8]) -> Option<Self> {
+        if data.len() < GLOBAL_LEN || data[..8] != discriminator[..] {
+            return None;
+        }
+        Some(Self {
+            fee_recipient: read_pubkey(data, FEE_RECIPIENT),
+            fee_basis_points: read_u64(data, FEE_BASIS_POINTS),
+            creator_fee_basis_points: read_u64(data, CREATOR_FEE_BASIS_POINTS),
+        })
+    }
+
+    /// protocol + creator fee (the creator fee only applies to curves with a creator)
+    pub fn total_fee_basis_points(&self, curve: &BondingCurve) -> u64 {
+        let creator = if curve.creator != Pubkey::default() { self.creator_fee_basis_points } else { 0 };
+        self.fee_basis_points + creator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a fresh curve
+    fn curve() -> BondingCurve {
+        BondingCurve {
+            virtual_token_reserves: 1_073_000_000_000_000,
+            virtual_sol_reserves: 30_000_000_000,
+            real_token_reserves: 793_100_000_000_000,
+            real_sol_reserves: 0,
+            complete: false,
+            creator: Pubkey::default(),
+        }
+    }
+
+    #[test]
+    fn buy_then_sell_loses_the_fees() {
+        let mut curve = curve();
+        let sol_in = 1_000_000_000;
+        let tokens = curve.buy_tokens_out(sol_in, 100).unwrap();
+        // 1 sol (less 1%) into 30 virtual sol
+        let expected = 1_073_000_000_000_000_u128 * 990_099_009 / (30_000_000_000 + 990_099_009);
+        assert!(tokens <= expected && expected - tokens < 100_000);
+        assert!(curve.buy_sol_cost(tokens, 100).unwrap() <= sol_in);
+        assert!(curve.buy_sol_cost(tokens + 1, 100).unwrap() > sol_in);
+
+        curve.virtual_token_reserves -= tokens as u64;
+        curve.virtual_sol_reserves += 990_099_009;
+        curve.real_sol_reserves += 990_099_009;
+        let sol_out = curve.sell_sol_out(tokens, 100).unwrap();
+        assert!(sol_out < 990_099_009 && sol_out >= 980_198_010);
+    }
+
+    #[test]
+    fn buys_are_capped_at_the_real_reserves() {
+        let curve = curve();
+        let tokens = curve.buy_tokens_out(1_000_000_000_000, 100).unwrap();
+        assert_eq!(tokens, curve.real_token_reserves as u128);
+    }
+
+    #[test]
+    fn graduated_curves_dont_quote() {
+        let curve = BondingCurve { complete: true, ..curve() };
+        assert_eq!(curve.buy_tokens_out(1_000, 100), None);
+        assert_eq!(curve.sell_sol_out(1_000, 100), None);
+    }
+}
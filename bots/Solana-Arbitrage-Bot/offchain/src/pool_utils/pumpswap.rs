@@ -0,0 +1,165 @@
+//! PumpSwap (pump amm) account layouts + swap math
+//! (anchor accounts -- offsets include the 8 byte discriminator)
+//!
+//! every fee is charged on the quote side: added on top of the quote paid
+//! for buys, cut from the quote received for sells (each rounded up)
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use arrayref::array_ref;
+
+pub const BPS_DENOMINATOR: u128 = 10_000;
+
+// Pool
+pub const POOL_LEN: usize = 243;
+const COIN_CREATOR: usize = 211;
+
+// GlobalConfig
+pub const GLOBAL_CONFIG_LEN: usize = 321;
+const LP_FEE_BASIS_POINTS: usize = 40;
+const PROTOCOL_FEE_BASIS_POINTS: usize = 48;
+const DISABLE_FLAGS: usize = 56;
+const PROTOCOL_FEE_RECIPIENTS: usize = 57;
+const COIN_CREATOR_FEE_BASIS_POINTS: usize = 313;
+
+// disable_flags bits
+const BUY_DISABLED: u8 = 1 << 3;
+const SELL_DISABLED: u8 = 1 << 4;
+
+pub const CREATOR_VAULT_SEED: &[u8] = b"creator_vault";
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*array_ref![data, offset, 8])
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(*array_ref![data, offset, 32])
+}
+
+/// the pool's coin creator (default = no creator fee)
+pub fn unpack_coin_creator(data: &[u8], discriminator: &[u8; 8]) -> Option<Pubkey> {
+    if data.len() < POOL_LEN || data[..8] != discriminator[..] {
+        return None;
+    }
+    Some(read_pubkey(data, COIN_CREATOR))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GlobalConfig {
+    pub lp_fee_basis_points: u64,
+    pub protocol_fee_basis_points: u64,
+    pub coin_creator_fee_basis_points: u64,
+    pub disable_flags: u8,
+    // the first of the protocol's fee recipients
+    pub protocol_fee_recipient: Pubkey,
+}
+
+impl GlobalConfig {
+    pub fn unpack(data: &[u8], discriminator: &[u8; 8]) -> Option<Self> {
+        if data.len() < GLOBAL_CONFIG_LEN || data[..8] != discriminator[..] {
+            return None;
+        }
+        Some(Self {
+            lp_fee_basis_points: read_u64(data, LP_FEE_BASIS_POINTS),
+            protocol_fee_basis_points: read_u64(data, PROTOCOL_FEE_BASIS_POINTS),
+            coin_creator_fee_basis_points: read_u64(data, COIN_CREATOR_FEE_BASIS_POINTS),
+            disable_flags: data[DISABLE_FLAGS],
+            protocol_fee_recipient: read_pubkey(data, PROTOCOL_FEE_RECIPIENTS),
+        })
+    }
+
+    pub fn buy_enabled(&self) -> bool {
+        self.disable_flags & BUY_DISABLED == 0
+    }
+
+    pub fn sell_enabled(&self) -> bool {
+        self.disable_flags & SELL_DISABLED == 0
+    }
+
+    /// fee rates charged on a swap -- the creator fee only applies to pools with a creator
+    pub fn fee_basis_points(&self, has_coin_creator: bool) -> [u64; 3] {
+        let creator = if has_coin_creator { self.coin_creator_fee_basis_points } else { 0 };
+        [self.lp_fee_basis_points, self.protocol_fee_basis_points, creator]
+    }
+}
+
+fn fee(amount: u128, basis_points: u64) -> Option<u128> {
+    let numerator = amount.checked_mul(basis_points as u128)?;
+    Some((numerator + BPS_DENOMINATOR - 1) / BPS_DENOMINATOR)
+}
+
+fn total_fees(amount: u128, fee_basis_points: &[u64; 3]) -> Option<u128> {
+    fee_basis_points
+        .iter()
+        .try_fold(0_u128, |total, bps| total.checked_add(fee(amount, *bps)?))
+}
+
+/// quote paid (fees included) to buy exactly `base_out`
+pub fn buy_quote_in(base_out: u128, base_reserve: u128, quote_reserve: u128, fee_basis_points: &[u64; 3]) -> Option<u128> {
+    let remaining = base_reserve.checked_sub(base_out)?;
+    if remaining == 0 {
+        return None;
+    }
+    let numerator = quote_reserve.checked_mul(base_out)?;
+    let quote_in = (numerator + remaining - 1) / remaining;
+    quote_in.checked_add(total_fees(quote_in, fee_basis_points)?)
+}
+
+/// most base a buy with `quote_in` (fees included) can take out
+pub fn buy_base_out(quote_in: u128, base_reserve: u128, quote_reserve: u128, fee_basis_points: &[u64; 3]) -> Option<u128> {
+    let total_bps = fee_basis_points.iter().map(|bps| *bps as u128).sum::<u128>();
+    // each fee rounds up on-chain -- largest quote whose quote + fees fits in quote_in
+    let mut effective_quote = quote_in.checked_mul(BPS_DENOMINATOR)? / (BPS_DENOMINATOR + total_bps);
+    while effective_quote > 0 && effective_quote + total_fees(effective_quote, fee_basis_points)? > quote_in {
+        effective_quote -= 1;
+    }
+    // and the most base that quote (rounded up) covers
+    base_reserve.checked_mul(effective_quote)?.checked_div(quote_reserve.checked_add(effective_quote)?)
+}
+
+/// quote received (fees taken) for selling `base_in`
+pub fn sell_quote_out(base_in: u128, base_reserve: u128, quote_reserve: u128, fee_basis_points: &[u64; 3]) -> Option<u128> {
+    let quote_out = quote_reserve.checked_mul(base_in)? / base_reserve.checked_add(base_in)?;
+    quote_out.checked_sub(total_fees(quote_out, fee_basis_points)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 20 lp, 5 protocol, 5 creator
+    const FEES: [u64; 3] = [20, 5, 5];
+
+    #[test]
+    fn buys_fit_the_quote_paid() {
+        let (base, quote) = (1_000_000_000_000_000, 80_000_000_000);
+        for quote_in in [1_000, 1_000_000, 1_000_000_000] {
+            let base_out = buy_base_out(quote_in, base, quote, &FEES).unwrap();
+            assert!(base_out > 0);
+            assert!(buy_quote_in(base_out, base, quote, &FEES).unwrap() <= quote_in);
+            // and its the most that fits
+            assert!(buy_quote_in(base_out + 1, base, quote, &FEES).unwrap() > quote_in);
+        }
+    }
+
+    #[test]
+    fn sell_fees_come_off_the_quote() {
+        let (base, quote) = (1_000_000_000, 1_000_000_000);
+        let no_fees = sell_quote_out(1_000_000, base, quote, &[0, 0, 0]).unwrap();
+        assert_eq!(no_fees, 999_000);
+        // 0.3% of 999_000, each of the three fees rounded up
+        let out = sell_quote_out(1_000_000, base, quote, &FEES).unwrap();
+        assert_eq!(out, 999_000 - 1_998 - 500 - 500);
+    }
+
+    #[test]
+    fn creator_fee_only_with_a_creator() {
+        let config = GlobalConfig {
+            lp_fee_basis_points: 20,
+            protocol_fee_basis_points: 5,
+            coin_creator_fee_basis_points: 5,
+            ..GlobalConfig::default()
+        };
+        assert_eq!(config.fee_basis_points(true), FEES);
+        assert_eq!(config.fee_basis_points(false), [20, 5, 0]);
+    }
+}
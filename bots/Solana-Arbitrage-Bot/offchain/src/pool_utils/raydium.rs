@@ -0,0 +1,119 @@
+//! Raydium AMM v4 account layouts + swap math
+//! (offsets from raydium-amm's `AmmInfo` and serum's `OpenOrders`)
+
+use arrayref::array_ref;
+
+// AmmInfo
+pub const AMM_INFO_LEN: usize = 752;
+const SWAP_FEE_NUMERATOR: usize = 176;
+const SWAP_FEE_DENOMINATOR: usize = 184;
+const NEED_TAKE_PNL_COIN: usize = 192;
+const NEED_TAKE_PNL_PC: usize = 200;
+
+// serum OpenOrders (after the 5 byte "serum" padding)
+pub const OPEN_ORDERS_LEN: usize = 3228;
+const NATIVE_COIN_TOTAL: usize = 85;
+const NATIVE_PC_TOTAL: usize = 101;
+
+// swap_base_in instruction tag
+pub const SWAP_BASE_IN_TAG: u8 = 9;
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*array_ref![data, offset, 8])
+}
+
+/// the parts of AmmInfo which move the quote
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AmmState {
+    pub swap_fee_numerator: u64,
+    pub swap_fee_denominator: u64,
+    pub need_take_pnl_coin: u64,
+    pub need_take_pnl_pc: u64,
+}
+
+impl AmmState {
+    pub fn unpack(data: &[u8]) -> Option<Self> {
+        if data.len() < AMM_INFO_LEN {
+            return None;
+        }
+        Some(Self {
+            swap_fee_numerator: read_u64(data, SWAP_FEE_NUMERATOR),
+            swap_fee_denominator: read_u64(data, SWAP_FEE_DENOMINATOR),
+            need_take_pnl_coin: read_u64(data, NEED_TAKE_PNL_COIN),
+            need_take_pnl_pc: read_u64(data, NEED_TAKE_PNL_PC),
+        })
+    }
+}
+
+/// (native_coin_total, native_pc_total) -- liquidity the amm has sitting on the book
+pub fn unpack_open_orders_totals(data: &[u8]) -> Option<(u64, u64)> {
+    if data.len() < OPEN_ORDERS_LEN {
+        return None;
+    }
+    Some((
+        read_u64(data, NATIVE_COIN_TOTAL),
+        read_u64(data, NATIVE_PC_TOTAL),
+    ))
+}
+
+/// reserves the program prices against: vault + open orders - pnl owed to the protocol
+pub fn total_reserve(vault: u128, open_orders_total: u128, need_take_pnl: u128) -> Option<u128> {
+    vault.checked_add(open_orders_total)?.checked_sub(need_take_pnl)
+}
+
+/// swap_base_in: fee (rounded up) comes off the input, then x * y = k
+pub fn swap_base_in(
+    amount_in: u128,
+    reserve_in: u128,
+    reserve_out: u128,
+    fee_numerator: u128,
+    fee_denominator: u128,
+) -> Option<u128> {
+    if fee_denominator == 0 {
+        return None;
+    }
+    let fee = amount_in
+        .checked_mul(fee_numerator)?
+        .checked_add(fee_denominator - 1)?
+        / fee_denominator;
+    let amount_in = amount_in.checked_sub(fee)?;
+    let denominator = reserve_in.checked_add(amount_in)?;
+    if denominator == 0 {
+        return Some(0);
+    }
+    reserve_out.checked_mul(amount_in)?.checked_div(denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fee_rounds_up() {
+        // 25 bps of 1 = 0.0025 -> 1 -> nothing left to swap
+        assert_eq!(swap_base_in(1, 1_000, 1_000, 25, 10_000), Some(0));
+        // 0.25% fee, 1:1 pool
+        let out = swap_base_in(1_000_000, 1_000_000_000_000, 1_000_000_000_000, 25, 10_000);
+        assert_eq!(out, Some(997_499));
+    }
+
+    #[test]
+    fn pnl_is_taken_out_of_reserves() {
+        assert_eq!(total_reserve(1_000, 200, 50), Some(1_150));
+        assert_eq!(total_reserve(10, 0, 50), None);
+    }
+
+    #[test]
+    fn unpacks_amm_state() {
+        let mut data = vec![0; AMM_INFO_LEN];
+        data[SWAP_FEE_NUMERATOR..SWAP_FEE_NUMERATOR + 8].copy_from_slice(&25_u64.to_le_bytes());
+        data[SWAP_FEE_DENOMINATOR..SWAP_FEE_DENOMINATOR + 8]
+            .copy_from_slice(&10_000_u64.to_le_bytes());
+        data[NEED_TAKE_PNL_PC..NEED_TAKE_PNL_PC + 8].copy_from_slice(&7_u64.to_le_bytes());
+        let state = AmmState::unpack(&data).unwrap();
+        assert_eq!(state.swap_fee_numerator, 25);
+        assert_eq!(state.swap_fee_denominator, 10_000);
+        assert_eq!(state.need_take_pnl_pc, 7);
+        assert!(AmmState::unpack(&data[..100]).is_none());
+    }
+}
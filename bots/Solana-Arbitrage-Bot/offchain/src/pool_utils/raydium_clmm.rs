@@ -0,0 +1,307 @@
+//! Raydium CLMM account layouts
+//! (anchor zero-copy accounts -- offsets include the 8 byte discriminator)
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use arrayref::array_ref;
+
+use crate::pool_utils::clmm::{tick_array_start, TickLiquidity, MAX_TICK, MIN_TICK};
+
+// PoolState (repr(packed))
+pub const POOL_STATE_LEN: usize = 1544;
+const TICK_SPACING: usize = 235;
+const LIQUIDITY: usize = 237;
+const SQRT_PRICE_X64: usize = 253;
+const TICK_CURRENT: usize = 269;
+const POOL_STATUS: usize = 389;
+const TICK_ARRAY_BITMAP: usize = 904;
+
+// AmmConfig
+pub const AMM_CONFIG_LEN: usize = 117;
+const TRADE_FEE_RATE: usize = 47;
+
+// TickArrayState
+pub const TICK_ARRAY_SIZE: i32 = 60;
+pub const TICK_ARRAY_LEN: usize = 10240;
+const TICK_ARRAY_START: usize = 40;
+const TICKS: usize = 44;
+const TICK_LEN: usize = 168;
+const TICK_LIQUIDITY_NET: usize = 4;
+const TICK_LIQUIDITY_GROSS: usize = 20;
+
+// TickArrayBitmapExtension: 14 bitmaps of 512 arrays on each side of the pool's own bitmap
+pub const BITMAP_EXTENSION_LEN: usize = 1832;
+const POSITIVE_BITMAPS: usize = 40;
+const NEGATIVE_BITMAPS: usize = 936;
+const EXTENSION_BITMAPS: usize = 14;
+const BITMAP_BITS: i32 = 512;
+
+// bit 4 of the pool status disables swaps
+const SWAP_DISABLED: u8 = 1 << 4;
+
+pub const TICK_ARRAY_SEED: &[u8] = b"tick_array";
+pub const BITMAP_EXTENSION_SEED: &[u8] = b"pool_tick_array_bitmap_extension";
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*array_ref![data, offset, 8])
+}
+
+fn read_u128(data: &[u8], offset: usize) -> u128 {
+    u128::from_le_bytes(*array_ref![data, offset, 16])
+}
+
+fn read_i32(data: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(*array_ref![data, offset, 4])
+}
+
+fn read_bitmap(data: &[u8], offset: usize, words: usize) -> Vec<u64> {
+    (0..words).map(|i| read_u64(data, offset + i * 8)).collect()
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClmmPoolState {
+    pub tick_spacing: u16,
+    pub liquidity: u128,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    pub status: u8,
+    // 1024 tick arrays around 0, (bit i = array starting at (i - 512) * span)
+    pub tick_array_bitmap: Vec<u64>,
+}
+
+impl ClmmPoolState {
+    pub fn unpack(data: &[u8], discriminator: &[u8; 8]) -> Option<Self> {
+        if data.len() < POOL_STATE_LEN || data[..8] != discriminator[..] {
+            return None;
+        }
+        Some(Self {
+            tick_spacing: u16::from_le_bytes(*array_ref![data, TICK_SPACING, 2]),
+            liquidity: read_u128(data, LIQUIDITY),
+            sqrt_price_x64: read_u128(data, SQRT_PRICE_X64),
+            tick_current: read_i32(data, TICK_CURRENT),
+            status: data[POOL_STATUS],
+            tick_array_bitmap: read_bitmap(data, TICK_ARRAY_BITMAP, 16),
+        })
+    }
+
+    pub fn swap_enabled(&self) -> bool {
+        self.status & SWAP_DISABLED == 0
+    }
+
+    pub fn tick_array_span(&self) -> i32 {
+        self.tick_spacing as i32 * TICK_ARRAY_SIZE
+    }
+
+    /// start ticks of every initialized tick array, sorted
+    pub fn initialized_tick_arrays(&self, extension: Option<&BitmapExtension>) -> Vec<i32> {
+        let span = self.tick_array_span();
+        let mut starts = vec![];
+        for (i, set) in set_bits(&self.tick_array_bitmap).into_iter().enumerate() {
+            if set {
+                starts.push((i as i32 - BITMAP_BITS) * span);
+            }
+        }
+        if let Some(extension) = extension {
+            starts.extend(extension.initialized_tick_arrays(span));
+        }
+        starts.sort_unstable();
+        starts.dedup();
+        starts
+    }
+}
+
+fn set_bits(words: &[u64]) -> Vec<bool> {
+    words
+        .iter()
+        .flat_map(|word| (0..64).map(move |bit| word & (1 << bit) != 0))
+        .collect()
+}
+
+/// trade fee rate out of 1e6
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClmmAmmConfig {
+    pub trade_fee_rate: u32,
+}
+
+impl ClmmAmmConfig {
+    pub fn unpack(data: &[u8], discriminator: &[u8; 8]) -> Option<Self> {
+        if data.len() < AMM_CONFIG_LEN || data[..8] != discriminator[..] {
+            return None;
+        }
+        Some(Self {
+            trade_fee_rate: u32::from_le_bytes(*array_ref![data, TRADE_FEE_RATE, 4]),
+        })
+    }
+}
+
+/// tick arrays past the pool's own bitmap
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BitmapExtension {
+    pub positive: Vec<Vec<u64>>,
+    pub negative: Vec<Vec<u64>>,
+}
+
+impl BitmapExtension {
+    pub fn unpack(data: &[u8], discriminator: &[u8; 8]) -> Option<Self> {
+        if data.len() < BITMAP_EXTENSION_LEN || data[..8] != discriminator[..] {
+            return None;
+        }
+        let bitmaps = |offset: usize| -> Vec<Vec<u64>> {
+            (0..EXTENSION_BITMAPS)
+                .map(|i| read_bitmap(data, offset + i * 64, 8))
+                .collect()
+        };
+        Some(Self {
+            positive: bitmaps(POSITIVE_BITMAPS),
+            negative: bitmaps(NEGATIVE_BITMAPS),
+        })
+    }
+
+    pub fn initialized_tick_arrays(&self, span: i32) -> Vec<i32> {
+        let mut starts = vec![];
+        for (i, bitmap) in self.positive.iter().enumerate() {
+            let offset = (i as i32 + 1) * BITMAP_BITS;
+            for (bit, set) in set_bits(bitmap).into_iter().enumerate() {
+                if set {
+                    starts.push((offset + bit as i32) * span);
+                }
+            }
+        }
+        // negative bitmaps count down from the far end (bit 0 = the bitmap's
+        // far boundary, ie, the array at -(i + 2) * BITMAP_BITS)
+        for (i, bitmap) in self.negative.iter().enumerate() {
+            for (bit, set) in set_bits(bitmap).into_iter().enumerate() {
+                if set {
+                    starts.push(-((i as i32 + 2) * BITMAP_BITS - bit as i32) * span);
+                }
+            }
+        }
+        starts
+    }
+}
+
+/// initialized ticks from a TickArrayState, (start tick, ticks)
+pub fn unpack_tick_array(data: &[u8], discriminator: &[u8; 8]) -> Option<(i32, Vec<TickLiquidity>)> {
+    if data.len() < TICK_ARRAY_LEN || data[..8] != discriminator[..] {
+        return None;
+    }
+    let start = read_i32(data, TICK_ARRAY_START);
+    let ticks = (0..TICK_ARRAY_SIZE as usize)
+        .map(|i| TICKS + i * TICK_LEN)
+        .filter(|offset| read_u128(data, offset + TICK_LIQUIDITY_GROSS) > 0)
+        .map(|offset| TickLiquidity {
+            tick: read_i32(data, offset),
+            liquidity_net: i128::from_le_bytes(*array_ref![data, offset + TICK_LIQUIDITY_NET, 16]),
+        })
+        .collect();
+    Some((start, ticks))
+}
+
+pub fn tick_array_address(program_id: &Pubkey, pool: &Pubkey, start: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[TICK_ARRAY_SEED, pool.as_ref(), &start.to_be_bytes()],
+        program_id,
+    ).0
+}
+
+pub fn bitmap_extension_address(program_id: &Pubkey, pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[BITMAP_EXTENSION_SEED, pool.as_ref()], program_id).0
+}
+
+/// the initialized arrays a swap could walk into: up to `n` at/below the
+/// current array and `n` above it. returns (starts, [lower, upper) tick coverage)
+pub fn tick_arrays_around(
+    initialized: &[i32],
+    tick_current: i32,
+    tick_spacing: u16,
+    n: usize,
+) -> (Vec<i32>, (i32, i32)) {
+    let span = tick_spacing as i32 * TICK_ARRAY_SIZE;
+    let current = tick_array_start(tick_current, tick_spacing, TICK_ARRAY_SIZE);
+    let below: Vec<i32> = initialized.iter().rev().filter(|s| **s <= current).take(n).cloned().collect();
+    let above: Vec<i32> = initialized.iter().filter(|s| **s > current).take(n).cloned().collect();
+
+    // past the last loaded array we dont know what is there -- unless there
+    // are no more initialized arrays that way
+    let lower = if below.len() < n { MIN_TICK } else { *below.last().unwrap() };
+    let upper = if above.len() < n { MAX_TICK } else { above.last().unwrap() + span };
+
+    let mut starts = below;
+    starts.reverse();
+    starts.extend(above);
+    (starts, (lower, upper))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::anchor_discriminator;
+
+    #[test]
+    fn unpacks_tick_arrays() {
+        let disc = anchor_discriminator("account", "TickArrayState");
+        let mut data = vec![0; TICK_ARRAY_LEN];
+        data[..8].copy_from_slice(&disc);
+        data[TICK_ARRAY_START..TICK_ARRAY_START + 4].copy_from_slice(&(-600_i32).to_le_bytes());
+        // one initialized tick in slot 3
+        let offset = TICKS + 3 * TICK_LEN;
+        data[offset..offset + 4].copy_from_slice(&(-570_i32).to_le_bytes());
+        data[offset + TICK_LIQUIDITY_NET..offset + TICK_LIQUIDITY_NET + 16]
+            .copy_from_slice(&(-5_000_i128).to_le_bytes());
+        data[offset + TICK_LIQUIDITY_GROSS..offset + TICK_LIQUIDITY_GROSS + 16]
+            .copy_from_slice(&5_000_u128.to_le_bytes());
+
+        let (start, ticks) = unpack_tick_array(&data, &disc).unwrap();
+        assert_eq!(start, -600);
+        assert_eq!(ticks, vec![TickLiquidity { tick: -570, liquidity_net: -5_000 }]);
+    }
+
+    #[test]
+    fn decodes_bitmaps() {
+        let pool = ClmmPoolState {
+            tick_spacing: 10,
+            // bit 512 = the array at 0, bit 511 = the one below it
+            tick_array_bitmap: {
+                let mut bitmap = vec![0; 16];
+                bitmap[8] = 1;
+                bitmap[7] = 1 << 63;
+                bitmap
+            },
+            ..ClmmPoolState::default()
+        };
+        let mut extension = BitmapExtension {
+            positive: vec![vec![0; 8]; EXTENSION_BITMAPS],
+            negative: vec![vec![0; 8]; EXTENSION_BITMAPS],
+        };
+        extension.positive[0][0] = 1; // the first array past the pool's bitmap
+        extension.negative[0][0] = 1 << 1; // the first array below it
+
+        let span = 600;
+        assert_eq!(
+            pool.initialized_tick_arrays(Some(&extension)),
+            vec![-1023 * span, -span, 0, 512 * span],
+        );
+        assert_eq!(pool.initialized_tick_arrays(None), vec![-span, 0]);
+
+        // bit 0 is the far end of the first negative bitmap, not the array
+        // next to the pool's bitmap
+        extension.negative[0][0] = 1;
+        extension.negative[1][0] = 1;
+        assert_eq!(extension.initialized_tick_arrays(span), vec![512 * span, -1024 * span, -1536 * span]);
+    }
+
+    #[test]
+    fn picks_arrays_in_both_directions() {
+        let initialized = vec![-1800, -600, 0, 1200, 6000];
+        let (starts, range) = tick_arrays_around(&initialized, 30, 10, 2);
+        assert_eq!(starts, vec![-600, 0, 1200, 6000]);
+        // only 2 arrays loaded each way -> thats as far as a quote can walk
+        assert_eq!(range, (-600, 6600));
+
+        let (starts, range) = tick_arrays_around(&initialized, 30, 10, 3);
+        assert_eq!(starts, vec![-1800, -600, 0, 1200, 6000]);
+        assert_eq!(range, (-1800, MAX_TICK));
+
+        let (_, range) = tick_arrays_around(&[], 30, 10, 3);
+        assert_eq!(range, (MIN_TICK, MAX_TICK));
+    }
+}
@@ -0,0 +1,167 @@
+//! Raydium CPMM (cp-swap) account layouts + swap math
+//! (both accounts are anchor accounts -- offsets include the 8 byte discriminator)
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use arrayref::array_ref;
+
+use crate::pool_utils::constant_product;
+
+pub const FEE_RATE_DENOMINATOR: u128 = 1_000_000;
+
+// PoolState (repr(packed))
+pub const POOL_STATE_LEN: usize = 637;
+const POOL_STATUS: usize = 329;
+const PROTOCOL_FEES_TOKEN_0: usize = 341;
+const PROTOCOL_FEES_TOKEN_1: usize = 349;
+const FUND_FEES_TOKEN_0: usize = 357;
+const FUND_FEES_TOKEN_1: usize = 365;
+const OPEN_TIME: usize = 373;
+
+// AmmConfig
+pub const AMM_CONFIG_LEN: usize = 236;
+const TRADE_FEE_RATE: usize = 12;
+const PROTOCOL_FEE_RATE: usize = 20;
+const FUND_FEE_RATE: usize = 28;
+
+// bit 2 of the pool status disables swaps
+const SWAP_DISABLED: u8 = 1 << 2;
+
+// pda for the vaults + lp mint
+pub const AUTH_SEED: &[u8] = b"vault_and_lp_mint_auth_seed";
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*array_ref![data, offset, 8])
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CpmmPoolState {
+    pub status: u8,
+    // fees owed to the protocol / fund still sitting in the vaults
+    pub protocol_fees: [u64; 2],
+    pub fund_fees: [u64; 2],
+    pub open_time: u64,
+}
+
+impl CpmmPoolState {
+    pub fn unpack(data: &[u8], discriminator: &[u8; 8]) -> Option<Self> {
+        if data.len() < POOL_STATE_LEN || data[..8] != discriminator[..] {
+            return None;
+        }
+        Some(Self {
+            status: data[POOL_STATUS],
+            protocol_fees: [
+                read_u64(data, PROTOCOL_FEES_TOKEN_0),
+                read_u64(data, PROTOCOL_FEES_TOKEN_1),
+            ],
+            fund_fees: [
+                read_u64(data, FUND_FEES_TOKEN_0),
+                read_u64(data, FUND_FEES_TOKEN_1),
+            ],
+            open_time: read_u64(data, OPEN_TIME),
+        })
+    }
+
+    pub fn swap_enabled(&self) -> bool {
+        self.status & SWAP_DISABLED == 0
+    }
+
+    /// vault amount minus the fees which dont belong to LPs
+    pub fn reserve(&self, token_idx: usize, vault_amount: u128) -> Option<u128> {
+        vault_amount
+            .checked_sub(self.protocol_fees[token_idx] as u128)?
+            .checked_sub(self.fund_fees[token_idx] as u128)
+    }
+}
+
+/// fee rates out of FEE_RATE_DENOMINATOR (protocol + fund fees are cut from the trade fee)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CpmmAmmConfig {
+    pub trade_fee_rate: u64,
+    pub protocol_fee_rate: u64,
+    pub fund_fee_rate: u64,
+}
+
+impl CpmmAmmConfig {
+    pub fn unpack(data: &[u8], discriminator: &[u8; 8]) -> Option<Self> {
+        if data.len() < AMM_CONFIG_LEN || data[..8] != discriminator[..] {
+            return None;
+        }
+        Some(Self {
+            trade_fee_rate: read_u64(data, TRADE_FEE_RATE),
+            protocol_fee_rate: read_u64(data, PROTOCOL_FEE_RATE),
+            fund_fee_rate: read_u64(data, FUND_FEE_RATE),
+        })
+    }
+}
+
+/// swap_base_input: trade fee (rounded up) off the input, then x * y = k
+pub fn swap_base_input(
+    amount_in: u128,
+    reserve_in: u128,
+    reserve_out: u128,
+    trade_fee_rate: u64,
+) -> Option<u128> {
+    let fee = amount_in
+        .checked_mul(trade_fee_rate as u128)?
+        .checked_add(FEE_RATE_DENOMINATOR - 1)?
+        / FEE_RATE_DENOMINATOR;
+    let amount_in = amount_in.checked_sub(fee)?;
+    if amount_in == 0 {
+        return Some(0);
+    }
+    Some(
+        constant_product::swap(amount_in, reserve_in, reserve_out)
+            .map(|result| result.destination_amount_swapped)
+            .unwrap_or(0),
+    )
+}
+
+pub fn authority(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[AUTH_SEED], program_id).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::anchor_discriminator;
+
+    #[test]
+    fn unpacks_config_fee_rates() {
+        let disc = anchor_discriminator("account", "AmmConfig");
+        let mut data = vec![0; AMM_CONFIG_LEN];
+        data[..8].copy_from_slice(&disc);
+        data[TRADE_FEE_RATE..TRADE_FEE_RATE + 8].copy_from_slice(&2_500_u64.to_le_bytes());
+        data[PROTOCOL_FEE_RATE..PROTOCOL_FEE_RATE + 8].copy_from_slice(&120_000_u64.to_le_bytes());
+        data[FUND_FEE_RATE..FUND_FEE_RATE + 8].copy_from_slice(&40_000_u64.to_le_bytes());
+
+        let config = CpmmAmmConfig::unpack(&data, &disc).unwrap();
+        assert_eq!(config.trade_fee_rate, 2_500);
+        assert_eq!(config.protocol_fee_rate, 120_000);
+        assert_eq!(config.fund_fee_rate, 40_000);
+
+        // wrong account type
+        let pool_disc = anchor_discriminator("account", "PoolState");
+        assert!(CpmmAmmConfig::unpack(&data, &pool_disc).is_none());
+    }
+
+    #[test]
+    fn fees_owed_are_not_reserves() {
+        let state = CpmmPoolState {
+            protocol_fees: [10, 0],
+            fund_fees: [5, 0],
+            ..CpmmPoolState::default()
+        };
+        assert_eq!(state.reserve(0, 1_000), Some(985));
+        assert_eq!(state.reserve(1, 1_000), Some(1_000));
+        assert!(state.swap_enabled());
+    }
+
+    #[test]
+    fn quote_takes_the_trade_fee() {
+        let depth = 1_000_000_000_000;
+        // 0.25% fee on a deep 1:1 pool
+        let out = swap_base_input(1_000_000, depth, depth, 2_500).unwrap();
+        assert!(out < 997_500 && out >= 997_498);
+        assert_eq!(swap_base_input(1_000_000, depth, depth, 0).unwrap(), 999_999);
+    }
+}
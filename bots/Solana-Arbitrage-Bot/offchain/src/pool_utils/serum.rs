@@ -141,4 +141,193 @@ impl FeeTier {
 #[inline]
 pub fn referrer_rebate(amount: u64) -> u64 {
     amount / 5
-}
\ No newline at end of file
+}
+// READ-ONLY ORDERBOOK
+// market + slab accounts start with 5 bytes of "serum" padding
+const ACCOUNT_HEAD_PADDING: usize = 5;
+const MARKET_STATE_LEN: usize = 376;
+const COIN_LOT_SIZE: usize = ACCOUNT_HEAD_PADDING + 344;
+const PC_LOT_SIZE: usize = ACCOUNT_HEAD_PADDING + 352;
+
+// slab: padding, account flags (u64), header, then the node array
+const SLAB_BUMP_INDEX: usize = ACCOUNT_HEAD_PADDING + 8;
+const SLAB_NODES: usize = SLAB_BUMP_INDEX + 32;
+const SLAB_NODE_LEN: usize = 72;
+const LEAF_TAG: u32 = 2;
+const LEAF_KEY: usize = 8;
+const LEAF_QUANTITY: usize = 56;
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// (coin lot size, pc lot size) from a market account
+pub fn unpack_lot_sizes(data: &[u8]) -> Option<(u64, u64)> {
+    if data.len() < ACCOUNT_HEAD_PADDING + MARKET_STATE_LEN {
+        return None;
+    }
+    let lot_sizes = (read_u64(data, COIN_LOT_SIZE)?, read_u64(data, PC_LOT_SIZE)?);
+    if lot_sizes.0 == 0 || lot_sizes.1 == 0 {
+        return None;
+    }
+    Some(lot_sizes)
+}
+
+/// a resting order: price in pc lots per coin lot, quantity in coin lots
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookLevel {
+    pub price: u64,
+    pub quantity: u64,
+}
+
+/// every leaf of a bids / asks slab, best price first. the node array is
+/// scanned directly -- free nodes are tagged so only live orders are leaves
+pub fn unpack_slab_leaves(data: &[u8], is_bids: bool) -> Option<Vec<BookLevel>> {
+    let bump_index = read_u64(data, SLAB_BUMP_INDEX)? as usize;
+    let capacity = data.len().saturating_sub(SLAB_NODES) / SLAB_NODE_LEN;
+    let mut levels = vec![];
+    for i in 0..bump_index.min(capacity) {
+        let node = &data[SLAB_NODES + i * SLAB_NODE_LEN..SLAB_NODES + (i + 1) * SLAB_NODE_LEN];
+        let tag = u32::from_le_bytes(node[..4].try_into().ok()?);
+        if tag != LEAF_TAG {
+            continue;
+        }
+        let key = u128::from_le_bytes(node[LEAF_KEY..LEAF_KEY + 16].try_into().ok()?);
+        levels.push(BookLevel {
+            price: (key >> 64) as u64,
+            quantity: read_u64(node, LEAF_QUANTITY)?,
+        });
+    }
+    if is_bids {
+        levels.sort_by(|a, b| b.price.cmp(&a.price));
+    } else {
+        levels.sort_by_key(|level| level.price);
+    }
+    Some(levels)
+}
+
+/// a decoded snapshot of one market -- quotes walk it without touching it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OrderBook {
+    pub coin_lot_size: u64,
+    pub pc_lot_size: u64,
+    // best first
+    pub bids: Vec<BookLevel>,
+    pub asks: Vec<BookLevel>,
+}
+
+impl OrderBook {
+    /// coin lots bought with up to `max_pc_lots`, walking the asks
+    pub fn fill_bid(&self, max_pc_lots: u64) -> u64 {
+        let mut pc_lots_remaining = max_pc_lots;
+        let mut coin_lots = 0;
+        for ask in &self.asks {
+            if ask.price == 0 {
+                continue;
+            }
+            let trade_qty = ask.quantity.min(pc_lots_remaining / ask.price);
+            if trade_qty == 0 {
+                break;
+            }
+            pc_lots_remaining -= trade_qty * ask.price;
+            coin_lots += trade_qty;
+        }
+        coin_lots
+    }
+
+    /// pc lots received for `coin_lots`, walking the bids
+    pub fn fill_ask(&self, coin_lots: u64) -> u64 {
+        let mut unfilled_qty = coin_lots;
+        let mut accum_fill_price: u64 = 0;
+        for bid in &self.bids {
+            let trade_qty = bid.quantity.min(unfilled_qty);
+            if trade_qty == 0 {
+                break;
+            }
+            unfilled_qty -= trade_qty;
+            accum_fill_price += trade_qty * bid.price;
+        }
+        accum_fill_price
+    }
+
+    /// bid: native quote in (fees included) -> native base out
+    pub fn quote_bid(&self, native_pc_in: u64, fee_tier: FeeTier) -> u64 {
+        let max_pc_qty = fee_tier.remove_taker_fee(native_pc_in) / self.pc_lot_size;
+        self.fill_bid(max_pc_qty) * self.coin_lot_size
+    }
+
+    /// ask: native base in -> native quote out (fee taken)
+    pub fn quote_ask(&self, native_coin_in: u64, fee_tier: FeeTier) -> u64 {
+        let native_pc_qty = self.fill_ask(native_coin_in / self.coin_lot_size) * self.pc_lot_size;
+        native_pc_qty - fee_tier.taker_fee(native_pc_qty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(price: u64, quantity: u64) -> Vec<u8> {
+        let mut node = vec![0; SLAB_NODE_LEN];
+        node[..4].copy_from_slice(&LEAF_TAG.to_le_bytes());
+        let key = ((price as u128) << 64) | 7;
+        node[LEAF_KEY..LEAF_KEY + 16].copy_from_slice(&key.to_le_bytes());
+        node[LEAF_QUANTITY..LEAF_QUANTITY + 8].copy_from_slice(&quantity.to_le_bytes());
+        node
+    }
+
+    fn slab(nodes: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = vec![0; SLAB_NODES];
+        data[SLAB_BUMP_INDEX..SLAB_BUMP_INDEX + 8].copy_from_slice(&(nodes.len() as u64).to_le_bytes());
+        for node in nodes {
+            data.extend_from_slice(node);
+        }
+        data
+    }
+
+    #[test]
+    fn slab_leaves_are_best_first() {
+        // an inner node between the leaves is skipped
+        let mut inner = vec![0; SLAB_NODE_LEN];
+        inner[..4].copy_from_slice(&1_u32.to_le_bytes());
+        let data = slab(&[leaf(101, 5), inner, leaf(103, 1), leaf(102, 2)]);
+
+        let asks = unpack_slab_leaves(&data, false).unwrap();
+        assert_eq!(asks.iter().map(|l| l.price).collect::<Vec<_>>(), vec![101, 102, 103]);
+        let bids = unpack_slab_leaves(&data, true).unwrap();
+        assert_eq!(bids.iter().map(|l| l.price).collect::<Vec<_>>(), vec![103, 102, 101]);
+    }
+
+    #[test]
+    fn quotes_walk_levels_with_lot_sizes() {
+        let book = OrderBook {
+            coin_lot_size: 1_000,
+            pc_lot_size: 10,
+            bids: vec![BookLevel { price: 100, quantity: 2 }, BookLevel { price: 90, quantity: 10 }],
+            asks: vec![BookLevel { price: 110, quantity: 3 }, BookLevel { price: 120, quantity: 10 }],
+        };
+        // sell 5 lots (+ a partial lot that cant fill): 2 @ 100 + 3 @ 90 = 470 pc lots
+        let out = book.quote_ask(5_500, FeeTier::Base);
+        assert_eq!(out, 4_700 - FeeTier::Base.taker_fee(4_700));
+
+        // buy with exactly 3 @ 110 + 1 @ 120 = 450 pc lots (+ the fee)
+        let pc_in = 4_500 + FeeTier::Base.taker_fee(4_500);
+        assert_eq!(book.quote_bid(pc_in, FeeTier::Base), 4_000);
+    }
+
+    #[test]
+    fn quoting_leaves_the_book_untouched() {
+        let book = OrderBook {
+            coin_lot_size: 1,
+            pc_lot_size: 1,
+            bids: vec![BookLevel { price: 100, quantity: 10 }],
+            asks: vec![],
+        };
+        let first = book.quote_ask(10, FeeTier::Stable);
+        assert_eq!(book.quote_ask(10, FeeTier::Stable), first);
+        assert_eq!(book.bids[0].quantity, 10);
+        // nothing to buy
+        assert_eq!(book.quote_bid(1_000_000, FeeTier::Base), 0);
+    }
+}
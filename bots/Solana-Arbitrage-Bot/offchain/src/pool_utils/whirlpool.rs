@@ -0,0 +1,199 @@
+//! Orca Whirlpool account layouts
+//! (borsh anchor accounts -- offsets include the 8 byte discriminator)
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use arrayref::array_ref;
+
+use crate::pool_utils::clmm::{tick_array_start, TickLiquidity};
+
+// Whirlpool
+pub const WHIRLPOOL_LEN: usize = 653;
+const TICK_SPACING: usize = 41;
+const FEE_RATE: usize = 45;
+const LIQUIDITY: usize = 49;
+const SQRT_PRICE: usize = 65;
+const TICK_CURRENT_INDEX: usize = 81;
+
+// TickArray
+pub const TICK_ARRAY_SIZE: i32 = 88;
+pub const TICK_ARRAY_LEN: usize = 9988;
+const TICK_ARRAY_START: usize = 8;
+const TICKS: usize = 12;
+const TICK_LEN: usize = 113;
+const TICK_LIQUIDITY_NET: usize = 1;
+
+// the program's own price limits (slightly tighter than the tick math's)
+pub const MIN_SQRT_PRICE: u128 = 4295048016;
+pub const MAX_SQRT_PRICE: u128 = 79226673515401279992447579055;
+
+pub const TICK_ARRAY_SEED: &[u8] = b"tick_array";
+pub const ORACLE_SEED: &[u8] = b"oracle";
+
+fn read_u128(data: &[u8], offset: usize) -> u128 {
+    u128::from_le_bytes(*array_ref![data, offset, 16])
+}
+
+fn read_i32(data: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(*array_ref![data, offset, 4])
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WhirlpoolState {
+    pub tick_spacing: u16,
+    // out of 1e6 (hundredths of a bip)
+    pub fee_rate: u16,
+    pub liquidity: u128,
+    pub sqrt_price: u128,
+    pub tick_current_index: i32,
+}
+
+impl WhirlpoolState {
+    pub fn unpack(data: &[u8], discriminator: &[u8; 8]) -> Option<Self> {
+        if data.len() < WHIRLPOOL_LEN || data[..8] != discriminator[..] {
+            return None;
+        }
+        Some(Self {
+            tick_spacing: u16::from_le_bytes(*array_ref![data, TICK_SPACING, 2]),
+            fee_rate: u16::from_le_bytes(*array_ref![data, FEE_RATE, 2]),
+            liquidity: read_u128(data, LIQUIDITY),
+            sqrt_price: read_u128(data, SQRT_PRICE),
+            tick_current_index: read_i32(data, TICK_CURRENT_INDEX),
+        })
+    }
+
+    pub fn tick_array_span(&self) -> i32 {
+        self.tick_spacing as i32 * TICK_ARRAY_SIZE
+    }
+}
+
+/// initialized ticks from a TickArray, (start tick, ticks)
+/// (whirlpool ticks dont store their index -- its start + slot * spacing)
+pub fn unpack_tick_array(
+    data: &[u8],
+    discriminator: &[u8; 8],
+    tick_spacing: u16,
+) -> Option<(i32, Vec<TickLiquidity>)> {
+    if data.len() < TICK_ARRAY_LEN || data[..8] != discriminator[..] {
+        return None;
+    }
+    let start = read_i32(data, TICK_ARRAY_START);
+    let ticks = (0..TICK_ARRAY_SIZE as usize)
+        .filter(|i| data[TICKS + i * TICK_LEN] != 0) // initialized
+        .map(|i| {
+            let offset = TICKS + i * TICK_LEN;
+            TickLiquidity {
+                tick: start + i as i32 * tick_spacing as i32,
+                liquidity_net: i128::from_le_bytes(*array_ref![data, offset + TICK_LIQUIDITY_NET, 16]),
+            }
+        })
+        .collect();
+    Some((start, ticks))
+}
+
+/// the contiguous arrays a swap walks through: the current one and `n` either side
+pub fn tick_array_starts(tick_current: i32, tick_spacing: u16, n: i32) -> Vec<i32> {
+    let span = tick_spacing as i32 * TICK_ARRAY_SIZE;
+    let current = tick_array_start(tick_current, tick_spacing, TICK_ARRAY_SIZE);
+    (-n..=n).map(|k| current + k * span).collect()
+}
+
+/// start ticks of the 3 tick arrays a swap passes, in swap order
+pub fn swap_tick_array_starts(tick_current: i32, tick_spacing: u16, a_to_b: bool) -> [i32; 3] {
+    let span = tick_spacing as i32 * TICK_ARRAY_SIZE;
+    // b -> a starts one tick over so a price sitting on an array's edge
+    // is walked from the right array
+    let shift = if a_to_b { 0 } else { tick_spacing as i32 };
+    let current = tick_array_start(tick_current + shift, tick_spacing, TICK_ARRAY_SIZE);
+    let direction = if a_to_b { -span } else { span };
+    [0, 1, 2].map(|k| current + k * direction)
+}
+
+/// [lower, upper] ticks a swap can walk to in either direction -- the
+/// first / last tick of the 3rd array it passes
+pub fn swap_tick_range(tick_current: i32, tick_spacing: u16) -> (i32, i32) {
+    let span = tick_spacing as i32 * TICK_ARRAY_SIZE;
+    let lower = swap_tick_array_starts(tick_current, tick_spacing, true)[2];
+    let upper = swap_tick_array_starts(tick_current, tick_spacing, false)[2] + span - tick_spacing as i32;
+    (lower, upper)
+}
+
+pub fn tick_array_address(program_id: &Pubkey, whirlpool: &Pubkey, start: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[TICK_ARRAY_SEED, whirlpool.as_ref(), start.to_string().as_bytes()],
+        program_id,
+    ).0
+}
+
+pub fn oracle_address(program_id: &Pubkey, whirlpool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[ORACLE_SEED, whirlpool.as_ref()], program_id).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool_utils::clmm::{sqrt_price_at_tick, ClmmState};
+    use crate::utils::anchor_discriminator;
+
+    #[test]
+    fn unpacks_whirlpool() {
+        let disc = anchor_discriminator("account", "Whirlpool");
+        let mut data = vec![0; WHIRLPOOL_LEN];
+        data[..8].copy_from_slice(&disc);
+        data[TICK_SPACING..TICK_SPACING + 2].copy_from_slice(&64_u16.to_le_bytes());
+        data[FEE_RATE..FEE_RATE + 2].copy_from_slice(&3_000_u16.to_le_bytes());
+        data[SQRT_PRICE..SQRT_PRICE + 16].copy_from_slice(&(1_u128 << 64).to_le_bytes());
+        data[TICK_CURRENT_INDEX..TICK_CURRENT_INDEX + 4].copy_from_slice(&(-5_i32).to_le_bytes());
+
+        let state = WhirlpoolState::unpack(&data, &disc).unwrap();
+        assert_eq!(state.tick_spacing, 64);
+        assert_eq!(state.fee_rate, 3_000);
+        assert_eq!(state.sqrt_price, 1 << 64);
+        assert_eq!(state.tick_current_index, -5);
+        assert_eq!(state.tick_array_span(), 64 * 88);
+    }
+
+    #[test]
+    fn tick_indexes_come_from_the_slot() {
+        let disc = anchor_discriminator("account", "TickArray");
+        let mut data = vec![0; TICK_ARRAY_LEN];
+        data[..8].copy_from_slice(&disc);
+        data[TICK_ARRAY_START..TICK_ARRAY_START + 4].copy_from_slice(&(-5632_i32).to_le_bytes());
+        let offset = TICKS + 2 * TICK_LEN;
+        data[offset] = 1;
+        data[offset + TICK_LIQUIDITY_NET..offset + TICK_LIQUIDITY_NET + 16]
+            .copy_from_slice(&42_i128.to_le_bytes());
+
+        let (start, ticks) = unpack_tick_array(&data, &disc, 64).unwrap();
+        assert_eq!(start, -5632);
+        assert_eq!(ticks, vec![TickLiquidity { tick: -5632 + 128, liquidity_net: 42 }]);
+    }
+
+    #[test]
+    fn arrays_around_the_current_tick() {
+        assert_eq!(tick_array_starts(-1, 1, 1), vec![-176, -88, 0]);
+        assert_eq!(tick_array_starts(100, 64, 0), vec![0]);
+    }
+
+    #[test]
+    fn quotes_stop_at_the_swaps_last_tick_array() {
+        assert_eq!(swap_tick_array_starts(-1, 1, true), [-88, -176, -264]);
+        // on the edge of an array -- b -> a starts from the next one
+        assert_eq!(swap_tick_array_starts(-1, 1, false), [0, 88, 176]);
+        assert_eq!(swap_tick_range(-1, 1), (-264, 263));
+
+        // arrays loaded up to 352 -- a b -> a quote cant cross into the 4th
+        // array (starting at 264), which the swap doesnt pass
+        let (lower, upper) = swap_tick_range(-1, 1);
+        let state = ClmmState {
+            sqrt_price_x64: sqrt_price_at_tick(-1).unwrap(),
+            tick_current: -1,
+            liquidity: 1_000_000_000,
+            fee_rate: 0,
+            ticks: vec![],
+            tick_range: (lower.max(-352), upper.min(352)),
+        };
+        let result = state.swap_exact_in(u64::MAX as u128, false).unwrap();
+        assert!(result.amount_in < u64::MAX as u128);
+        assert_eq!(result.sqrt_price_x64, sqrt_price_at_tick(263).unwrap());
+    }
+}
@@ -1,6 +1,5 @@
 use std::collections::HashMap;
 use std::fmt::Debug;
-use std::str::FromStr;
 
 use serde;
 use serde::{Deserialize, Serialize};
@@ -15,7 +14,8 @@ use solana_sdk::instruction::Instruction;
 use tmp::accounts as tmp_accounts;
 use tmp::instruction as tmp_ix;
 
-use crate::serialize::token::{Token, WrappedPubkey, unpack_token_account};
+use crate::serialize::token::{Token, WrappedPubkey, try_unpack_token_amount};
+use crate::error::{PoolError, PoolResult};
 use crate::serialize::pool::JSONFeeStructure; 
 use crate::pool::PoolOperations;
 use crate::pool_utils::base::CurveType;
@@ -52,13 +52,27 @@ pub struct AldrinPool {
     pub pool_amounts: HashMap<String, u128>
 }
 
+impl AldrinPool {
+    fn pool_amount(&self, mint: &Pubkey) -> PoolResult<u128> {
+        if !self.tokens.contains_key(&mint.to_string()) {
+            return Err(PoolError::UnknownMint(*mint));
+        }
+        self.pool_amounts
+            .get(&mint.to_string())
+            .copied()
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))
+    }
+}
+
 impl PoolOperations for AldrinPool {
     fn swap_ix(&self, 
         program: &Program,
         owner: &Pubkey,
         _mint_in: &Pubkey, 
-        mint_out: &Pubkey
-    ) -> Vec<Instruction> {
+        mint_out: &Pubkey,
+        _amount_in: u64, // read from swap_state on-chain
+        _min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
         let (state_pda, _) = Pubkey::find_program_address(
             &[b"swap_state"], 
             &program.id()
@@ -69,21 +83,21 @@ impl PoolOperations for AldrinPool {
 
         let base_token_vault = self.tokens
             .get(base_token_mint)
-            .unwrap()
+            .ok_or_else(|| PoolError::UnknownMint(str2pubkey(base_token_mint)))?
             .addr.0;
         let quote_token_vault = self.tokens
             .get(quote_token_mint)
-            .unwrap()
+            .ok_or_else(|| PoolError::UnknownMint(str2pubkey(quote_token_mint)))?
             .addr.0;
 
         let is_inverted = &mint_out.to_string() == quote_token_mint;
         let user_base_ata = derive_token_address(
             owner, 
-            &Pubkey::from_str(base_token_mint).unwrap()
+            &str2pubkey(base_token_mint)
         );
         let user_quote_ata = derive_token_address(
             owner, 
-            &Pubkey::from_str(quote_token_mint).unwrap()
+            &str2pubkey(quote_token_mint)
         );
 
         let swap_ix;
@@ -106,8 +120,7 @@ impl PoolOperations for AldrinPool {
                     swap_state: state_pda, 
                 })
                 .args(tmp_ix::AldrinSwapV1 { is_inverted })
-                .instructions()
-                .unwrap();
+                .instructions()?;
         } else { 
             swap_ix = program
                 .request()
@@ -128,10 +141,9 @@ impl PoolOperations for AldrinPool {
                     swap_state: state_pda, 
                 })
                 .args(tmp_ix::AldrinSwapV2 { is_inverted })
-                .instructions()
-                .unwrap();
+                .instructions()?;
         }
-        swap_ix
+        Ok(swap_ix)
     }
 
     fn get_quote_with_amounts_scaled(
@@ -139,10 +151,10 @@ impl PoolOperations for AldrinPool {
         scaled_amount_in: u128, 
         mint_in: &Pubkey,
         mint_out: &Pubkey,
-    ) -> u128 {
+    ) -> PoolResult<u128> {
         
-        let pool_src_amount = *self.pool_amounts.get(&mint_in.to_string()).unwrap();
-        let pool_dst_amount = *self.pool_amounts.get(&mint_out.to_string()).unwrap();
+        let pool_src_amount = self.pool_amount(mint_in)?;
+        let pool_dst_amount = self.pool_amount(mint_out)?;
 
         // compute fees 
         let trader_fee = &self.fees.trader_fee;
@@ -175,7 +187,7 @@ impl PoolOperations for AldrinPool {
             pool_src_amount, 
             pool_dst_amount, 
             None,
-        ).unwrap()
+        ).map_err(|e| PoolError::QuoteFailed(e.to_string()))
     }
 
     fn can_trade(&self, 
@@ -197,6 +209,10 @@ impl PoolOperations for AldrinPool {
         }
     }
 
+    fn get_own_addr(&self) -> Pubkey {
+        self.pool_public_key.0
+    }
+
     fn get_update_accounts(&self) -> Vec<Pubkey> {
         // pool vault amount 
         // TODO: replace with token_ids + ['addr'] key
@@ -208,7 +224,7 @@ impl PoolOperations for AldrinPool {
         accounts 
     }
 
-    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) { 
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> { 
         let ids: Vec<String> = self
             .get_mints()
             .iter()
@@ -216,15 +232,14 @@ impl PoolOperations for AldrinPool {
             .collect();
         let id0 = &ids[0];
         let id1 = &ids[1];
-        
-        let acc_data0 = &accounts[0].as_ref().unwrap().data;
-        let acc_data1 = &accounts[1].as_ref().unwrap().data;
+        let pks = self.get_update_accounts();
 
-        let amount0 = unpack_token_account(acc_data0).amount as u128;
-        let amount1 = unpack_token_account(acc_data1).amount as u128;
+        let amount0 = try_unpack_token_amount(&pks[0], &accounts[0])?;
+        let amount1 = try_unpack_token_amount(&pks[1], &accounts[1])?;
 
         self.pool_amounts.insert(id0.clone(), amount0);
         self.pool_amounts.insert(id1.clone(), amount1);
+        Ok(())
     }
 
     fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
@@ -2,7 +2,8 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 use serde;
 use serde::{Deserialize, Serialize};
-use crate::serialize::token::{Token, WrappedPubkey, unpack_token_account};
+use crate::serialize::token::{Token, WrappedPubkey, try_unpack_token_amount};
+use crate::error::{PoolError, PoolResult};
 use crate::pool::PoolOperations;
 
 use anchor_client::solana_sdk::pubkey::Pubkey;
@@ -37,13 +38,27 @@ pub struct MercurialPool {
     pub pool_amounts: HashMap<String, u128>
 }
 
+impl MercurialPool {
+    fn pool_amount(&self, mint: &Pubkey) -> PoolResult<u128> {
+        if !self.tokens.contains_key(&mint.to_string()) {
+            return Err(PoolError::UnknownMint(*mint));
+        }
+        self.pool_amounts
+            .get(&mint.to_string())
+            .copied()
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))
+    }
+}
+
 impl PoolOperations for MercurialPool {
     fn swap_ix(&self, 
         program: &Program,
         owner: &Pubkey,
         mint_in: &Pubkey, 
-        mint_out: &Pubkey
-    ) -> Vec<Instruction> {
+        mint_out: &Pubkey,
+        _amount_in: u64, // read from swap_state on-chain
+        _min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
         let (swap_state_pda, _) = Pubkey::find_program_address(
             &[b"swap_state"], 
             &program.id()
@@ -69,10 +84,9 @@ impl PoolOperations for MercurialPool {
                 swap_state: swap_state_pda,
             })
             .args(tmp_ix::MercurialSwap { })
-            .instructions()
-            .unwrap();        
+            .instructions()?;
         
-        swap_ix
+        Ok(swap_ix)
     }
 
     fn get_quote_with_amounts_scaled(
@@ -80,7 +94,7 @@ impl PoolOperations for MercurialPool {
         scaled_amount_in: u128, 
         mint_in: &Pubkey,
         mint_out: &Pubkey,
-    ) -> u128 {
+    ) -> PoolResult<u128> {
         let fee_denom = 10_u128.pow(10); 
 
         let calculator = Stable {
@@ -90,14 +104,14 @@ impl PoolOperations for MercurialPool {
         };
 
         // only stable swap pools here 
-        let pool_src_amount = self.pool_amounts.get(&mint_in.to_string()).unwrap();
-        let pool_dst_amount = self.pool_amounts.get(&mint_out.to_string()).unwrap();
-        let pool_amounts = [*pool_src_amount, *pool_dst_amount];
+        let pool_src_amount = self.pool_amount(mint_in)?;
+        let pool_dst_amount = self.pool_amount(mint_out)?;
+        let pool_amounts = [pool_src_amount, pool_dst_amount];
 
         let input_idx = self.token_ids
             .iter()
             .position(|m| *m == mint_in.to_string())
-            .unwrap();
+            .ok_or(PoolError::UnknownMint(*mint_in))?;
         let output_idx = (input_idx + 1) % 2; 
 
         let percision_multipliers = [
@@ -111,7 +125,7 @@ impl PoolOperations for MercurialPool {
             pool_amounts,    
             percision_multipliers, 
             scaled_amount_in 
-        )
+        ).ok_or_else(|| PoolError::QuoteFailed("stable swap overflow".to_string()))
     }
 
     fn get_name(&self) -> String {
@@ -119,6 +133,10 @@ impl PoolOperations for MercurialPool {
         "Mercurial".to_string()
     }
 
+    fn get_own_addr(&self) -> Pubkey {
+        self.pool_account.0
+    }
+
     fn can_trade(&self, 
         _mint_in: &Pubkey,
         _mint_out: &Pubkey
@@ -139,7 +157,7 @@ impl PoolOperations for MercurialPool {
         accounts 
     }
 
-    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) { 
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> { 
         let ids: Vec<String> = self
             .get_mints()
             .iter()
@@ -147,15 +165,14 @@ impl PoolOperations for MercurialPool {
             .collect();
         let id0 = &ids[0];
         let id1 = &ids[1];
-        
-        let acc_data0 = &accounts[0].as_ref().unwrap().data;
-        let acc_data1 = &accounts[1].as_ref().unwrap().data;
+        let pks = self.get_update_accounts();
 
-        let amount0 = unpack_token_account(acc_data0).amount as u128;
-        let amount1 = unpack_token_account(acc_data1).amount as u128;
+        let amount0 = try_unpack_token_amount(&pks[0], &accounts[0])?;
+        let amount1 = try_unpack_token_amount(&pks[1], &accounts[1])?;
 
         self.pool_amounts.insert(id0.clone(), amount0);
         self.pool_amounts.insert(id1.clone(), amount1);
+        Ok(())
     }
 
 
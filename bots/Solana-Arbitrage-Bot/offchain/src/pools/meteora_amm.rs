@@ -0,0 +1,246 @@
+use std::fmt::Debug;
+use serde;
+use serde::{Deserialize, Serialize};
+use crate::serialize::token::{WrappedPubkey, try_unpack_token_amount};
+use crate::error::{PoolError, PoolResult};
+use crate::pool::PoolOperations;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anchor_client::Program;
+
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+
+use crate::utils::{anchor_discriminator, derive_token_address, unix_now};
+use crate::constants::*;
+use crate::pool_utils::meteora_amm::{
+    swap, unpack_mint_supply, AmmCurve, AmmPoolState, VaultShare, VaultState,
+};
+
+lazy_static! {
+    static ref POOL_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "Pool");
+    static ref VAULT_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "Vault");
+    static ref SWAP_DISCRIMINATOR: [u8; 8] = anchor_discriminator("global", "swap");
+}
+
+// meteora's dynamic amm json (eg, amm-v2.meteora.ag/pools), one pool per file
+// -- a/b_token_vault are the vaults' token accounts, a/b_vault_lp the pool's lp
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MeteoraDynamicAmmPool {
+    pub pool: WrappedPubkey,
+    pub token_a_mint: WrappedPubkey,
+    pub token_b_mint: WrappedPubkey,
+    pub a_vault: WrappedPubkey,
+    pub b_vault: WrappedPubkey,
+    pub a_token_vault: WrappedPubkey,
+    pub b_token_vault: WrappedPubkey,
+    pub a_vault_lp_mint: WrappedPubkey,
+    pub b_vault_lp_mint: WrappedPubkey,
+    pub a_vault_lp: WrappedPubkey,
+    pub b_vault_lp: WrappedPubkey,
+    pub protocol_token_a_fee: WrappedPubkey,
+    pub protocol_token_b_fee: WrappedPubkey,
+    pub decimals_a: u64,
+    pub decimals_b: u64,
+    // to set later
+    #[serde(skip)]
+    pub pool_state: Option<AmmPoolState>,
+    #[serde(skip)]
+    pub vaults: [VaultState; 2],
+    // [lp supply, pool lp] per side
+    #[serde(skip)]
+    pub lp_amounts: [[u128; 2]; 2],
+}
+
+impl MeteoraDynamicAmmPool {
+    fn token_idx(&self, mint: &Pubkey) -> PoolResult<usize> {
+        if *mint == self.token_a_mint.0 {
+            Ok(0)
+        } else if *mint == self.token_b_mint.0 {
+            Ok(1)
+        } else {
+            Err(PoolError::UnknownMint(*mint))
+        }
+    }
+
+    fn mint(&self, idx: usize) -> Pubkey {
+        if idx == 0 { self.token_a_mint.0 } else { self.token_b_mint.0 }
+    }
+
+    /// the pool's share of a vault at unix time `now`
+    fn vault_share(&self, idx: usize, now: i64) -> PoolResult<VaultShare> {
+        let unlocked = self.vaults[idx].unlocked_amount(now)
+            .ok_or_else(|| PoolError::QuoteFailed("vault locked profit exceeds its total".to_string()))?;
+        Ok(VaultShare {
+            unlocked,
+            lp_supply: self.lp_amounts[idx][0],
+            pool_lp: self.lp_amounts[idx][1],
+        })
+    }
+
+    fn is_depegged(&self) -> bool {
+        matches!(
+            self.pool_state.map(|pool_state| pool_state.curve),
+            Some(AmmCurve::Stable { depeg: true, .. })
+        )
+    }
+}
+
+impl PoolOperations for MeteoraDynamicAmmPool {
+    fn get_name(&self) -> String {
+        "MeteoraDynamicAmm".to_string()
+    }
+
+    fn get_own_addr(&self) -> Pubkey {
+        self.pool.0
+    }
+
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        vec![
+            self.pool.0,
+            self.a_vault.0,
+            self.b_vault.0,
+            self.a_vault_lp.0,
+            self.b_vault_lp.0,
+            self.a_vault_lp_mint.0,
+            self.b_vault_lp_mint.0,
+        ]
+    }
+
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> {
+        let pks = self.get_update_accounts();
+
+        let pool_acc = accounts[0].as_ref().ok_or(PoolError::MissingAccount(pks[0]))?;
+        let pool_state = AmmPoolState::unpack(&pool_acc.data, &POOL_DISCRIMINATOR)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[0], "not a dynamic amm pool".to_string()))?;
+
+        let mut vaults = [VaultState::default(); 2];
+        for (idx, vault) in vaults.iter_mut().enumerate() {
+            let i = 1 + idx;
+            let acc = accounts[i].as_ref().ok_or(PoolError::MissingAccount(pks[i]))?;
+            *vault = VaultState::unpack(&acc.data, &VAULT_DISCRIMINATOR)
+                .ok_or_else(|| PoolError::InvalidAccountData(pks[i], "not a meteora vault".to_string()))?;
+        }
+
+        let mut lp_amounts = [[0; 2]; 2];
+        for (idx, lp_amount) in lp_amounts.iter_mut().enumerate() {
+            let i = 5 + idx;
+            let acc = accounts[i].as_ref().ok_or(PoolError::MissingAccount(pks[i]))?;
+            let supply = unpack_mint_supply(&acc.data)
+                .ok_or_else(|| PoolError::InvalidAccountData(pks[i], "not a mint".to_string()))?;
+            *lp_amount = [supply as u128, try_unpack_token_amount(&pks[3 + idx], &accounts[3 + idx])?];
+        }
+
+        self.pool_state = Some(pool_state);
+        self.vaults = vaults;
+        self.lp_amounts = lp_amounts;
+        Ok(())
+    }
+
+    fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
+        if *mint == self.token_a_mint.0 { self.a_token_vault.0 } else { self.b_token_vault.0 }
+    }
+
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints = vec![self.token_a_mint.0, self.token_b_mint.0];
+        // sort so that its consistent across different pools
+        mints.sort();
+        mints
+    }
+
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        if *mint == self.token_a_mint.0 { self.decimals_a } else { self.decimals_b }
+    }
+
+    fn get_quote_with_amounts_scaled(
+        &self,
+        scaled_amount_in: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> PoolResult<u128> {
+        let idx_in = self.token_idx(mint_in)?;
+        let idx_out = self.token_idx(mint_out)?;
+        let pool_state = self.pool_state
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))?;
+        if !pool_state.enabled {
+            return Ok(0);
+        }
+        // depeg pools price off an external rate (stake pool / oracle) we dont load
+        if self.is_depegged() {
+            return Err(PoolError::QuoteFailed("depeg stable pools arent supported".to_string()));
+        }
+
+        // vault profit unlocks with time so the reserves drift between refreshes
+        let now = unix_now();
+        let vault_in = self.vault_share(idx_in, now)?;
+        let vault_out = self.vault_share(idx_out, now)?;
+        swap(&pool_state, &vault_in, &vault_out, idx_in == 0, scaled_amount_in)
+            .ok_or_else(|| PoolError::QuoteFailed("dynamic amm overflow".to_string()))
+    }
+
+    fn swap_ix(&self,
+        _program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
+        let idx_in = self.token_idx(mint_in)?;
+        let idx_out = self.token_idx(mint_out)?;
+        let user_src = derive_token_address(owner, &self.mint(idx_in));
+        let user_dst = derive_token_address(owner, &self.mint(idx_out));
+        let protocol_fee = if idx_in == 0 { self.protocol_token_a_fee.0 } else { self.protocol_token_b_fee.0 };
+
+        // swap { in_amount, minimum_out_amount }
+        let mut data = SWAP_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&min_amount_out.to_le_bytes());
+
+        let accounts = vec![
+            AccountMeta::new(self.pool.0, false),
+            AccountMeta::new(user_src, false),
+            AccountMeta::new(user_dst, false),
+            AccountMeta::new(self.a_vault.0, false),
+            AccountMeta::new(self.b_vault.0, false),
+            AccountMeta::new(self.a_token_vault.0, false),
+            AccountMeta::new(self.b_token_vault.0, false),
+            AccountMeta::new(self.a_vault_lp_mint.0, false),
+            AccountMeta::new(self.b_vault_lp_mint.0, false),
+            AccountMeta::new(self.a_vault_lp.0, false),
+            AccountMeta::new(self.b_vault_lp.0, false),
+            AccountMeta::new(protocol_fee, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(*METEORA_VAULT_PROGRAM_ID, false),
+            AccountMeta::new_readonly(*TOKEN_PROGRAM_ID, false),
+        ];
+
+        Ok(vec![Instruction {
+            program_id: *METEORA_DYNAMIC_AMM_PROGRAM_ID,
+            accounts,
+            data,
+        }])
+    }
+
+    fn uses_swap_state(&self) -> bool {
+        false
+    }
+
+    fn can_trade(&self,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey
+    ) -> bool {
+        match self.pool_state {
+            Some(pool_state) => {
+                pool_state.enabled
+                    && !self.is_depegged()
+                    && self.lp_amounts.iter().all(|[_, pool_lp]| *pool_lp > 0)
+                    && self.token_idx(mint_in).is_ok()
+                    && self.token_idx(mint_out).is_ok()
+            }
+            None => false,
+        }
+    }
+}
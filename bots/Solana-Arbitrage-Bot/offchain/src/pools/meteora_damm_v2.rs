@@ -0,0 +1,179 @@
+use std::fmt::Debug;
+use serde;
+use serde::{Deserialize, Serialize};
+use crate::serialize::token::WrappedPubkey;
+use crate::error::{PoolError, PoolResult};
+use crate::pool::PoolOperations;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anchor_client::Program;
+
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+
+use crate::utils::{anchor_discriminator, derive_token_address_with_program, unix_now};
+use crate::constants::*;
+use crate::pool_utils::damm_v2::DammV2State;
+use crate::pool_utils::dlmm::event_authority;
+
+lazy_static! {
+    static ref POOL_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "Pool");
+    static ref SWAP_DISCRIMINATOR: [u8; 8] = anchor_discriminator("global", "swap");
+}
+
+// meteora's damm v2 json (eg, dammv2-api.meteora.ag/pools), one pool per file
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MeteoraDammV2Pool {
+    pub pool: WrappedPubkey,
+    pub token_a_mint: WrappedPubkey,
+    pub token_b_mint: WrappedPubkey,
+    pub token_a_vault: WrappedPubkey,
+    pub token_b_vault: WrappedPubkey,
+    pub token_a_program: WrappedPubkey,
+    pub token_b_program: WrappedPubkey,
+    pub decimals_a: u64,
+    pub decimals_b: u64,
+    // to set later
+    #[serde(skip)]
+    pub pool_state: Option<DammV2State>,
+}
+
+impl MeteoraDammV2Pool {
+    fn token_idx(&self, mint: &Pubkey) -> PoolResult<usize> {
+        if *mint == self.token_a_mint.0 {
+            Ok(0)
+        } else if *mint == self.token_b_mint.0 {
+            Ok(1)
+        } else {
+            Err(PoolError::UnknownMint(*mint))
+        }
+    }
+}
+
+impl PoolOperations for MeteoraDammV2Pool {
+    fn get_name(&self) -> String {
+        "MeteoraDammV2".to_string()
+    }
+
+    fn get_own_addr(&self) -> Pubkey {
+        self.pool.0
+    }
+
+    // reserves are implied by the pool's liquidity + price, no vaults needed
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        vec![self.pool.0]
+    }
+
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> {
+        let pks = self.get_update_accounts();
+
+        let pool_acc = accounts[0].as_ref().ok_or(PoolError::MissingAccount(pks[0]))?;
+        let pool_state = DammV2State::unpack(&pool_acc.data, &POOL_DISCRIMINATOR)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[0], "not a damm v2 pool".to_string()))?;
+
+        self.pool_state = Some(pool_state);
+        Ok(())
+    }
+
+    fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
+        if *mint == self.token_a_mint.0 { self.token_a_vault.0 } else { self.token_b_vault.0 }
+    }
+
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints = vec![self.token_a_mint.0, self.token_b_mint.0];
+        // sort so that its consistent across different pools
+        mints.sort();
+        mints
+    }
+
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        if *mint == self.token_a_mint.0 { self.decimals_a } else { self.decimals_b }
+    }
+
+    fn get_quote_with_amounts_scaled(
+        &self,
+        scaled_amount_in: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> PoolResult<u128> {
+        let idx_in = self.token_idx(mint_in)?;
+        self.token_idx(mint_out)?;
+        let pool_state = self.pool_state
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))?;
+        // the base fee decays and the variable fee cools down with time
+        let now = unix_now();
+        if !pool_state.can_swap(now) {
+            return Ok(0);
+        }
+
+        // None = pushed past the pool's price range -- the swap would fail on-chain
+        Ok(pool_state.swap_exact_in(scaled_amount_in, idx_in == 0, now).unwrap_or(0))
+    }
+
+    fn swap_ix(&self,
+        _program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
+        let idx_in = self.token_idx(mint_in)?;
+        self.token_idx(mint_out)?;
+
+        let user_a = derive_token_address_with_program(owner, &self.token_a_mint.0, &self.token_a_program.0);
+        let user_b = derive_token_address_with_program(owner, &self.token_b_mint.0, &self.token_b_program.0);
+        let (user_src, user_dst) = if idx_in == 0 { (user_a, user_b) } else { (user_b, user_a) };
+
+        // swap { amount_in, minimum_amount_out }
+        let mut data = SWAP_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&min_amount_out.to_le_bytes());
+
+        // optional accounts are passed as the program id
+        let program_id = *METEORA_DAMM_V2_PROGRAM_ID;
+        let accounts = vec![
+            AccountMeta::new_readonly(*METEORA_DAMM_V2_POOL_AUTHORITY, false),
+            AccountMeta::new(self.pool.0, false),
+            AccountMeta::new(user_src, false),
+            AccountMeta::new(user_dst, false),
+            AccountMeta::new(self.token_a_vault.0, false),
+            AccountMeta::new(self.token_b_vault.0, false),
+            AccountMeta::new_readonly(self.token_a_mint.0, false),
+            AccountMeta::new_readonly(self.token_b_mint.0, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(self.token_a_program.0, false),
+            AccountMeta::new_readonly(self.token_b_program.0, false),
+            AccountMeta::new_readonly(program_id, false), // referral token account
+            AccountMeta::new_readonly(event_authority(&program_id), false),
+            AccountMeta::new_readonly(program_id, false),
+        ];
+
+        Ok(vec![Instruction {
+            program_id,
+            accounts,
+            data,
+        }])
+    }
+
+    fn uses_swap_state(&self) -> bool {
+        false
+    }
+
+    fn can_trade(&self,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey
+    ) -> bool {
+        match self.pool_state {
+            Some(pool_state) => {
+                pool_state.can_swap(unix_now())
+                    && pool_state.liquidity > 0
+                    && self.token_idx(mint_in).is_ok()
+                    && self.token_idx(mint_out).is_ok()
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use serde;
+use serde::{Deserialize, Serialize};
+use crate::serialize::token::WrappedPubkey;
+use crate::error::{PoolError, PoolResult};
+use crate::pool::PoolOperations;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anchor_client::Program;
+
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+
+use crate::utils::{anchor_discriminator, derive_token_address_with_program, unix_now};
+use crate::constants::*;
+use crate::pool_utils::dlmm::{
+    bin_array_address, bin_array_index, event_authority, unpack_bin_array, DlmmState, LbPairState,
+};
+
+// initialized bin arrays loaded on each side of the active bin
+const BIN_ARRAYS_PER_SIDE: usize = 3;
+
+lazy_static! {
+    static ref LB_PAIR_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "LbPair");
+    static ref BIN_ARRAY_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "BinArray");
+    static ref SWAP_DISCRIMINATOR: [u8; 8] = anchor_discriminator("global", "swap");
+}
+
+// meteora's pair json (eg, dlmm-api.meteora.ag/pair/all), one pair per file
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MeteoraDlmmPool {
+    pub address: WrappedPubkey,
+    pub token_x_mint: WrappedPubkey,
+    pub token_y_mint: WrappedPubkey,
+    pub reserve_x: WrappedPubkey,
+    pub reserve_y: WrappedPubkey,
+    pub token_x_program: WrappedPubkey,
+    pub token_y_program: WrappedPubkey,
+    pub oracle: WrappedPubkey,
+    pub decimals_x: u64,
+    pub decimals_y: u64,
+    // to set later
+    #[serde(skip)]
+    pub dlmm: Option<DlmmState>,
+    // indexes of the bin arrays in get_update_accounts
+    #[serde(skip)]
+    pub bin_arrays: Vec<i64>,
+}
+
+impl MeteoraDlmmPool {
+    fn token_idx(&self, mint: &Pubkey) -> PoolResult<usize> {
+        if *mint == self.token_x_mint.0 {
+            Ok(0)
+        } else if *mint == self.token_y_mint.0 {
+            Ok(1)
+        } else {
+            Err(PoolError::UnknownMint(*mint))
+        }
+    }
+
+    fn bin_array(&self, index: i64) -> Pubkey {
+        bin_array_address(&METEORA_DLMM_PROGRAM_ID, &self.address.0, index)
+    }
+
+    /// bin arrays the swap walks through, in swap order
+    fn swap_bin_arrays(&self, swap_for_y: bool) -> PoolResult<Vec<Pubkey>> {
+        let dlmm = self.dlmm.as_ref()
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))?;
+        let active = bin_array_index(dlmm.pair.active_id);
+        let mut indexes: Vec<i64> = if swap_for_y {
+            self.bin_arrays.iter().rev().filter(|i| **i <= active).cloned().collect()
+        } else {
+            self.bin_arrays.iter().filter(|i| **i >= active).cloned().collect()
+        };
+        indexes.truncate(BIN_ARRAYS_PER_SIDE);
+        if indexes.is_empty() {
+            return Err(PoolError::MissingSwapAccount("no bin arrays".to_string()));
+        }
+        Ok(indexes.iter().map(|index| self.bin_array(*index)).collect())
+    }
+}
+
+impl PoolOperations for MeteoraDlmmPool {
+    fn get_name(&self) -> String {
+        "MeteoraDlmm".to_string()
+    }
+
+    fn get_own_addr(&self) -> Pubkey {
+        self.address.0
+    }
+
+    // the bin arrays follow the active bin -- they change as it moves
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        let mut accounts = vec![self.address.0];
+        accounts.extend(self.bin_arrays.iter().map(|index| self.bin_array(*index)));
+        accounts
+    }
+
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> {
+        let pks = self.get_update_accounts();
+        if accounts.len() != pks.len() {
+            return Err(PoolError::InvalidAccountData(self.address.0, "bin arrays out of sync".to_string()));
+        }
+
+        let pair_acc = accounts[0].as_ref().ok_or(PoolError::MissingAccount(pks[0]))?;
+        let pair = LbPairState::unpack(&pair_acc.data, &LB_PAIR_DISCRIMINATOR)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[0], "not an lb pair".to_string()))?;
+
+        let mut bins = BTreeMap::new();
+        let mut loaded = vec![];
+        for (pk, account) in pks[1..].iter().zip(accounts[1..].iter()) {
+            let acc = account.as_ref().ok_or(PoolError::MissingAccount(*pk))?;
+            let (index, array_bins) = unpack_bin_array(&acc.data, &BIN_ARRAY_DISCRIMINATOR)
+                .ok_or_else(|| PoolError::InvalidAccountData(*pk, "not a bin array".to_string()))?;
+            loaded.push(index);
+            bins.extend(array_bins);
+        }
+
+        let (wanted, mut bin_range) = pair.bin_arrays_around(BIN_ARRAYS_PER_SIDE);
+        if wanted != loaded {
+            // the active bin moved onto arrays we havent loaded yet -- only
+            // quote inside the active bin until the next refresh brings them in
+            bin_range = (pair.active_id, pair.active_id);
+        }
+
+        self.dlmm = Some(DlmmState {
+            pair,
+            bins,
+            bin_range,
+        });
+        self.bin_arrays = wanted;
+        Ok(())
+    }
+
+    fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
+        if *mint == self.token_x_mint.0 { self.reserve_x.0 } else { self.reserve_y.0 }
+    }
+
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints = vec![self.token_x_mint.0, self.token_y_mint.0];
+        // sort so that its consistent across different pools
+        mints.sort();
+        mints
+    }
+
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        if *mint == self.token_x_mint.0 { self.decimals_x } else { self.decimals_y }
+    }
+
+    fn get_quote_with_amounts_scaled(
+        &self,
+        scaled_amount_in: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> PoolResult<u128> {
+        let idx_in = self.token_idx(mint_in)?;
+        self.token_idx(mint_out)?;
+        let dlmm = self.dlmm.as_ref()
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))?;
+        if !dlmm.pair.swap_enabled() {
+            return Ok(0);
+        }
+
+        // the variable fee decays with time since the last swap
+        let result = dlmm.swap_exact_in(scaled_amount_in, idx_in == 0, unix_now())
+            .ok_or_else(|| PoolError::QuoteFailed("dlmm overflow".to_string()))?;
+        // ran out of loaded bins -- the swap would fail on-chain
+        if result.amount_in < scaled_amount_in {
+            return Ok(0);
+        }
+        Ok(result.amount_out)
+    }
+
+    fn swap_ix(&self,
+        _program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
+        let idx_in = self.token_idx(mint_in)?;
+        self.token_idx(mint_out)?;
+        let bin_arrays = self.swap_bin_arrays(idx_in == 0)?;
+
+        let user_x = derive_token_address_with_program(owner, &self.token_x_mint.0, &self.token_x_program.0);
+        let user_y = derive_token_address_with_program(owner, &self.token_y_mint.0, &self.token_y_program.0);
+        let (user_src, user_dst) = if idx_in == 0 { (user_x, user_y) } else { (user_y, user_x) };
+
+        // swap { amount_in, min_amount_out }
+        let mut data = SWAP_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&min_amount_out.to_le_bytes());
+
+        // optional accounts are passed as the program id
+        let program_id = *METEORA_DLMM_PROGRAM_ID;
+        let mut accounts = vec![
+            AccountMeta::new(self.address.0, false),
+            AccountMeta::new_readonly(program_id, false), // bin array bitmap extension
+            AccountMeta::new(self.reserve_x.0, false),
+            AccountMeta::new(self.reserve_y.0, false),
+            AccountMeta::new(user_src, false),
+            AccountMeta::new(user_dst, false),
+            AccountMeta::new_readonly(self.token_x_mint.0, false),
+            AccountMeta::new_readonly(self.token_y_mint.0, false),
+            AccountMeta::new(self.oracle.0, false),
+            AccountMeta::new_readonly(program_id, false), // host fee
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(self.token_x_program.0, false),
+            AccountMeta::new_readonly(self.token_y_program.0, false),
+            AccountMeta::new_readonly(event_authority(&program_id), false),
+            AccountMeta::new_readonly(program_id, false),
+        ];
+        accounts.extend(bin_arrays.into_iter().map(|pk| AccountMeta::new(pk, false)));
+
+        Ok(vec![Instruction {
+            program_id,
+            accounts,
+            data,
+        }])
+    }
+
+    fn uses_swap_state(&self) -> bool {
+        false
+    }
+
+    fn can_trade(&self,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey
+    ) -> bool {
+        match &self.dlmm {
+            Some(dlmm) => {
+                dlmm.pair.swap_enabled()
+                    && !dlmm.bins.is_empty()
+                    && self.token_idx(mint_in).is_ok()
+                    && self.token_idx(mint_out).is_ok()
+            }
+            None => false,
+        }
+    }
+}
@@ -11,4 +11,37 @@ pub mod saber;
 pub use saber::*; 
 
 pub mod serum; 
-pub use serum::*; 
\ No newline at end of file
+pub use serum::*;
+
+pub mod raydium; 
+pub use raydium::*;
+
+pub mod raydium_cpmm; 
+pub use raydium_cpmm::*; 
+
+pub mod raydium_clmm; 
+pub use raydium_clmm::*; 
+
+pub mod orca_whirlpool; 
+pub use orca_whirlpool::*; 
+
+pub mod meteora_dlmm; 
+pub use meteora_dlmm::*; 
+
+pub mod meteora_amm; 
+pub use meteora_amm::*; 
+
+pub mod meteora_damm_v2; 
+pub use meteora_damm_v2::*; 
+
+pub mod pumpswap; 
+pub use pumpswap::*; 
+
+pub mod pump_fun; 
+pub use pump_fun::*; 
+
+pub mod openbook_v2; 
+pub use openbook_v2::*; 
+
+pub mod phoenix; 
+pub use phoenix::*; 
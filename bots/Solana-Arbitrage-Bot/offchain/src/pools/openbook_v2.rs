@@ -0,0 +1,238 @@
+use std::fmt::Debug;
+use serde;
+use serde::{Deserialize, Serialize};
+use crate::serialize::token::WrappedPubkey;
+use crate::error::{PoolError, PoolResult};
+use crate::pool::PoolOperations;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anchor_client::Program;
+
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::system_program;
+
+use crate::utils::{anchor_discriminator, derive_token_address, unix_now};
+use crate::constants::*;
+use crate::pool_utils::openbook_v2::{unpack_book_side, MarketState, MARKET_AUTHORITY_SEED};
+use crate::pool_utils::serum::OrderBook;
+
+// place_take_order args
+const SIDE_BID: u8 = 0;
+const SIDE_ASK: u8 = 1;
+const IMMEDIATE_OR_CANCEL: u8 = 1;
+const FILL_OR_KILL: u8 = 5;
+// orders matched per take -- the quote only walks this many resting orders
+const MATCH_LIMIT: u8 = 32;
+
+lazy_static! {
+    static ref MARKET_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "Market");
+    static ref BOOK_SIDE_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "BookSide");
+    static ref PLACE_TAKE_ORDER_DISCRIMINATOR: [u8; 8] = anchor_discriminator("global", "place_take_order");
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenBookV2Pool {
+    pub address: WrappedPubkey,
+    pub base_mint: WrappedPubkey,
+    pub quote_mint: WrappedPubkey,
+    pub bids: WrappedPubkey,
+    pub asks: WrappedPubkey,
+    pub base_decimals: u64,
+    pub quote_decimals: u64,
+    // to set later
+    #[serde(skip)]
+    pub market: Option<MarketState>,
+    #[serde(skip)]
+    pub book: Option<OrderBook>,
+}
+
+impl OpenBookV2Pool {
+    fn token_idx(&self, mint: &Pubkey) -> PoolResult<usize> {
+        if *mint == self.base_mint.0 {
+            Ok(0)
+        } else if *mint == self.quote_mint.0 {
+            Ok(1)
+        } else {
+            Err(PoolError::UnknownMint(*mint))
+        }
+    }
+
+    fn loaded(&self) -> PoolResult<(&MarketState, &OrderBook)> {
+        match (&self.market, &self.book) {
+            (Some(market), Some(book)) => Ok((market, book)),
+            _ => Err(PoolError::NotLoaded(self.get_name())),
+        }
+    }
+}
+
+impl PoolOperations for OpenBookV2Pool {
+    fn get_name(&self) -> String {
+        "OpenBookV2".to_string()
+    }
+
+    fn get_own_addr(&self) -> Pubkey {
+        self.address.0
+    }
+
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        vec![self.address.0, self.bids.0, self.asks.0]
+    }
+
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> {
+        let pks = self.get_update_accounts();
+        let data = |i: usize| accounts[i].as_ref()
+            .map(|account| &account.data[..])
+            .ok_or(PoolError::MissingAccount(pks[i]));
+
+        let market = MarketState::unpack(data(0)?, &MARKET_DISCRIMINATOR)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[0], "not an openbook v2 market".to_string()))?;
+        if market.bids != self.bids.0 || market.asks != self.asks.0 {
+            return Err(PoolError::InvalidAccountData(pks[0], "bids/asks dont match the market".to_string()));
+        }
+
+        let now = unix_now().max(0) as u64;
+        let side = |i: usize, is_bids: bool| {
+            let mut levels = unpack_book_side(data(i)?, &BOOK_SIDE_DISCRIMINATOR, is_bids, now)
+                .ok_or_else(|| PoolError::InvalidAccountData(pks[i], "not an openbook v2 book side".to_string()))?;
+            levels.truncate(MATCH_LIMIT as usize);
+            Ok::<_, PoolError>(levels)
+        };
+        let book = OrderBook {
+            coin_lot_size: market.base_lot_size,
+            pc_lot_size: market.quote_lot_size,
+            bids: side(1, true)?,
+            asks: side(2, false)?,
+        };
+
+        self.market = Some(market);
+        self.book = Some(book);
+        Ok(())
+    }
+
+    fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
+        let market = self.market.unwrap_or_default();
+        if *mint == self.base_mint.0 { market.market_base_vault } else { market.market_quote_vault }
+    }
+
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints = vec![self.base_mint.0, self.quote_mint.0];
+        // sort so that its consistent across different pools
+        mints.sort();
+        mints
+    }
+
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        if *mint == self.base_mint.0 { self.base_decimals } else { self.quote_decimals }
+    }
+
+    fn get_quote_with_amounts_scaled(
+        &self,
+        scaled_amount_in: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> PoolResult<u128> {
+        let idx_in = self.token_idx(mint_in)?;
+        self.token_idx(mint_out)?;
+        let (market, book) = self.loaded()?;
+        let amount_in = scaled_amount_in.min(u64::MAX as u128) as u64;
+        let amount_out = if idx_in == 1 {
+            // bid: quote -> base
+            market.quote_bid(book, amount_in)
+        } else {
+            // ask: base -> quote
+            market.quote_ask(book, amount_in)
+        };
+        Ok(amount_out as u128)
+    }
+
+    fn swap_ix(&self,
+        _program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
+        let is_bid = self.token_idx(mint_in)? == 1;
+        self.token_idx(mint_out)?;
+        let (market, _) = self.loaded()?;
+
+        // place_take_order { side, price_lots, max_base_lots,
+        // max_quote_lots_including_fees, order_type, limit } -- an IOC at the
+        // worst price, sized by amount_in on the side being paid. with a min
+        // out it's a fill or kill instead: bids have to take the min out's
+        // base lots within amount_in, asks sell every lot at no less than
+        // the average price which nets the min out
+        let (side, price_lots, max_base_lots, max_quote_lots) = if is_bid {
+            let min_base_lots = (min_amount_out + market.base_lot_size - 1) / market.base_lot_size;
+            let max_base_lots = if min_amount_out > 0 { min_base_lots as i64 } else { i64::MAX };
+            (SIDE_BID, i64::MAX, max_base_lots, (amount_in / market.quote_lot_size) as i64)
+        } else {
+            let base_lots = amount_in / market.base_lot_size;
+            let price_lots = market.min_ask_price(base_lots, min_amount_out).max(1);
+            (SIDE_ASK, price_lots as i64, base_lots as i64, i64::MAX)
+        };
+        let order_type = if min_amount_out > 0 { FILL_OR_KILL } else { IMMEDIATE_OR_CANCEL };
+        let mut data = PLACE_TAKE_ORDER_DISCRIMINATOR.to_vec();
+        data.push(side);
+        data.extend_from_slice(&price_lots.to_le_bytes());
+        data.extend_from_slice(&max_base_lots.to_le_bytes());
+        data.extend_from_slice(&max_quote_lots.to_le_bytes());
+        data.push(order_type);
+        data.push(MATCH_LIMIT);
+
+        let program_id = *OPENBOOK_V2_PROGRAM_ID;
+        let (market_authority, _) = Pubkey::find_program_address(
+            &[MARKET_AUTHORITY_SEED, self.address.0.as_ref()],
+            &program_id,
+        );
+        // unset optional accounts are passed as the program id
+        let optional = |key: Pubkey| if key == Pubkey::default() { program_id } else { key };
+
+        let accounts = vec![
+            AccountMeta::new(*owner, true),
+            // penalty payer
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(self.address.0, false),
+            AccountMeta::new_readonly(market_authority, false),
+            AccountMeta::new(self.bids.0, false),
+            AccountMeta::new(self.asks.0, false),
+            AccountMeta::new(market.market_base_vault, false),
+            AccountMeta::new(market.market_quote_vault, false),
+            AccountMeta::new(market.event_heap, false),
+            AccountMeta::new(derive_token_address(owner, &self.base_mint.0), false),
+            AccountMeta::new(derive_token_address(owner, &self.quote_mint.0), false),
+            AccountMeta::new_readonly(optional(market.oracle_a), false),
+            AccountMeta::new_readonly(optional(market.oracle_b), false),
+            AccountMeta::new_readonly(*TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            // open orders admin
+            AccountMeta::new_readonly(program_id, false),
+        ];
+
+        Ok(vec![Instruction {
+            program_id,
+            accounts,
+            data,
+        }])
+    }
+
+    fn uses_swap_state(&self) -> bool {
+        false
+    }
+
+    fn can_trade(&self,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey
+    ) -> bool {
+        match (self.loaded(), self.token_idx(mint_in), self.token_idx(mint_out)) {
+            // is there a bid or ask we can trade with
+            (Ok((_, book)), Ok(1), Ok(_)) => !book.asks.is_empty(),
+            (Ok((_, book)), Ok(_), Ok(_)) => !book.bids.is_empty(),
+            _ => false,
+        }
+    }
+}
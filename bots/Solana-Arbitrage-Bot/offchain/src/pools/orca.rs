@@ -3,7 +3,8 @@ use std::fmt::Debug;
 use serde;
 use serde::{Deserialize, Serialize};
 use solana_sdk::account::Account;
-use crate::serialize::token::{Token, WrappedPubkey, unpack_token_account};
+use crate::serialize::token::{Token, WrappedPubkey, try_unpack_token_amount};
+use crate::error::{PoolError, PoolResult};
 use crate::serialize::pool::JSONFeeStructure; 
 use crate::pool::PoolOperations;
 
@@ -44,13 +45,27 @@ pub struct OrcaPool {
     pub pool_amounts: HashMap<String, u128>
 }
 
+impl OrcaPool {
+    fn pool_amount(&self, mint: &Pubkey) -> PoolResult<u128> {
+        if !self.tokens.contains_key(&mint.to_string()) {
+            return Err(PoolError::UnknownMint(*mint));
+        }
+        self.pool_amounts
+            .get(&mint.to_string())
+            .copied()
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))
+    }
+}
+
 impl PoolOperations for OrcaPool {
     fn swap_ix(&self, 
         program: &Program,
         owner: &Pubkey,
         mint_in: &Pubkey, 
-        mint_out: &Pubkey
-    ) -> Vec<Instruction> {
+        mint_out: &Pubkey,
+        _amount_in: u64, // read from swap_state on-chain
+        _min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
         let (swap_state, _) = Pubkey::find_program_address(
             &[b"swap_state"], 
             &program.id()
@@ -63,8 +78,10 @@ impl PoolOperations for OrcaPool {
             &ORCA_PROGRAM_ID 
         );
 
-        let pool_src = self.mint_2_addr(mint_in);
-        let pool_dst = self.mint_2_addr(mint_out);
+        let pool_src = self.tokens.get(&mint_in.to_string())
+            .ok_or(PoolError::UnknownMint(*mint_in))?.addr.0;
+        let pool_dst = self.tokens.get(&mint_out.to_string())
+            .ok_or(PoolError::UnknownMint(*mint_out))?.addr.0;
 
         let swap_ix = program
             .request()
@@ -83,10 +100,9 @@ impl PoolOperations for OrcaPool {
                 swap_state,
             })
             .args(tmp_ix::OrcaSwap { })
-            .instructions()
-            .unwrap();
+            .instructions()?;
 
-        swap_ix
+        Ok(swap_ix)
     }
 
     fn get_quote_with_amounts_scaled(
@@ -94,10 +110,10 @@ impl PoolOperations for OrcaPool {
         scaled_amount_in: u128, 
         mint_in: &Pubkey,
         mint_out: &Pubkey,
-    ) -> u128 {
+    ) -> PoolResult<u128> {
         
-        let pool_src_amount = self.pool_amounts.get(&mint_in.to_string()).unwrap();
-        let pool_dst_amount = self.pool_amounts.get(&mint_out.to_string()).unwrap();
+        let pool_src_amount = self.pool_amount(mint_in)?;
+        let pool_dst_amount = self.pool_amount(mint_out)?;
 
         // compute fees 
         let trader_fee = &self.fee_structure.trader_fee;
@@ -117,7 +133,7 @@ impl PoolOperations for OrcaPool {
         } else if self.curve_type == 2 {
             CurveType::Stable
         } else { 
-            panic!("invalid self curve type: {:?}", self.curve_type);
+            return Err(PoolError::UnsupportedCurveType(self.curve_type));
         };
 
         // get quote -- works for either constant product or stable swap 
@@ -128,10 +144,10 @@ impl PoolOperations for OrcaPool {
             ctype,
             self.amp, 
             &fees, 
-            *pool_src_amount, 
-            *pool_dst_amount, 
+            pool_src_amount, 
+            pool_dst_amount, 
             None,
-        ).unwrap()
+        ).map_err(|e| PoolError::QuoteFailed(e.to_string()))
     }
 
     fn get_update_accounts(&self) -> Vec<Pubkey> {
@@ -154,7 +170,7 @@ impl PoolOperations for OrcaPool {
         true
     }
 
-    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) { 
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> { 
         let ids: Vec<String> = self
             .get_mints()
             .iter()
@@ -162,15 +178,14 @@ impl PoolOperations for OrcaPool {
             .collect();
         let id0 = &ids[0];
         let id1 = &ids[1];
-        
-        let acc_data0 = &accounts[0].as_ref().unwrap().data;
-        let acc_data1 = &accounts[1].as_ref().unwrap().data;
+        let pks = self.get_update_accounts();
 
-        let amount0 = unpack_token_account(acc_data0).amount as u128;
-        let amount1 = unpack_token_account(acc_data1).amount as u128;
+        let amount0 = try_unpack_token_amount(&pks[0], &accounts[0])?;
+        let amount1 = try_unpack_token_amount(&pks[1], &accounts[1])?;
 
         self.pool_amounts.insert(id0.clone(), amount0);
         self.pool_amounts.insert(id1.clone(), amount1);
+        Ok(())
     }
 
     fn get_name(&self) -> String {
@@ -178,6 +193,10 @@ impl PoolOperations for OrcaPool {
         "Orca".to_string()
     }
 
+    fn get_own_addr(&self) -> Pubkey {
+        self.address.0
+    }
+
     fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
         let token = self.tokens.get(&mint.to_string()).unwrap();
         
@@ -0,0 +1,260 @@
+use std::fmt::Debug;
+use serde;
+use serde::{Deserialize, Serialize};
+use crate::serialize::token::WrappedPubkey;
+use crate::error::{PoolError, PoolResult};
+use crate::pool::PoolOperations;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anchor_client::Program;
+
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+
+use crate::utils::{anchor_discriminator, derive_token_address_with_program};
+use crate::constants::*;
+use crate::pool_utils::clmm::{tick_array_start, ClmmState};
+use crate::pool_utils::whirlpool::{
+    oracle_address, swap_tick_array_starts, swap_tick_range, tick_array_address, tick_array_starts,
+    unpack_tick_array, WhirlpoolState, MAX_SQRT_PRICE, MIN_SQRT_PRICE, TICK_ARRAY_SIZE,
+};
+
+// tick arrays loaded on each side of the current one (a swap can use up to
+// 3, see swap_tick_range)
+const TICK_ARRAYS_PER_SIDE: i32 = 2;
+
+lazy_static! {
+    static ref WHIRLPOOL_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "Whirlpool");
+    static ref TICK_ARRAY_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "TickArray");
+    static ref SWAP_V2_DISCRIMINATOR: [u8; 8] = anchor_discriminator("global", "swap_v2");
+}
+
+// orca's whirlpool json (eg, api.mainnet.orca.so/v1/whirlpool/list), one pool per file
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OrcaWhirlpoolPool {
+    pub address: WrappedPubkey,
+    pub token_mint_a: WrappedPubkey,
+    pub token_mint_b: WrappedPubkey,
+    pub token_vault_a: WrappedPubkey,
+    pub token_vault_b: WrappedPubkey,
+    pub token_program_a: WrappedPubkey,
+    pub token_program_b: WrappedPubkey,
+    pub decimals_a: u64,
+    pub decimals_b: u64,
+    // to set later
+    #[serde(skip)]
+    pub whirlpool: Option<WhirlpoolState>,
+    #[serde(skip)]
+    pub clmm: Option<ClmmState>,
+    // start ticks of the tick arrays in get_update_accounts
+    #[serde(skip)]
+    pub tick_arrays: Vec<i32>,
+}
+
+impl OrcaWhirlpoolPool {
+    fn token_idx(&self, mint: &Pubkey) -> PoolResult<usize> {
+        if *mint == self.token_mint_a.0 {
+            Ok(0)
+        } else if *mint == self.token_mint_b.0 {
+            Ok(1)
+        } else {
+            Err(PoolError::UnknownMint(*mint))
+        }
+    }
+
+    fn tick_array(&self, start: i32) -> Pubkey {
+        tick_array_address(&ORCA_WHIRLPOOL_PROGRAM_ID, &self.address.0, start)
+    }
+
+    /// the 3 tick arrays a swap walks through, in swap order
+    fn swap_tick_arrays(&self, a_to_b: bool) -> PoolResult<Vec<Pubkey>> {
+        let whirlpool = self.whirlpool
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))?;
+        let starts = swap_tick_array_starts(whirlpool.tick_current_index, whirlpool.tick_spacing, a_to_b);
+        Ok(starts.iter().map(|start| self.tick_array(*start)).collect())
+    }
+}
+
+impl PoolOperations for OrcaWhirlpoolPool {
+    fn get_name(&self) -> String {
+        "OrcaWhirlpool".to_string()
+    }
+
+    fn get_own_addr(&self) -> Pubkey {
+        self.address.0
+    }
+
+    // the tick arrays follow the price -- they change as it moves
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        let mut accounts = vec![self.address.0];
+        accounts.extend(self.tick_arrays.iter().map(|start| self.tick_array(*start)));
+        accounts
+    }
+
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> {
+        let pks = self.get_update_accounts();
+        if accounts.len() != pks.len() {
+            return Err(PoolError::InvalidAccountData(self.address.0, "tick arrays out of sync".to_string()));
+        }
+
+        let pool_acc = accounts[0].as_ref().ok_or(PoolError::MissingAccount(pks[0]))?;
+        let whirlpool = WhirlpoolState::unpack(&pool_acc.data, &WHIRLPOOL_DISCRIMINATOR)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[0], "not a whirlpool".to_string()))?;
+        let span = whirlpool.tick_array_span();
+
+        // uninitialized arrays dont exist -- the price cant be walked into them
+        let mut ticks = vec![];
+        let mut loaded = vec![];
+        for (pk, account) in pks[1..].iter().zip(accounts[1..].iter()) {
+            if let Some(acc) = account {
+                let (start, array_ticks) =
+                    unpack_tick_array(&acc.data, &TICK_ARRAY_DISCRIMINATOR, whirlpool.tick_spacing)
+                        .ok_or_else(|| PoolError::InvalidAccountData(*pk, "not a tick array".to_string()))?;
+                loaded.push(start);
+                ticks.extend(array_ticks);
+            }
+        }
+        ticks.sort_by_key(|t| t.tick);
+
+        // contiguous loaded arrays around the current one
+        let current = tick_array_start(whirlpool.tick_current_index, whirlpool.tick_spacing, TICK_ARRAY_SIZE);
+        let tick_range = if loaded.contains(&current) {
+            let mut lower = current;
+            while loaded.contains(&(lower - span)) {
+                lower -= span;
+            }
+            let mut upper = current + span;
+            while loaded.contains(&upper) {
+                upper += span;
+            }
+            (lower, upper)
+        } else {
+            // price moved off what we loaded -- only quote inside the current
+            // tick until the next refresh
+            (whirlpool.tick_current_index, whirlpool.tick_current_index + 1)
+        };
+        // nor past the arrays the swap ix passes
+        let (lower, upper) = swap_tick_range(whirlpool.tick_current_index, whirlpool.tick_spacing);
+        let tick_range = (tick_range.0.max(lower), tick_range.1.min(upper));
+
+        self.clmm = Some(ClmmState {
+            sqrt_price_x64: whirlpool.sqrt_price,
+            tick_current: whirlpool.tick_current_index,
+            liquidity: whirlpool.liquidity,
+            fee_rate: whirlpool.fee_rate as u32,
+            ticks,
+            tick_range,
+        });
+        self.whirlpool = Some(whirlpool);
+        self.tick_arrays = tick_array_starts(
+            whirlpool.tick_current_index,
+            whirlpool.tick_spacing,
+            TICK_ARRAYS_PER_SIDE,
+        );
+        Ok(())
+    }
+
+    fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
+        if *mint == self.token_mint_a.0 { self.token_vault_a.0 } else { self.token_vault_b.0 }
+    }
+
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints = vec![self.token_mint_a.0, self.token_mint_b.0];
+        // sort so that its consistent across different pools
+        mints.sort();
+        mints
+    }
+
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        if *mint == self.token_mint_a.0 { self.decimals_a } else { self.decimals_b }
+    }
+
+    fn get_quote_with_amounts_scaled(
+        &self,
+        scaled_amount_in: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> PoolResult<u128> {
+        let idx_in = self.token_idx(mint_in)?;
+        self.token_idx(mint_out)?;
+        let clmm = self.clmm.as_ref()
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))?;
+
+        let result = clmm.swap_exact_in(scaled_amount_in, idx_in == 0)
+            .ok_or_else(|| PoolError::QuoteFailed("whirlpool overflow".to_string()))?;
+        // walked off the loaded tick arrays -- the swap would fail on-chain
+        if result.amount_in < scaled_amount_in {
+            return Ok(0);
+        }
+        Ok(result.amount_out)
+    }
+
+    fn swap_ix(&self,
+        _program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
+        let a_to_b = self.token_idx(mint_in)? == 0;
+        self.token_idx(mint_out)?;
+        let tick_arrays = self.swap_tick_arrays(a_to_b)?;
+
+        let user_a = derive_token_address_with_program(owner, &self.token_mint_a.0, &self.token_program_a.0);
+        let user_b = derive_token_address_with_program(owner, &self.token_mint_b.0, &self.token_program_b.0);
+        let sqrt_price_limit = if a_to_b { MIN_SQRT_PRICE } else { MAX_SQRT_PRICE };
+
+        // swap_v2 { amount, other_amount_threshold, sqrt_price_limit,
+        //   amount_specified_is_input, a_to_b, remaining_accounts_info: None }
+        let mut data = SWAP_V2_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&min_amount_out.to_le_bytes());
+        data.extend_from_slice(&sqrt_price_limit.to_le_bytes());
+        data.push(1);
+        data.push(a_to_b as u8);
+        data.push(0);
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(self.token_program_a.0, false),
+            AccountMeta::new_readonly(self.token_program_b.0, false),
+            AccountMeta::new_readonly(*MEMO_PROGRAM_ID, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(self.address.0, false),
+            AccountMeta::new_readonly(self.token_mint_a.0, false),
+            AccountMeta::new_readonly(self.token_mint_b.0, false),
+            AccountMeta::new(user_a, false),
+            AccountMeta::new(self.token_vault_a.0, false),
+            AccountMeta::new(user_b, false),
+            AccountMeta::new(self.token_vault_b.0, false),
+        ];
+        accounts.extend(tick_arrays.into_iter().map(|pk| AccountMeta::new(pk, false)));
+        accounts.push(AccountMeta::new(oracle_address(&ORCA_WHIRLPOOL_PROGRAM_ID, &self.address.0), false));
+
+        Ok(vec![Instruction {
+            program_id: *ORCA_WHIRLPOOL_PROGRAM_ID,
+            accounts,
+            data,
+        }])
+    }
+
+    fn uses_swap_state(&self) -> bool {
+        false
+    }
+
+    fn can_trade(&self,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey
+    ) -> bool {
+        match &self.clmm {
+            Some(clmm) => {
+                clmm.liquidity > 0
+                    && self.token_idx(mint_in).is_ok()
+                    && self.token_idx(mint_out).is_ok()
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::fmt::Debug;
+use serde;
+use serde::{Deserialize, Serialize};
+use crate::serialize::token::WrappedPubkey;
+use crate::error::{PoolError, PoolResult};
+use crate::pool::PoolOperations;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anchor_client::Program;
+
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+
+use crate::utils::{derive_token_address, unix_now};
+use crate::constants::*;
+use crate::pool_utils::phoenix::{PhoenixMarket, LOG_AUTHORITY_SEED};
+
+// Swap instruction tag + OrderPacket::ImmediateOrCancel
+const SWAP_TAG: u8 = 0;
+const IMMEDIATE_OR_CANCEL: u8 = 2;
+const SIDE_BID: u8 = 0;
+const SIDE_ASK: u8 = 1;
+const SELF_TRADE_CANCEL_PROVIDE: u8 = 1;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PhoenixPool {
+    pub address: WrappedPubkey,
+    pub base_mint: WrappedPubkey,
+    pub quote_mint: WrappedPubkey,
+    pub base_decimals: u64,
+    pub quote_decimals: u64,
+    // to set later
+    #[serde(skip)]
+    pub market: Option<PhoenixMarket>,
+}
+
+impl PhoenixPool {
+    fn token_idx(&self, mint: &Pubkey) -> PoolResult<usize> {
+        if *mint == self.base_mint.0 {
+            Ok(0)
+        } else if *mint == self.quote_mint.0 {
+            Ok(1)
+        } else {
+            Err(PoolError::UnknownMint(*mint))
+        }
+    }
+
+    fn market(&self) -> PoolResult<&PhoenixMarket> {
+        self.market.as_ref()
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))
+    }
+}
+
+// borsh Option<u64>
+fn push_option(data: &mut Vec<u8>, value: Option<u64>) {
+    match value {
+        Some(value) => {
+            data.push(1);
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        None => data.push(0),
+    }
+}
+
+impl PoolOperations for PhoenixPool {
+    fn get_name(&self) -> String {
+        "Phoenix".to_string()
+    }
+
+    fn get_own_addr(&self) -> Pubkey {
+        self.address.0
+    }
+
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        vec![self.address.0]
+    }
+
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> {
+        let pks = self.get_update_accounts();
+        let market_acc = accounts[0].as_ref().ok_or(PoolError::MissingAccount(pks[0]))?;
+        let now = unix_now().max(0) as u64;
+        let market = PhoenixMarket::unpack(&market_acc.data, now)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[0], "not a phoenix market".to_string()))?;
+        if market.base_mint != self.base_mint.0 || market.quote_mint != self.quote_mint.0 {
+            return Err(PoolError::InvalidAccountData(pks[0], "mints dont match the market".to_string()));
+        }
+        self.market = Some(market);
+        Ok(())
+    }
+
+    fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
+        match &self.market {
+            Some(market) if *mint == self.base_mint.0 => market.base_vault,
+            Some(market) => market.quote_vault,
+            None => Pubkey::default(),
+        }
+    }
+
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints = vec![self.base_mint.0, self.quote_mint.0];
+        // sort so that its consistent across different pools
+        mints.sort();
+        mints
+    }
+
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        if *mint == self.base_mint.0 { self.base_decimals } else { self.quote_decimals }
+    }
+
+    fn get_quote_with_amounts_scaled(
+        &self,
+        scaled_amount_in: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> PoolResult<u128> {
+        let idx_in = self.token_idx(mint_in)?;
+        self.token_idx(mint_out)?;
+        let market = self.market()?;
+        if !market.active {
+            return Ok(0);
+        }
+        let amount_in = scaled_amount_in.min(u64::MAX as u128) as u64;
+        let amount_out = if idx_in == 1 {
+            // buy: quote -> base
+            market.quote_buy(amount_in)
+        } else {
+            // sell: base -> quote
+            market.quote_sell(amount_in)
+        };
+        Ok(amount_out)
+    }
+
+    fn swap_ix(&self,
+        _program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
+        let is_buy = self.token_idx(mint_in)? == 1;
+        self.token_idx(mint_out)?;
+        let market = self.market()?;
+
+        // ImmediateOrCancel { side, price_in_ticks, num_base_lots, num_quote_lots,
+        // min_base_lots_to_fill, min_quote_lots_to_fill, self_trade_behavior,
+        // match_limit, client_order_id, use_only_deposited_funds,
+        // last_valid_slot, last_valid_unix_timestamp_in_seconds }
+        // buys spend quote lots (fees included), sells sell base lots, and
+        // each has to fill at least min_amount_out on the side it receives
+        let (side, num_base_lots, num_quote_lots, min_base_lots, min_quote_lots) = if is_buy {
+            let min_base_lots = (min_amount_out + market.base_lot_size - 1) / market.base_lot_size;
+            (SIDE_BID, 0, amount_in / market.quote_lot_size, min_base_lots, 0)
+        } else {
+            let min_quote_lots = (min_amount_out + market.quote_lot_size - 1) / market.quote_lot_size;
+            (SIDE_ASK, amount_in / market.base_lot_size, 0, 0, min_quote_lots)
+        };
+        let mut data = vec![SWAP_TAG, IMMEDIATE_OR_CANCEL, side];
+        push_option(&mut data, None);
+        data.extend_from_slice(&num_base_lots.to_le_bytes());
+        data.extend_from_slice(&num_quote_lots.to_le_bytes());
+        data.extend_from_slice(&min_base_lots.to_le_bytes());
+        data.extend_from_slice(&min_quote_lots.to_le_bytes());
+        data.push(SELF_TRADE_CANCEL_PROVIDE);
+        push_option(&mut data, None);
+        data.extend_from_slice(&0_u128.to_le_bytes());
+        data.push(0);
+        push_option(&mut data, None);
+        push_option(&mut data, None);
+
+        let program_id = *PHOENIX_PROGRAM_ID;
+        let (log_authority, _) = Pubkey::find_program_address(&[LOG_AUTHORITY_SEED], &program_id);
+        let accounts = vec![
+            AccountMeta::new_readonly(program_id, false),
+            AccountMeta::new_readonly(log_authority, false),
+            AccountMeta::new(self.address.0, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(derive_token_address(owner, &self.base_mint.0), false),
+            AccountMeta::new(derive_token_address(owner, &self.quote_mint.0), false),
+            AccountMeta::new(market.base_vault, false),
+            AccountMeta::new(market.quote_vault, false),
+            AccountMeta::new_readonly(*TOKEN_PROGRAM_ID, false),
+        ];
+
+        Ok(vec![Instruction {
+            program_id,
+            accounts,
+            data,
+        }])
+    }
+
+    fn uses_swap_state(&self) -> bool {
+        false
+    }
+
+    fn can_trade(&self,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey
+    ) -> bool {
+        match (self.market(), self.token_idx(mint_in), self.token_idx(mint_out)) {
+            (Ok(market), Ok(1), Ok(_)) => market.active && !market.asks.is_empty(),
+            (Ok(market), Ok(_), Ok(_)) => market.active && !market.bids.is_empty(),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use std::fmt::Debug;
+use serde;
+use serde::{Deserialize, Serialize};
+use crate::serialize::token::WrappedPubkey;
+use crate::error::{PoolError, PoolResult};
+use crate::pool::PoolOperations;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anchor_client::Program;
+
+use solana_program::program_pack::Pack;
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::rent::Rent;
+use solana_sdk::{system_instruction, system_program};
+use spl_token::native_mint;
+
+use crate::utils::{anchor_discriminator, derive_token_address, derive_token_address_with_program};
+use crate::constants::*;
+use crate::pool_utils::dlmm::event_authority;
+use crate::pool_utils::pump_fun::{
+    BondingCurve, PumpGlobal, BONDING_CURVE_SEED, CREATOR_VAULT_SEED,
+};
+
+// sol is native on the curve -- buys are funded by unwrapping through a
+// temporary wsol account (seeded off the owner + the curve, so each buy in
+// a route gets its own) and sells are wrapped back
+const UNWRAP_SEED_PREFIX: &str = "unwrap";
+const WSOL_DECIMALS: u64 = 9;
+
+lazy_static! {
+    static ref BONDING_CURVE_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "BondingCurve");
+    static ref GLOBAL_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "Global");
+    static ref BUY_DISCRIMINATOR: [u8; 8] = anchor_discriminator("global", "buy");
+    static ref SELL_DISCRIMINATOR: [u8; 8] = anchor_discriminator("global", "sell");
+}
+
+// a pre-graduation pump.fun token -- traded against wsol in the graph
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PumpFunPool {
+    pub mint: WrappedPubkey,
+    pub token_program: WrappedPubkey,
+    pub decimals: u64,
+    // to set later
+    #[serde(skip)]
+    pub curve: Option<BondingCurve>,
+    #[serde(skip)]
+    pub global: Option<PumpGlobal>,
+}
+
+impl PumpFunPool {
+    fn bonding_curve(&self) -> Pubkey {
+        Pubkey::find_program_address(&[BONDING_CURVE_SEED, self.mint.0.as_ref()], &PUMP_FUN_PROGRAM_ID).0
+    }
+
+    // true = sol in (buy), false = token in (sell)
+    fn is_buy(&self, mint_in: &Pubkey, mint_out: &Pubkey) -> PoolResult<bool> {
+        if *mint_in == native_mint::id() && *mint_out == self.mint.0 {
+            Ok(true)
+        } else if *mint_in == self.mint.0 && *mint_out == native_mint::id() {
+            Ok(false)
+        } else if *mint_in != self.mint.0 && *mint_in != native_mint::id() {
+            Err(PoolError::UnknownMint(*mint_in))
+        } else {
+            Err(PoolError::UnknownMint(*mint_out))
+        }
+    }
+
+    fn quote(&self, amount_in: u128, is_buy: bool) -> PoolResult<u128> {
+        let (curve, global) = match (self.curve, self.global) {
+            (Some(curve), Some(global)) => (curve, global),
+            _ => return Err(PoolError::NotLoaded(self.get_name())),
+        };
+        // graduated -- the liquidity lives on pumpswap now
+        if curve.complete {
+            return Ok(0);
+        }
+        let fee_basis_points = global.total_fee_basis_points(&curve);
+        let out = if is_buy {
+            curve.buy_tokens_out(amount_in, fee_basis_points)
+        } else {
+            curve.sell_sol_out(amount_in, fee_basis_points)
+        };
+        // sells past the real sol reserves fail on-chain
+        Ok(out.unwrap_or(0))
+    }
+
+    // seeds are capped at 32 bytes -- a prefix of the curve is unique enough
+    fn unwrap_seed(&self) -> String {
+        format!("{}{}", UNWRAP_SEED_PREFIX, &self.bonding_curve().to_string()[..24])
+    }
+
+    /// moves `amount` out of the owner's wsol ata as native sol
+    fn unwrap_ixs(&self, owner: &Pubkey, amount: u64) -> PoolResult<Vec<Instruction>> {
+        let token_program = *TOKEN_PROGRAM_ID;
+        let seed = self.unwrap_seed();
+        let temp = Pubkey::create_with_seed(owner, &seed, &token_program)
+            .map_err(|err| PoolError::MissingSwapAccount(err.to_string()))?;
+        let wsol_ata = derive_token_address(owner, &native_mint::id());
+        let build_err = |err: solana_sdk::program_error::ProgramError| PoolError::QuoteFailed(err.to_string());
+        // rent exempt minimum for the account (returned when it is closed)
+        let len = spl_token::state::Account::LEN;
+        let rent = Rent::default().minimum_balance(len);
+        Ok(vec![
+            system_instruction::create_account_with_seed(
+                owner, &temp, owner, &seed, rent, len as u64, &token_program,
+            ),
+            spl_token::instruction::initialize_account2(&token_program, &temp, &native_mint::id(), owner)
+                .map_err(build_err)?,
+            spl_token::instruction::transfer(&token_program, &wsol_ata, &temp, owner, &[], amount)
+                .map_err(build_err)?,
+            spl_token::instruction::close_account(&token_program, &temp, owner, owner, &[])
+                .map_err(build_err)?,
+        ])
+    }
+
+    /// wraps `amount` lamports back into the owner's wsol ata
+    fn wrap_ixs(&self, owner: &Pubkey, amount: u64) -> PoolResult<Vec<Instruction>> {
+        let wsol_ata = derive_token_address(owner, &native_mint::id());
+        Ok(vec![
+            system_instruction::transfer(owner, &wsol_ata, amount),
+            spl_token::instruction::sync_native(&TOKEN_PROGRAM_ID, &wsol_ata)
+                .map_err(|err| PoolError::QuoteFailed(err.to_string()))?,
+        ])
+    }
+}
+
+impl PoolOperations for PumpFunPool {
+    fn get_name(&self) -> String {
+        "PumpFun".to_string()
+    }
+
+    fn get_own_addr(&self) -> Pubkey {
+        self.bonding_curve()
+    }
+
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        vec![self.bonding_curve(), *PUMP_FUN_GLOBAL]
+    }
+
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> {
+        let pks = self.get_update_accounts();
+
+        let curve_acc = accounts[0].as_ref().ok_or(PoolError::MissingAccount(pks[0]))?;
+        let curve = BondingCurve::unpack(&curve_acc.data, &BONDING_CURVE_DISCRIMINATOR)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[0], "not a bonding curve".to_string()))?;
+
+        let global_acc = accounts[1].as_ref().ok_or(PoolError::MissingAccount(pks[1]))?;
+        let global = PumpGlobal::unpack(&global_acc.data, &GLOBAL_DISCRIMINATOR)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[1], "not the pump global".to_string()))?;
+
+        self.curve = Some(curve);
+        self.global = Some(global);
+        Ok(())
+    }
+
+    // the curve holds the sol itself, the tokens sit in its ata
+    fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
+        let bonding_curve = self.bonding_curve();
+        if *mint == self.mint.0 {
+            derive_token_address_with_program(&bonding_curve, &self.mint.0, &self.token_program.0)
+        } else {
+            bonding_curve
+        }
+    }
+
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints = vec![self.mint.0, native_mint::id()];
+        // sort so that its consistent across different pools
+        mints.sort();
+        mints
+    }
+
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        if *mint == self.mint.0 { self.decimals } else { WSOL_DECIMALS }
+    }
+
+    fn get_quote_with_amounts_scaled(
+        &self,
+        scaled_amount_in: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> PoolResult<u128> {
+        let is_buy = self.is_buy(mint_in, mint_out)?;
+        self.quote(scaled_amount_in, is_buy)
+    }
+
+    fn swap_ix(&self,
+        _program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
+        let is_buy = self.is_buy(mint_in, mint_out)?;
+        let (curve, global) = match (self.curve, self.global) {
+            (Some(curve), Some(global)) => (curve, global),
+            _ => return Err(PoolError::NotLoaded(self.get_name())),
+        };
+        let out = self.quote(amount_in as u128, is_buy)?;
+        if out == 0 {
+            return Err(PoolError::QuoteFailed("bonding curve cant fill the swap".to_string()));
+        }
+        if out < min_amount_out as u128 {
+            return Err(PoolError::QuoteFailed("swap is short of the min out".to_string()));
+        }
+
+        // buy { amount (tokens out), max_sol_cost } -- buys are exact out so the
+        // tokens are quoted from the loaded curve, capped at amount_in
+        // sell { amount (tokens in), min_sol_output } -- the quoted sol is what
+        // gets wrapped back, so the sell must pay at least that (otherwise the
+        // wrap would be topped up from the owner's own sol)
+        let mut data = if is_buy { BUY_DISCRIMINATOR.to_vec() } else { SELL_DISCRIMINATOR.to_vec() };
+        if is_buy {
+            data.extend_from_slice(&(out as u64).to_le_bytes());
+            data.extend_from_slice(&amount_in.to_le_bytes());
+        } else {
+            data.extend_from_slice(&amount_in.to_le_bytes());
+            data.extend_from_slice(&(out as u64).to_le_bytes());
+        }
+
+        let program_id = *PUMP_FUN_PROGRAM_ID;
+        let token_program = self.token_program.0;
+        let bonding_curve = self.bonding_curve();
+        let curve_ata = derive_token_address_with_program(&bonding_curve, &self.mint.0, &token_program);
+        let user_ata = derive_token_address_with_program(owner, &self.mint.0, &token_program);
+        let (creator_vault, _) = Pubkey::find_program_address(
+            &[CREATOR_VAULT_SEED, curve.creator.as_ref()],
+            &program_id,
+        );
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*PUMP_FUN_GLOBAL, false),
+            AccountMeta::new(global.fee_recipient, false),
+            AccountMeta::new_readonly(self.mint.0, false),
+            AccountMeta::new(bonding_curve, false),
+            AccountMeta::new(curve_ata, false),
+            AccountMeta::new(user_ata, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        // buy and sell order the token program / creator vault differently
+        if is_buy {
+            accounts.push(AccountMeta::new_readonly(token_program, false));
+            accounts.push(AccountMeta::new(creator_vault, false));
+        } else {
+            accounts.push(AccountMeta::new(creator_vault, false));
+            accounts.push(AccountMeta::new_readonly(token_program, false));
+        }
+        accounts.push(AccountMeta::new_readonly(event_authority(&program_id), false));
+        accounts.push(AccountMeta::new_readonly(program_id, false));
+
+        let swap_ix = Instruction {
+            program_id,
+            accounts,
+            data,
+        };
+        // a buy unwraps its quoted input -- the hop before it has to have paid
+        // out at least that much (its min out)
+        let ixs = if is_buy {
+            let mut ixs = self.unwrap_ixs(owner, amount_in)?;
+            ixs.push(swap_ix);
+            ixs
+        } else {
+            let mut ixs = vec![swap_ix];
+            ixs.extend(self.wrap_ixs(owner, out as u64)?);
+            ixs
+        };
+        Ok(ixs)
+    }
+
+    fn uses_swap_state(&self) -> bool {
+        false
+    }
+
+    fn can_trade(&self,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey
+    ) -> bool {
+        match self.curve {
+            Some(curve) => !curve.complete && self.is_buy(mint_in, mint_out).is_ok(),
+            None => false,
+        }
+    }
+}
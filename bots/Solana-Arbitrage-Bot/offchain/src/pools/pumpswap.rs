@@ -0,0 +1,241 @@
+use std::fmt::Debug;
+use serde;
+use serde::{Deserialize, Serialize};
+use crate::serialize::token::{WrappedPubkey, try_unpack_token_amount};
+use crate::error::{PoolError, PoolResult};
+use crate::pool::PoolOperations;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anchor_client::Program;
+
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::system_program;
+
+use crate::utils::{anchor_discriminator, derive_token_address_with_program};
+use crate::constants::*;
+use crate::pool_utils::dlmm::event_authority;
+use crate::pool_utils::pumpswap::{
+    buy_base_out, sell_quote_out, unpack_coin_creator, GlobalConfig, CREATOR_VAULT_SEED,
+};
+
+lazy_static! {
+    static ref POOL_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "Pool");
+    static ref GLOBAL_CONFIG_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "GlobalConfig");
+    static ref BUY_DISCRIMINATOR: [u8; 8] = anchor_discriminator("global", "buy");
+    static ref SELL_DISCRIMINATOR: [u8; 8] = anchor_discriminator("global", "sell");
+}
+
+// pumpswap pools (graduated pump.fun tokens) -- base is the token, quote
+// usually wsol. buys pay quote for base, sells pay base for quote
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PumpSwapPool {
+    pub address: WrappedPubkey,
+    pub base_mint: WrappedPubkey,
+    pub quote_mint: WrappedPubkey,
+    pub pool_base_token_account: WrappedPubkey,
+    pub pool_quote_token_account: WrappedPubkey,
+    pub base_token_program: WrappedPubkey,
+    pub quote_token_program: WrappedPubkey,
+    pub base_decimals: u64,
+    pub quote_decimals: u64,
+    // to set later
+    #[serde(skip)]
+    pub coin_creator: Pubkey,
+    #[serde(skip)]
+    pub config: Option<GlobalConfig>,
+    #[serde(skip)]
+    pub reserves: [u128; 2],
+}
+
+impl PumpSwapPool {
+    fn token_idx(&self, mint: &Pubkey) -> PoolResult<usize> {
+        if *mint == self.base_mint.0 {
+            Ok(0)
+        } else if *mint == self.quote_mint.0 {
+            Ok(1)
+        } else {
+            Err(PoolError::UnknownMint(*mint))
+        }
+    }
+
+    fn fee_basis_points(&self, config: &GlobalConfig) -> [u64; 3] {
+        config.fee_basis_points(self.coin_creator != Pubkey::default())
+    }
+
+    // quote in -> base out (buy) or base in -> quote out (sell)
+    fn quote(&self, amount_in: u128, is_buy: bool) -> PoolResult<u128> {
+        let config = self.config
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))?;
+        let enabled = if is_buy { config.buy_enabled() } else { config.sell_enabled() };
+        if !enabled {
+            return Ok(0);
+        }
+        let fees = self.fee_basis_points(&config);
+        let [base_reserve, quote_reserve] = self.reserves;
+        let out = if is_buy {
+            buy_base_out(amount_in, base_reserve, quote_reserve, &fees)
+        } else {
+            sell_quote_out(amount_in, base_reserve, quote_reserve, &fees)
+        };
+        out.ok_or_else(|| PoolError::QuoteFailed("pumpswap overflow".to_string()))
+    }
+}
+
+impl PoolOperations for PumpSwapPool {
+    fn get_name(&self) -> String {
+        "PumpSwap".to_string()
+    }
+
+    fn get_own_addr(&self) -> Pubkey {
+        self.address.0
+    }
+
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        vec![
+            self.address.0,
+            *PUMPSWAP_GLOBAL_CONFIG,
+            self.pool_base_token_account.0,
+            self.pool_quote_token_account.0,
+        ]
+    }
+
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> {
+        let pks = self.get_update_accounts();
+
+        let pool_acc = accounts[0].as_ref().ok_or(PoolError::MissingAccount(pks[0]))?;
+        let coin_creator = unpack_coin_creator(&pool_acc.data, &POOL_DISCRIMINATOR)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[0], "not a pumpswap pool".to_string()))?;
+
+        let config_acc = accounts[1].as_ref().ok_or(PoolError::MissingAccount(pks[1]))?;
+        let config = GlobalConfig::unpack(&config_acc.data, &GLOBAL_CONFIG_DISCRIMINATOR)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[1], "not a pumpswap global config".to_string()))?;
+
+        let base_amount = try_unpack_token_amount(&pks[2], &accounts[2])?;
+        let quote_amount = try_unpack_token_amount(&pks[3], &accounts[3])?;
+
+        self.coin_creator = coin_creator;
+        self.config = Some(config);
+        self.reserves = [base_amount, quote_amount];
+        Ok(())
+    }
+
+    fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
+        if *mint == self.base_mint.0 { self.pool_base_token_account.0 } else { self.pool_quote_token_account.0 }
+    }
+
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints = vec![self.base_mint.0, self.quote_mint.0];
+        // sort so that its consistent across different pools
+        mints.sort();
+        mints
+    }
+
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        if *mint == self.base_mint.0 { self.base_decimals } else { self.quote_decimals }
+    }
+
+    fn get_quote_with_amounts_scaled(
+        &self,
+        scaled_amount_in: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> PoolResult<u128> {
+        let idx_in = self.token_idx(mint_in)?;
+        self.token_idx(mint_out)?;
+        self.quote(scaled_amount_in, idx_in == 1)
+    }
+
+    fn swap_ix(&self,
+        _program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
+        let is_buy = self.token_idx(mint_in)? == 1;
+        self.token_idx(mint_out)?;
+        let config = self.config
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))?;
+
+        // buy { base_amount_out, max_quote_amount_in } -- buys are exact out so
+        // the base out is quoted from the loaded reserves, capped at amount_in
+        // sell { base_amount_in, min_quote_amount_out }
+        let mut data = if is_buy { BUY_DISCRIMINATOR.to_vec() } else { SELL_DISCRIMINATOR.to_vec() };
+        if is_buy {
+            let base_out = self.quote(amount_in as u128, true)?;
+            if base_out == 0 {
+                return Err(PoolError::QuoteFailed("buy rounds to zero".to_string()));
+            }
+            if base_out < min_amount_out as u128 {
+                return Err(PoolError::QuoteFailed("buy is short of the min out".to_string()));
+            }
+            data.extend_from_slice(&(base_out as u64).to_le_bytes());
+            data.extend_from_slice(&amount_in.to_le_bytes());
+        } else {
+            data.extend_from_slice(&amount_in.to_le_bytes());
+            data.extend_from_slice(&min_amount_out.to_le_bytes());
+        }
+
+        let program_id = *PUMPSWAP_PROGRAM_ID;
+        let quote_program = self.quote_token_program.0;
+        let user_base = derive_token_address_with_program(owner, &self.base_mint.0, &self.base_token_program.0);
+        let user_quote = derive_token_address_with_program(owner, &self.quote_mint.0, &quote_program);
+        let fee_recipient = config.protocol_fee_recipient;
+        let fee_recipient_ata = derive_token_address_with_program(&fee_recipient, &self.quote_mint.0, &quote_program);
+        let (creator_vault, _) = Pubkey::find_program_address(
+            &[CREATOR_VAULT_SEED, self.coin_creator.as_ref()],
+            &program_id,
+        );
+        let creator_vault_ata = derive_token_address_with_program(&creator_vault, &self.quote_mint.0, &quote_program);
+
+        // same accounts for both directions
+        let accounts = vec![
+            AccountMeta::new(self.address.0, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(*PUMPSWAP_GLOBAL_CONFIG, false),
+            AccountMeta::new_readonly(self.base_mint.0, false),
+            AccountMeta::new_readonly(self.quote_mint.0, false),
+            AccountMeta::new(user_base, false),
+            AccountMeta::new(user_quote, false),
+            AccountMeta::new(self.pool_base_token_account.0, false),
+            AccountMeta::new(self.pool_quote_token_account.0, false),
+            AccountMeta::new_readonly(fee_recipient, false),
+            AccountMeta::new(fee_recipient_ata, false),
+            AccountMeta::new_readonly(self.base_token_program.0, false),
+            AccountMeta::new_readonly(quote_program, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(*ASSOCIATED_TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(event_authority(&program_id), false),
+            AccountMeta::new_readonly(program_id, false),
+            AccountMeta::new(creator_vault_ata, false),
+            AccountMeta::new_readonly(creator_vault, false),
+        ];
+
+        Ok(vec![Instruction {
+            program_id,
+            accounts,
+            data,
+        }])
+    }
+
+    fn uses_swap_state(&self) -> bool {
+        false
+    }
+
+    fn can_trade(&self,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey
+    ) -> bool {
+        match (self.config, self.token_idx(mint_in), self.token_idx(mint_out)) {
+            (Some(config), Ok(idx_in), Ok(_)) => {
+                let enabled = if idx_in == 1 { config.buy_enabled() } else { config.sell_enabled() };
+                enabled && self.reserves.iter().all(|reserve| *reserve > 0)
+            }
+            _ => false,
+        }
+    }
+}
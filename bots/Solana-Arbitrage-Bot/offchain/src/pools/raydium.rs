@@ -0,0 +1,212 @@
+use std::fmt::Debug;
+use serde;
+use serde::{Deserialize, Serialize};
+use crate::serialize::token::{WrappedPubkey, try_unpack_token_amount};
+use crate::error::{PoolError, PoolResult};
+use crate::pool::PoolOperations;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anchor_client::Program;
+
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+
+use crate::utils::derive_token_address;
+use crate::constants::*;
+use crate::pool_utils::raydium::{
+    AmmState, unpack_open_orders_totals, total_reserve, swap_base_in, SWAP_BASE_IN_TAG,
+};
+
+// raydium's liquidity json (eg, api.raydium.io/v2/sdk/liquidity/mainnet.json)
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RaydiumAmmV4Pool {
+    pub id: WrappedPubkey,
+    pub base_mint: WrappedPubkey,
+    pub quote_mint: WrappedPubkey,
+    pub base_decimals: u64,
+    pub quote_decimals: u64,
+    pub authority: WrappedPubkey,
+    pub open_orders: WrappedPubkey,
+    pub target_orders: WrappedPubkey,
+    pub base_vault: WrappedPubkey,
+    pub quote_vault: WrappedPubkey,
+    // serum/openbook market the amm sits on
+    pub market_program_id: WrappedPubkey,
+    pub market_id: WrappedPubkey,
+    pub market_authority: WrappedPubkey,
+    pub market_base_vault: WrappedPubkey,
+    pub market_quote_vault: WrappedPubkey,
+    pub market_bids: WrappedPubkey,
+    pub market_asks: WrappedPubkey,
+    pub market_event_queue: WrappedPubkey,
+    // to set later
+    #[serde(skip)]
+    pub amm_state: Option<AmmState>,
+    #[serde(skip)]
+    pub base_amount: u128,
+    #[serde(skip)]
+    pub quote_amount: u128,
+    // amm liquidity sitting in open orders
+    #[serde(skip)]
+    pub base_oo_total: u128,
+    #[serde(skip)]
+    pub quote_oo_total: u128,
+}
+
+impl RaydiumAmmV4Pool {
+    /// (base, quote) reserves the program prices against
+    fn reserves(&self) -> PoolResult<(u128, u128)> {
+        let state = self.amm_state
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))?;
+        let base = total_reserve(self.base_amount, self.base_oo_total, state.need_take_pnl_coin as u128);
+        let quote = total_reserve(self.quote_amount, self.quote_oo_total, state.need_take_pnl_pc as u128);
+        match (base, quote) {
+            (Some(base), Some(quote)) => Ok((base, quote)),
+            _ => Err(PoolError::QuoteFailed("pnl owed exceeds the reserves".to_string())),
+        }
+    }
+}
+
+impl PoolOperations for RaydiumAmmV4Pool {
+    fn get_name(&self) -> String {
+        "RaydiumAmmV4".to_string()
+    }
+
+    fn get_own_addr(&self) -> Pubkey {
+        self.id.0
+    }
+
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        vec![
+            self.id.0,
+            self.base_vault.0,
+            self.quote_vault.0,
+            self.open_orders.0,
+        ]
+    }
+
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> {
+        let pks = self.get_update_accounts();
+
+        let amm_acc = accounts[0].as_ref().ok_or(PoolError::MissingAccount(pks[0]))?;
+        let amm_state = AmmState::unpack(&amm_acc.data)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[0], "not an amm v4 account".to_string()))?;
+
+        let base_amount = try_unpack_token_amount(&pks[1], &accounts[1])?;
+        let quote_amount = try_unpack_token_amount(&pks[2], &accounts[2])?;
+
+        let oo_acc = accounts[3].as_ref().ok_or(PoolError::MissingAccount(pks[3]))?;
+        let (base_oo_total, quote_oo_total) = unpack_open_orders_totals(&oo_acc.data)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[3], "not an open orders account".to_string()))?;
+
+        self.amm_state = Some(amm_state);
+        self.base_amount = base_amount;
+        self.quote_amount = quote_amount;
+        self.base_oo_total = base_oo_total as u128;
+        self.quote_oo_total = quote_oo_total as u128;
+        Ok(())
+    }
+
+    fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
+        if *mint == self.base_mint.0 { self.base_vault.0 } else { self.quote_vault.0 }
+    }
+
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints = vec![self.base_mint.0, self.quote_mint.0];
+        // sort so that its consistent across different pools
+        mints.sort();
+        mints
+    }
+
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        if *mint == self.base_mint.0 { self.base_decimals } else { self.quote_decimals }
+    }
+
+    fn get_quote_with_amounts_scaled(
+        &self,
+        scaled_amount_in: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> PoolResult<u128> {
+        let (base, quote) = self.reserves()?;
+        let (reserve_in, reserve_out) = if *mint_in == self.base_mint.0 && *mint_out == self.quote_mint.0 {
+            (base, quote)
+        } else if *mint_in == self.quote_mint.0 && *mint_out == self.base_mint.0 {
+            (quote, base)
+        } else {
+            return Err(PoolError::UnknownMint(*mint_in));
+        };
+
+        let state = self.amm_state.unwrap(); // loaded by reserves()
+        swap_base_in(
+            scaled_amount_in,
+            reserve_in,
+            reserve_out,
+            state.swap_fee_numerator as u128,
+            state.swap_fee_denominator as u128,
+        ).ok_or_else(|| PoolError::QuoteFailed("amm v4 overflow".to_string()))
+    }
+
+    fn swap_ix(&self,
+        _program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
+        if !self.get_mints().contains(mint_in) {
+            return Err(PoolError::UnknownMint(*mint_in));
+        }
+        let user_src = derive_token_address(owner, mint_in);
+        let user_dst = derive_token_address(owner, mint_out);
+
+        // swap_base_in { amount_in, minimum_amount_out }
+        let mut data = vec![SWAP_BASE_IN_TAG];
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&min_amount_out.to_le_bytes());
+
+        let accounts = vec![
+            AccountMeta::new_readonly(*TOKEN_PROGRAM_ID, false),
+            AccountMeta::new(self.id.0, false),
+            AccountMeta::new_readonly(self.authority.0, false),
+            AccountMeta::new(self.open_orders.0, false),
+            AccountMeta::new(self.target_orders.0, false),
+            AccountMeta::new(self.base_vault.0, false),
+            AccountMeta::new(self.quote_vault.0, false),
+            AccountMeta::new_readonly(self.market_program_id.0, false),
+            AccountMeta::new(self.market_id.0, false),
+            AccountMeta::new(self.market_bids.0, false),
+            AccountMeta::new(self.market_asks.0, false),
+            AccountMeta::new(self.market_event_queue.0, false),
+            AccountMeta::new(self.market_base_vault.0, false),
+            AccountMeta::new(self.market_quote_vault.0, false),
+            AccountMeta::new_readonly(self.market_authority.0, false),
+            AccountMeta::new(user_src, false),
+            AccountMeta::new(user_dst, false),
+            AccountMeta::new_readonly(*owner, true),
+        ];
+
+        Ok(vec![Instruction {
+            program_id: *RAYDIUM_AMM_V4_PROGRAM_ID,
+            accounts,
+            data,
+        }])
+    }
+
+    fn uses_swap_state(&self) -> bool {
+        false
+    }
+
+    fn can_trade(&self,
+        _mint_in: &Pubkey,
+        _mint_out: &Pubkey
+    ) -> bool {
+        match self.reserves() {
+            Ok((base, quote)) => base > 0 && quote > 0,
+            Err(_) => false,
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use std::fmt::Debug;
+use serde;
+use serde::{Deserialize, Serialize};
+use crate::serialize::token::WrappedPubkey;
+use crate::error::{PoolError, PoolResult};
+use crate::pool::PoolOperations;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anchor_client::Program;
+
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+
+use crate::utils::{anchor_discriminator, derive_token_address_with_program};
+use crate::constants::*;
+use crate::pool_utils::clmm::{tick_array_start, ClmmState};
+use crate::pool_utils::raydium_clmm::{
+    bitmap_extension_address, tick_array_address, tick_arrays_around, unpack_tick_array,
+    BitmapExtension, ClmmAmmConfig, ClmmPoolState, TICK_ARRAY_SIZE,
+};
+
+// initialized tick arrays loaded on each side of the price
+const TICK_ARRAYS_PER_SIDE: usize = 3;
+
+lazy_static! {
+    static ref POOL_STATE_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "PoolState");
+    static ref AMM_CONFIG_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "AmmConfig");
+    static ref TICK_ARRAY_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "TickArrayState");
+    static ref BITMAP_EXTENSION_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "TickArrayBitmapExtension");
+    static ref SWAP_V2_DISCRIMINATOR: [u8; 8] = anchor_discriminator("global", "swap_v2");
+}
+
+// token 0/1 are the pool's (sorted) mints
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RaydiumClmmPool {
+    pub id: WrappedPubkey,
+    pub amm_config: WrappedPubkey,
+    pub observation_key: WrappedPubkey,
+    pub token_0_mint: WrappedPubkey,
+    pub token_1_mint: WrappedPubkey,
+    pub token_0_vault: WrappedPubkey,
+    pub token_1_vault: WrappedPubkey,
+    pub token_0_program: WrappedPubkey,
+    pub token_1_program: WrappedPubkey,
+    pub mint_0_decimals: u64,
+    pub mint_1_decimals: u64,
+    // to set later
+    #[serde(skip)]
+    pub pool_state: Option<ClmmPoolState>,
+    #[serde(skip)]
+    pub clmm: Option<ClmmState>,
+    // start ticks of the tick arrays in get_update_accounts
+    #[serde(skip)]
+    pub tick_arrays: Vec<i32>,
+}
+
+impl RaydiumClmmPool {
+    fn token_idx(&self, mint: &Pubkey) -> PoolResult<usize> {
+        if *mint == self.token_0_mint.0 {
+            Ok(0)
+        } else if *mint == self.token_1_mint.0 {
+            Ok(1)
+        } else {
+            Err(PoolError::UnknownMint(*mint))
+        }
+    }
+
+    fn vault(&self, idx: usize) -> Pubkey {
+        if idx == 0 { self.token_0_vault.0 } else { self.token_1_vault.0 }
+    }
+
+    fn token_program(&self, idx: usize) -> Pubkey {
+        if idx == 0 { self.token_0_program.0 } else { self.token_1_program.0 }
+    }
+
+    fn mint(&self, idx: usize) -> Pubkey {
+        if idx == 0 { self.token_0_mint.0 } else { self.token_1_mint.0 }
+    }
+
+    fn bitmap_extension(&self) -> Pubkey {
+        bitmap_extension_address(&RAYDIUM_CLMM_PROGRAM_ID, &self.id.0)
+    }
+
+    /// tick arrays the swap walks through, in swap order
+    fn swap_tick_arrays(&self, zero_for_one: bool) -> PoolResult<Vec<Pubkey>> {
+        let pool_state = self.pool_state.as_ref()
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))?;
+        let current = tick_array_start(pool_state.tick_current, pool_state.tick_spacing, TICK_ARRAY_SIZE);
+        let mut starts: Vec<i32> = if zero_for_one {
+            self.tick_arrays.iter().rev().filter(|s| **s <= current).cloned().collect()
+        } else {
+            self.tick_arrays.iter().filter(|s| **s >= current).cloned().collect()
+        };
+        starts.truncate(TICK_ARRAYS_PER_SIDE);
+        if starts.is_empty() {
+            return Err(PoolError::MissingSwapAccount("no initialized tick arrays".to_string()));
+        }
+        Ok(starts
+            .iter()
+            .map(|start| tick_array_address(&RAYDIUM_CLMM_PROGRAM_ID, &self.id.0, *start))
+            .collect())
+    }
+}
+
+impl PoolOperations for RaydiumClmmPool {
+    fn get_name(&self) -> String {
+        "RaydiumClmm".to_string()
+    }
+
+    fn get_own_addr(&self) -> Pubkey {
+        self.id.0
+    }
+
+    // the tick arrays follow the price -- they change as it moves
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        let mut accounts = vec![
+            self.id.0,
+            self.amm_config.0,
+            self.bitmap_extension(),
+        ];
+        accounts.extend(
+            self.tick_arrays
+                .iter()
+                .map(|start| tick_array_address(&RAYDIUM_CLMM_PROGRAM_ID, &self.id.0, *start)),
+        );
+        accounts
+    }
+
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> {
+        let pks = self.get_update_accounts();
+        if accounts.len() != pks.len() {
+            return Err(PoolError::InvalidAccountData(self.id.0, "tick arrays out of sync".to_string()));
+        }
+
+        let pool_acc = accounts[0].as_ref().ok_or(PoolError::MissingAccount(pks[0]))?;
+        let pool_state = ClmmPoolState::unpack(&pool_acc.data, &POOL_STATE_DISCRIMINATOR)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[0], "not a clmm pool state".to_string()))?;
+
+        let config_acc = accounts[1].as_ref().ok_or(PoolError::MissingAccount(pks[1]))?;
+        let config = ClmmAmmConfig::unpack(&config_acc.data, &AMM_CONFIG_DISCRIMINATOR)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[1], "not a clmm amm config".to_string()))?;
+
+        // only pools with arrays far from 0 have an extension
+        let extension = match &accounts[2] {
+            Some(acc) => Some(
+                BitmapExtension::unpack(&acc.data, &BITMAP_EXTENSION_DISCRIMINATOR)
+                    .ok_or_else(|| PoolError::InvalidAccountData(pks[2], "not a bitmap extension".to_string()))?,
+            ),
+            None => None,
+        };
+
+        // ticks from the arrays we were asked to load
+        let mut ticks = vec![];
+        let mut loaded = vec![];
+        for (pk, account) in pks[3..].iter().zip(accounts[3..].iter()) {
+            let acc = account.as_ref().ok_or(PoolError::MissingAccount(*pk))?;
+            let (start, array_ticks) = unpack_tick_array(&acc.data, &TICK_ARRAY_DISCRIMINATOR)
+                .ok_or_else(|| PoolError::InvalidAccountData(*pk, "not a tick array".to_string()))?;
+            loaded.push(start);
+            ticks.extend(array_ticks);
+        }
+        ticks.sort_by_key(|t| t.tick);
+
+        let initialized = pool_state.initialized_tick_arrays(extension.as_ref());
+        let (wanted, mut tick_range) = tick_arrays_around(
+            &initialized,
+            pool_state.tick_current,
+            pool_state.tick_spacing,
+            TICK_ARRAYS_PER_SIDE,
+        );
+        if wanted != loaded {
+            // the price moved onto arrays we havent loaded yet -- only quote
+            // inside the current tick until the next refresh brings them in
+            tick_range = (pool_state.tick_current, pool_state.tick_current + 1);
+        }
+
+        self.clmm = Some(ClmmState {
+            sqrt_price_x64: pool_state.sqrt_price_x64,
+            tick_current: pool_state.tick_current,
+            liquidity: pool_state.liquidity,
+            fee_rate: config.trade_fee_rate,
+            ticks,
+            tick_range,
+        });
+        self.pool_state = Some(pool_state);
+        self.tick_arrays = wanted;
+        Ok(())
+    }
+
+    fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
+        if *mint == self.token_0_mint.0 { self.token_0_vault.0 } else { self.token_1_vault.0 }
+    }
+
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints = vec![self.token_0_mint.0, self.token_1_mint.0];
+        // sort so that its consistent across different pools
+        mints.sort();
+        mints
+    }
+
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        if *mint == self.token_0_mint.0 { self.mint_0_decimals } else { self.mint_1_decimals }
+    }
+
+    fn get_quote_with_amounts_scaled(
+        &self,
+        scaled_amount_in: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> PoolResult<u128> {
+        let idx_in = self.token_idx(mint_in)?;
+        self.token_idx(mint_out)?;
+        let (pool_state, clmm) = match (&self.pool_state, &self.clmm) {
+            (Some(pool_state), Some(clmm)) => (pool_state, clmm),
+            _ => return Err(PoolError::NotLoaded(self.get_name())),
+        };
+        if !pool_state.swap_enabled() {
+            return Ok(0);
+        }
+
+        let result = clmm.swap_exact_in(scaled_amount_in, idx_in == 0)
+            .ok_or_else(|| PoolError::QuoteFailed("clmm overflow".to_string()))?;
+        // walked off the loaded tick arrays -- the swap would fail on-chain
+        if result.amount_in < scaled_amount_in {
+            return Ok(0);
+        }
+        Ok(result.amount_out)
+    }
+
+    fn swap_ix(&self,
+        _program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
+        let idx_in = self.token_idx(mint_in)?;
+        let idx_out = self.token_idx(mint_out)?;
+        let tick_arrays = self.swap_tick_arrays(idx_in == 0)?;
+
+        let user_src = derive_token_address_with_program(owner, mint_in, &self.token_program(idx_in));
+        let user_dst = derive_token_address_with_program(owner, mint_out, &self.token_program(idx_out));
+
+        // swap_v2 { amount, other_amount_threshold, sqrt_price_limit_x64, is_base_input }
+        // (limit = 0 is no limit)
+        let mut data = SWAP_V2_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&min_amount_out.to_le_bytes());
+        data.extend_from_slice(&0_u128.to_le_bytes());
+        data.push(1);
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(self.amm_config.0, false),
+            AccountMeta::new(self.id.0, false),
+            AccountMeta::new(user_src, false),
+            AccountMeta::new(user_dst, false),
+            AccountMeta::new(self.vault(idx_in), false),
+            AccountMeta::new(self.vault(idx_out), false),
+            AccountMeta::new(self.observation_key.0, false),
+            AccountMeta::new_readonly(*TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(*TOKEN_2022_PROGRAM_ID, false),
+            AccountMeta::new_readonly(*MEMO_PROGRAM_ID, false),
+            AccountMeta::new_readonly(self.mint(idx_in), false),
+            AccountMeta::new_readonly(self.mint(idx_out), false),
+            // remaining accounts: bitmap extension then the tick arrays
+            AccountMeta::new(self.bitmap_extension(), false),
+        ];
+        accounts.extend(tick_arrays.into_iter().map(|pk| AccountMeta::new(pk, false)));
+
+        Ok(vec![Instruction {
+            program_id: *RAYDIUM_CLMM_PROGRAM_ID,
+            accounts,
+            data,
+        }])
+    }
+
+    fn uses_swap_state(&self) -> bool {
+        false
+    }
+
+    fn can_trade(&self,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey
+    ) -> bool {
+        match (&self.pool_state, &self.clmm) {
+            (Some(pool_state), Some(clmm)) => {
+                pool_state.swap_enabled()
+                    && clmm.liquidity > 0
+                    && self.token_idx(mint_in).is_ok()
+                    && self.token_idx(mint_out).is_ok()
+            }
+            _ => false,
+        }
+    }
+}
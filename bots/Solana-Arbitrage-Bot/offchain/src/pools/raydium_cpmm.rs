@@ -0,0 +1,217 @@
+use std::fmt::Debug;
+use serde;
+use serde::{Deserialize, Serialize};
+use crate::serialize::token::{WrappedPubkey, try_unpack_token_amount};
+use crate::error::{PoolError, PoolResult};
+use crate::pool::PoolOperations;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anchor_client::Program;
+
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+
+use crate::utils::{anchor_discriminator, derive_token_address_with_program};
+use crate::constants::*;
+use crate::pool_utils::raydium_cpmm::{
+    authority, swap_base_input, CpmmAmmConfig, CpmmPoolState,
+};
+
+lazy_static! {
+    static ref POOL_STATE_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "PoolState");
+    static ref AMM_CONFIG_DISCRIMINATOR: [u8; 8] = anchor_discriminator("account", "AmmConfig");
+    static ref SWAP_BASE_INPUT_DISCRIMINATOR: [u8; 8] = anchor_discriminator("global", "swap_base_input");
+}
+
+// token 0/1 are the pool's (sorted) mints -- each vault can be spl-token or token-2022
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RaydiumCpmmPool {
+    pub id: WrappedPubkey,
+    pub amm_config: WrappedPubkey,
+    pub observation_key: WrappedPubkey,
+    pub token_0_mint: WrappedPubkey,
+    pub token_1_mint: WrappedPubkey,
+    pub token_0_vault: WrappedPubkey,
+    pub token_1_vault: WrappedPubkey,
+    pub token_0_program: WrappedPubkey,
+    pub token_1_program: WrappedPubkey,
+    pub mint_0_decimals: u64,
+    pub mint_1_decimals: u64,
+    // to set later
+    #[serde(skip)]
+    pub pool_state: Option<CpmmPoolState>,
+    #[serde(skip)]
+    pub config: Option<CpmmAmmConfig>,
+    #[serde(skip)]
+    pub vault_amounts: [u128; 2],
+}
+
+impl RaydiumCpmmPool {
+    fn token_idx(&self, mint: &Pubkey) -> PoolResult<usize> {
+        if *mint == self.token_0_mint.0 {
+            Ok(0)
+        } else if *mint == self.token_1_mint.0 {
+            Ok(1)
+        } else {
+            Err(PoolError::UnknownMint(*mint))
+        }
+    }
+
+    fn vault(&self, idx: usize) -> Pubkey {
+        if idx == 0 { self.token_0_vault.0 } else { self.token_1_vault.0 }
+    }
+
+    fn token_program(&self, idx: usize) -> Pubkey {
+        if idx == 0 { self.token_0_program.0 } else { self.token_1_program.0 }
+    }
+
+    fn mint(&self, idx: usize) -> Pubkey {
+        if idx == 0 { self.token_0_mint.0 } else { self.token_1_mint.0 }
+    }
+}
+
+impl PoolOperations for RaydiumCpmmPool {
+    fn get_name(&self) -> String {
+        "RaydiumCpmm".to_string()
+    }
+
+    fn get_own_addr(&self) -> Pubkey {
+        self.id.0
+    }
+
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        vec![
+            self.id.0,
+            self.amm_config.0,
+            self.token_0_vault.0,
+            self.token_1_vault.0,
+        ]
+    }
+
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> {
+        let pks = self.get_update_accounts();
+
+        let pool_acc = accounts[0].as_ref().ok_or(PoolError::MissingAccount(pks[0]))?;
+        let pool_state = CpmmPoolState::unpack(&pool_acc.data, &POOL_STATE_DISCRIMINATOR)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[0], "not a cpmm pool state".to_string()))?;
+
+        let config_acc = accounts[1].as_ref().ok_or(PoolError::MissingAccount(pks[1]))?;
+        let config = CpmmAmmConfig::unpack(&config_acc.data, &AMM_CONFIG_DISCRIMINATOR)
+            .ok_or_else(|| PoolError::InvalidAccountData(pks[1], "not a cpmm amm config".to_string()))?;
+
+        // token-2022 vaults share the base token account layout
+        let amount0 = try_unpack_token_amount(&pks[2], &accounts[2])?;
+        let amount1 = try_unpack_token_amount(&pks[3], &accounts[3])?;
+
+        self.pool_state = Some(pool_state);
+        self.config = Some(config);
+        self.vault_amounts = [amount0, amount1];
+        Ok(())
+    }
+
+    fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
+        if *mint == self.token_0_mint.0 { self.token_0_vault.0 } else { self.token_1_vault.0 }
+    }
+
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints = vec![self.token_0_mint.0, self.token_1_mint.0];
+        // sort so that its consistent across different pools
+        mints.sort();
+        mints
+    }
+
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        if *mint == self.token_0_mint.0 { self.mint_0_decimals } else { self.mint_1_decimals }
+    }
+
+    fn get_quote_with_amounts_scaled(
+        &self,
+        scaled_amount_in: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> PoolResult<u128> {
+        let idx_in = self.token_idx(mint_in)?;
+        let idx_out = self.token_idx(mint_out)?;
+        let (pool_state, config) = match (self.pool_state, self.config) {
+            (Some(pool_state), Some(config)) => (pool_state, config),
+            _ => return Err(PoolError::NotLoaded(self.get_name())),
+        };
+        if !pool_state.swap_enabled() {
+            return Ok(0);
+        }
+
+        let reserve_in = pool_state.reserve(idx_in, self.vault_amounts[idx_in]);
+        let reserve_out = pool_state.reserve(idx_out, self.vault_amounts[idx_out]);
+        let (reserve_in, reserve_out) = match (reserve_in, reserve_out) {
+            (Some(reserve_in), Some(reserve_out)) => (reserve_in, reserve_out),
+            _ => return Err(PoolError::QuoteFailed("fees owed exceed the vaults".to_string())),
+        };
+
+        swap_base_input(scaled_amount_in, reserve_in, reserve_out, config.trade_fee_rate)
+            .ok_or_else(|| PoolError::QuoteFailed("cpmm overflow".to_string()))
+    }
+
+    fn swap_ix(&self,
+        _program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
+        let idx_in = self.token_idx(mint_in)?;
+        let idx_out = self.token_idx(mint_out)?;
+        let (program_in, program_out) = (self.token_program(idx_in), self.token_program(idx_out));
+
+        let user_src = derive_token_address_with_program(owner, mint_in, &program_in);
+        let user_dst = derive_token_address_with_program(owner, mint_out, &program_out);
+
+        // swap_base_input { amount_in, minimum_amount_out }
+        let mut data = SWAP_BASE_INPUT_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&min_amount_out.to_le_bytes());
+
+        let accounts = vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(authority(&RAYDIUM_CPMM_PROGRAM_ID), false),
+            AccountMeta::new_readonly(self.amm_config.0, false),
+            AccountMeta::new(self.id.0, false),
+            AccountMeta::new(user_src, false),
+            AccountMeta::new(user_dst, false),
+            AccountMeta::new(self.vault(idx_in), false),
+            AccountMeta::new(self.vault(idx_out), false),
+            AccountMeta::new_readonly(program_in, false),
+            AccountMeta::new_readonly(program_out, false),
+            AccountMeta::new_readonly(self.mint(idx_in), false),
+            AccountMeta::new_readonly(self.mint(idx_out), false),
+            AccountMeta::new(self.observation_key.0, false),
+        ];
+
+        Ok(vec![Instruction {
+            program_id: *RAYDIUM_CPMM_PROGRAM_ID,
+            accounts,
+            data,
+        }])
+    }
+
+    fn uses_swap_state(&self) -> bool {
+        false
+    }
+
+    fn can_trade(&self,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey
+    ) -> bool {
+        match self.pool_state {
+            Some(pool_state) => {
+                pool_state.swap_enabled()
+                    && self.token_idx(mint_in).is_ok()
+                    && self.token_idx(mint_out).is_ok()
+                    && self.vault_amounts.iter().all(|amount| *amount > 0)
+            }
+            None => false,
+        }
+    }
+}
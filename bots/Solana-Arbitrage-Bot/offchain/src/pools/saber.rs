@@ -3,7 +3,8 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 use serde;
 use serde::{Deserialize, Serialize};
-use crate::serialize::token::{Token, WrappedPubkey, unpack_token_account};
+use crate::serialize::token::{Token, WrappedPubkey, try_unpack_token_amount};
+use crate::error::{PoolError, PoolResult};
 use crate::pool::PoolOperations;
 
 use anchor_client::solana_sdk::pubkey::Pubkey;
@@ -37,13 +38,27 @@ pub struct SaberPool {
     pub pool_amounts: HashMap<String, u128>
 }
 
+impl SaberPool {
+    fn pool_amount(&self, mint: &Pubkey) -> PoolResult<u128> {
+        if !self.tokens.contains_key(&mint.to_string()) {
+            return Err(PoolError::UnknownMint(*mint));
+        }
+        self.pool_amounts
+            .get(&mint.to_string())
+            .copied()
+            .ok_or_else(|| PoolError::NotLoaded(self.get_name()))
+    }
+}
+
 impl PoolOperations for SaberPool {
     fn swap_ix(&self, 
         program: &Program,
         owner: &Pubkey,
         mint_in: &Pubkey, 
-        mint_out: &Pubkey
-    ) -> Vec<Instruction> {
+        mint_out: &Pubkey,
+        _amount_in: u64, // read from swap_state on-chain
+        _min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
         let (swap_state, _) = Pubkey::find_program_address(
             &[b"swap_state"], 
             &program.id()
@@ -51,9 +66,12 @@ impl PoolOperations for SaberPool {
         let user_src = derive_token_address(owner, mint_in);
         let user_dst = derive_token_address(owner, mint_out); 
         
-        let pool_src = self.tokens.get(&mint_in.to_string()).unwrap().addr.0;
-        let pool_dst = self.tokens.get(&mint_out.to_string()).unwrap().addr.0;
-        let fee_acc = self.fee_accounts.get(&mint_out.to_string()).unwrap();
+        let pool_src = self.tokens.get(&mint_in.to_string())
+            .ok_or(PoolError::UnknownMint(*mint_in))?.addr.0;
+        let pool_dst = self.tokens.get(&mint_out.to_string())
+            .ok_or(PoolError::UnknownMint(*mint_out))?.addr.0;
+        let fee_acc = self.fee_accounts.get(&mint_out.to_string())
+            .ok_or(PoolError::UnknownMint(*mint_out))?;
 
         let swap_ix = program
             .request()
@@ -71,9 +89,8 @@ impl PoolOperations for SaberPool {
                 token_program: *TOKEN_PROGRAM_ID,
             }) 
             .args(tmp_ix::SaberSwap {}) 
-            .instructions()
-            .unwrap();
-        swap_ix
+            .instructions()?;
+        Ok(swap_ix)
     }
 
     fn get_quote_with_amounts_scaled(
@@ -81,7 +98,7 @@ impl PoolOperations for SaberPool {
         scaled_amount_in: u128, 
         mint_in: &Pubkey,
         mint_out: &Pubkey,
-    ) -> u128 {
+    ) -> PoolResult<u128> {
 
         let calculator = Stable {
             amp: self.target_amp, 
@@ -89,9 +106,9 @@ impl PoolOperations for SaberPool {
             fee_denominator: self.fee_denominator as u128,
         };
 
-        let pool_src_amount = self.pool_amounts.get(&mint_in.to_string()).unwrap();
-        let pool_dst_amount = self.pool_amounts.get(&mint_out.to_string()).unwrap();
-        let pool_amounts = [*pool_src_amount, *pool_dst_amount];
+        let pool_src_amount = self.pool_amount(mint_in)?;
+        let pool_dst_amount = self.pool_amount(mint_out)?;
+        let pool_amounts = [pool_src_amount, pool_dst_amount];
         let percision_multipliers = [1, 1];
 
         
@@ -99,7 +116,7 @@ impl PoolOperations for SaberPool {
             pool_amounts,    
             percision_multipliers, 
             scaled_amount_in 
-        )
+        ).ok_or_else(|| PoolError::QuoteFailed("stable swap overflow".to_string()))
 
     }
 
@@ -113,7 +130,7 @@ impl PoolOperations for SaberPool {
         accounts 
     }
 
-    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) { 
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> { 
         let ids: Vec<String> = self
             .get_mints()
             .iter()
@@ -121,15 +138,14 @@ impl PoolOperations for SaberPool {
             .collect();
         let id0 = &ids[0];
         let id1 = &ids[1];
-        
-        let acc_data0 = &accounts[0].as_ref().unwrap().data;
-        let acc_data1 = &accounts[1].as_ref().unwrap().data;
+        let pks = self.get_update_accounts();
 
-        let amount0 = unpack_token_account(acc_data0).amount as u128;
-        let amount1 = unpack_token_account(acc_data1).amount as u128;
+        let amount0 = try_unpack_token_amount(&pks[0], &accounts[0])?;
+        let amount1 = try_unpack_token_amount(&pks[1], &accounts[1])?;
 
         self.pool_amounts.insert(id0.clone(), amount0);
         self.pool_amounts.insert(id1.clone(), amount1);
+        Ok(())
     }
 
     fn can_trade(&self, 
@@ -147,6 +163,10 @@ impl PoolOperations for SaberPool {
         "Saber".to_string()
     }
 
+    fn get_own_addr(&self) -> Pubkey {
+        self.pool_account.0
+    }
+
     fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
         let token = self.tokens.get(&mint.to_string()).unwrap();
         
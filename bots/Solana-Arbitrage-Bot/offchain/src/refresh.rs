@@ -0,0 +1,325 @@
+//! Keeps pool state in sync with the chain
+//!
+//! every account from `PoolOperations::get_update_accounts` is polled through
+//! an `AccountSource`; only pools whose accounts changed are updated (in place)
+//! and reported back as dirty so the search can be limited to their cycles.
+
+use std::collections::HashMap;
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anyhow::Result;
+use log::debug;
+use solana_sdk::account::Account;
+
+use crate::utils::PoolQuote;
+
+// max accounts per getMultipleAccounts request
+const MAX_ACCOUNTS_PER_REQUEST: usize = 99;
+// times a refresh follows pools onto new accounts before leaving it to the next one
+const MAX_REINDEX_PASSES: usize = 3;
+
+/// where account data comes from (rpc, or a stand-in for tests)
+pub trait AccountSource {
+    fn get_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>>;
+
+    /// the accounts + the slot they were read at (None = the source doesnt
+    /// know it)
+    fn get_accounts_at(&self, pubkeys: &[Pubkey]) -> Result<(Option<u64>, Vec<Option<Account>>)> {
+        Ok((None, self.get_accounts(pubkeys)?))
+    }
+}
+
+impl AccountSource for RpcClient {
+    fn get_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        Ok(self.get_accounts_at(pubkeys)?.1)
+    }
+
+    // requests can land on different slots -- this is the oldest of them
+    fn get_accounts_at(&self, pubkeys: &[Pubkey]) -> Result<(Option<u64>, Vec<Option<Account>>)> {
+        let mut slot: Option<u64> = None;
+        let mut accounts = vec![];
+        for chunk in pubkeys.chunks(MAX_ACCOUNTS_PER_REQUEST) {
+            let response = self.get_multiple_accounts_with_commitment(chunk, self.commitment())?;
+            slot = Some(slot.map_or(response.context.slot, |slot| slot.min(response.context.slot)));
+            accounts.push(response.value);
+        }
+        Ok((slot, accounts.concat()))
+    }
+}
+
+/// pubkey -> pools which read it
+#[derive(Debug, Default)]
+pub struct AccountIndex {
+    pub pubkeys: Vec<Pubkey>,            // unique, in fetch order
+    pub pool_accounts: Vec<Vec<Pubkey>>, // pool idx -> its update accounts (in order)
+    pub pk2pools: HashMap<Pubkey, Vec<usize>>,
+}
+
+impl AccountIndex {
+    pub fn new(pools: &[PoolQuote]) -> Self {
+        let mut index = AccountIndex::default();
+        for (pool_idx, pool) in pools.iter().enumerate() {
+            let update_accounts = pool.snapshot().get_update_accounts();
+            for pk in &update_accounts {
+                let pool_idxs = index.pk2pools.entry(*pk).or_insert_with(|| {
+                    index.pubkeys.push(*pk);
+                    vec![]
+                });
+                if !pool_idxs.contains(&pool_idx) {
+                    pool_idxs.push(pool_idx);
+                }
+            }
+            index.pool_accounts.push(update_accounts);
+        }
+        index
+    }
+}
+
+pub struct PoolRefresher {
+    pub pools: Vec<PoolQuote>,
+    pub index: AccountIndex,
+    pub cluster: Cluster,
+    // last seen account state
+    accounts: HashMap<Pubkey, Option<Account>>,
+}
+
+impl PoolRefresher {
+    pub fn new(pools: Vec<PoolQuote>, cluster: Cluster) -> Self {
+        let index = AccountIndex::new(&pools);
+        Self {
+            pools,
+            index,
+            cluster,
+            accounts: HashMap::new(),
+        }
+    }
+
+    /// fetch every update account, apply changes to the pools which read them
+    /// and return the pools which changed (the first call updates them all)
+    pub fn refresh<S: AccountSource + ?Sized>(&mut self, source: &S) -> Result<Vec<PoolQuote>> {
+        let fetched = source.get_accounts(&self.index.pubkeys)?;
+
+        let mut dirty_idxs = vec![];
+        for (pk, account) in self.index.pubkeys.iter().zip(fetched.into_iter()) {
+            let changed = match self.accounts.get(pk) {
+                Some(prev) => *prev != account,
+                None => true,
+            };
+            if !changed {
+                continue;
+            }
+            self.accounts.insert(*pk, account);
+            for pool_idx in &self.index.pk2pools[pk] {
+                if !dirty_idxs.contains(pool_idx) {
+                    dirty_idxs.push(*pool_idx);
+                }
+            }
+        }
+        dirty_idxs.sort_unstable();
+        debug!("{:?} pools changed", dirty_idxs.len());
+
+        // pools which asked for new accounts (eg, clmm tick arrays) get them
+        // loaded + applied straight away rather than on the next refresh
+        let mut to_update = dirty_idxs.clone();
+        for _ in 0..MAX_REINDEX_PASSES {
+            let moved = self.update_pools(&to_update);
+            if moved.is_empty() {
+                break;
+            }
+            self.index = AccountIndex::new(&self.pools);
+
+            let missing: Vec<Pubkey> = self.index.pubkeys
+                .iter()
+                .filter(|pk| !self.accounts.contains_key(pk))
+                .cloned()
+                .collect();
+            let fetched = source.get_accounts(&missing)?;
+            self.accounts.extend(missing.into_iter().zip(fetched.into_iter()));
+            to_update = moved;
+        }
+
+        Ok(dirty_idxs
+            .into_iter()
+            .map(|pool_idx| self.pools[pool_idx].clone())
+            .collect())
+    }
+
+    /// update the pools from the last seen accounts, returns the ones whose
+    /// update accounts moved
+    fn update_pools(&self, pool_idxs: &[usize]) -> Vec<usize> {
+        let mut moved = vec![];
+        for pool_idx in pool_idxs {
+            let accounts = self.index.pool_accounts[*pool_idx]
+                .iter()
+                .map(|pk| self.accounts[pk].clone())
+                .collect();
+
+            let pool = &self.pools[*pool_idx];
+            // a failing pool is flagged (+ keeps its old state) -- still report it
+            // as dirty so its edges get re-weighted out of the search
+            if pool.update(accounts, self.cluster.clone()).is_err() {
+                continue;
+            }
+
+            // some pools need different accounts as their state moves
+            if pool.snapshot().get_update_accounts() != self.index.pool_accounts[*pool_idx] {
+                moved.push(*pool_idx);
+            }
+        }
+        moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PoolResult;
+    use crate::pool::PoolOperations;
+    use crate::serialize::token::try_unpack_token_amount;
+    use crate::tests::mock::{add_mock_pool, mock_mints, MockPool};
+    use crate::utils::PoolGraph;
+    use anchor_client::Program;
+    use solana_sdk::instruction::Instruction;
+    use std::cell::RefCell;
+
+    // in-memory stand-in for the rpc
+    struct MockSource(RefCell<HashMap<Pubkey, Account>>);
+
+    impl AccountSource for MockSource {
+        fn get_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+            let accounts = self.0.borrow();
+            Ok(pubkeys.iter().map(|pk| accounts.get(pk).cloned()).collect())
+        }
+    }
+
+    fn vault(amount: u64) -> Account {
+        let mut data = vec![0; 165];
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        Account {
+            data,
+            ..Account::default()
+        }
+    }
+
+    #[test]
+    fn only_changed_pools_are_dirty() {
+        let mints = mock_mints(3);
+        let mut graph = PoolGraph::new();
+        let pool0 = add_mock_pool(&mut graph, &mints, 0, 1, [0, 0]);
+        let pool1 = add_mock_pool(&mut graph, &mints, 1, 2, [0, 0]);
+
+        let source = MockSource(RefCell::new(HashMap::new()));
+        for pool in [&pool0, &pool1] {
+            for pk in pool.snapshot().get_update_accounts() {
+                source.0.borrow_mut().insert(pk, vault(1_000));
+            }
+        }
+
+        let mut refresher = PoolRefresher::new(vec![pool0.clone(), pool1.clone()], Cluster::Localnet);
+        assert_eq!(refresher.index.pubkeys.len(), 4);
+
+        // first refresh loads everything
+        let dirty = refresher.refresh(&source).unwrap();
+        assert_eq!(dirty.len(), 2);
+        assert!(pool0.snapshot().can_trade(&mints[0], &mints[1]));
+
+        // nothing moved
+        assert!(refresher.refresh(&source).unwrap().is_empty());
+
+        // one of pool1's vaults moved
+        let pk = pool1.snapshot().get_update_accounts()[0];
+        source.0.borrow_mut().insert(pk, vault(2_000));
+        let dirty = refresher.refresh(&source).unwrap();
+        assert_eq!(dirty.len(), 1);
+        assert!(dirty[0].ptr_eq(&pool1));
+    }
+
+    #[test]
+    fn missing_accounts_flag_the_pool() {
+        let mints = mock_mints(2);
+        let mut graph = PoolGraph::new();
+        let pool = add_mock_pool(&mut graph, &mints, 0, 1, [1_000, 1_000]);
+
+        // nothing on chain
+        let source = MockSource(RefCell::new(HashMap::new()));
+        let mut refresher = PoolRefresher::new(vec![pool.clone()], Cluster::Localnet);
+        let dirty = refresher.refresh(&source).unwrap();
+        assert_eq!(dirty.len(), 1);
+        assert!(pool.is_failed());
+        // old state is kept
+        assert!(pool.snapshot().can_trade(&mints[0], &mints[1]));
+
+        // accounts show up -> the pool recovers
+        for pk in pool.snapshot().get_update_accounts() {
+            source.0.borrow_mut().insert(pk, vault(2_000));
+        }
+        refresher.refresh(&source).unwrap();
+        assert!(!pool.is_failed());
+    }
+
+    // reads vault 0 first, then (like a clmm's tick arrays) asks for vault 1
+    // once it has seen vault 0
+    #[derive(Debug, Clone)]
+    struct MovingPool(MockPool, bool);
+
+    impl PoolOperations for MovingPool {
+        fn get_name(&self) -> String {
+            "Moving".to_string()
+        }
+        fn get_own_addr(&self) -> Pubkey {
+            self.0.get_own_addr()
+        }
+        fn get_update_accounts(&self) -> Vec<Pubkey> {
+            if self.1 { self.0.vaults.to_vec() } else { vec![self.0.vaults[0]] }
+        }
+        fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> PoolResult<()> {
+            for (i, account) in accounts.iter().enumerate() {
+                self.0.amounts[i] = try_unpack_token_amount(&self.0.vaults[i], account)?;
+            }
+            self.1 = true;
+            Ok(())
+        }
+        fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
+            self.0.mint_2_addr(mint)
+        }
+        fn get_mints(&self) -> Vec<Pubkey> {
+            self.0.get_mints()
+        }
+        fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+            self.0.mint_2_scale(mint)
+        }
+        fn get_quote_with_amounts_scaled(&self, amount_in: u128, mint_in: &Pubkey, mint_out: &Pubkey) -> PoolResult<u128> {
+            self.0.get_quote_with_amounts_scaled(amount_in, mint_in, mint_out)
+        }
+        fn swap_ix(&self, program: &Program, owner: &Pubkey, mint_in: &Pubkey, mint_out: &Pubkey, amount_in: u64, min_amount_out: u64) -> PoolResult<Vec<Instruction>> {
+            self.0.swap_ix(program, owner, mint_in, mint_out, amount_in, min_amount_out)
+        }
+        fn can_trade(&self, mint_in: &Pubkey, mint_out: &Pubkey) -> bool {
+            self.0.can_trade(mint_in, mint_out)
+        }
+    }
+
+    #[test]
+    fn moved_accounts_load_in_the_same_refresh() {
+        let mints = mock_mints(2);
+        let mock = MockPool {
+            mints: [mints[0], mints[1]],
+            vaults: [Pubkey::new_unique(), Pubkey::new_unique()],
+            amounts: [0, 0],
+        };
+        let source = MockSource(RefCell::new(HashMap::new()));
+        for pk in mock.vaults.iter() {
+            source.0.borrow_mut().insert(*pk, vault(1_000));
+        }
+        let pool = PoolQuote::new(Box::new(MovingPool(mock, false)));
+
+        let mut refresher = PoolRefresher::new(vec![pool.clone()], Cluster::Localnet);
+        assert_eq!(refresher.index.pubkeys.len(), 1);
+        let dirty = refresher.refresh(&source).unwrap();
+        assert_eq!(dirty.len(), 1);
+        assert_eq!(refresher.index.pubkeys.len(), 2);
+        assert!(pool.snapshot().can_trade(&mints[0], &mints[1]));
+    }
+}
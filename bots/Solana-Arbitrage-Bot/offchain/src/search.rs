@@ -0,0 +1,480 @@
+//! Negative-log cycle search over the pool graph
+//!
+//! each pool quote becomes a directed edge weighted by `-ln(marginal price)`,
+//! so a cycle whose weights sum to < 0 is a cycle whose product of prices is > 1
+//! (ie, an arbitrage before price impact). a hop-bounded bellman-ford from the
+//! start mint finds the cheapest path of each length back to the start mint;
+//! only those candidate cycles get re-quoted at real sizes.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use log::debug;
+use rayon::prelude::*;
+
+use crate::error::PoolResult;
+use crate::mints::MintRegistry;
+use crate::utils::{PoolGraph, PoolIndex, PoolQuote};
+
+// path = A -> B -> C -> D -> E -> A
+// routes go out as v0 txs with their accounts in lookup tables, so the limit
+// is the 64 account locks (~10 per hop) -- routes which still dont compile
+// into a tx are skipped when sent
+pub const MAX_HOPS: usize = 5;
+
+// marginal price is quoted with 1 / PROBE_DIVISOR of a full token
+const PROBE_DIVISOR: u128 = 100;
+
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub pool: PoolQuote,
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cycle {
+    pub mints: Vec<usize>, // start -> ... -> start
+    pub pools: Vec<PoolQuote>,
+    pub weight: f64,
+}
+
+impl Cycle {
+    pub fn contains_any(&self, pools: &[PoolQuote]) -> bool {
+        self.pools
+            .iter()
+            .any(|p| pools.iter().any(|pool| pool.ptr_eq(p)))
+    }
+
+    /// output of the full cycle. a pool with bad state is flagged + returned
+    /// as the error; a quote which only fails at this size (eg, overflows)
+    /// is 0 output at this size
+    pub fn quote(&self, registry: &MintRegistry, token_mints: &[Pubkey], amount_in: u128) -> PoolResult<u128> {
+        let mut amount = amount_in;
+        for (i, pool) in self.pools.iter().enumerate() {
+            let mint_in = &token_mints[self.mints[i]];
+            let mint_out = &token_mints[self.mints[i + 1]];
+            amount = match pool.quote(registry, amount, mint_in, mint_out) {
+                Ok(amount) => amount,
+                Err(err) if err.is_pool_state() => {
+                    pool.flag(&err);
+                    return Err(err);
+                }
+                Err(err) => {
+                    debug!("no quote for {} at {}: {}", pool.snapshot().get_name(), amount, err);
+                    0
+                }
+            };
+            if amount == 0 {
+                break;
+            }
+        }
+        Ok(amount)
+    }
+}
+
+/// -ln(out / in) for a small trade so price impact doesnt dominate the weight
+/// (None = no liquidity)
+pub fn marginal_weight(
+    registry: &MintRegistry,
+    pool: &PoolQuote,
+    mint_in: &Pubkey,
+    mint_out: &Pubkey,
+) -> PoolResult<Option<f64>> {
+    let scale = pool.snapshot().mint_2_scale(mint_in);
+    let probe = (10_u128.pow(scale as u32) / PROBE_DIVISOR).max(1);
+    let out = pool.quote(registry, probe, mint_in, mint_out)?;
+    if out == 0 {
+        return Ok(None);
+    }
+    Ok(Some(-((out as f64) / (probe as f64)).ln()))
+}
+
+fn edge_weight(edge: &Edge, registry: &MintRegistry, token_mints: &[Pubkey]) -> f64 {
+    if edge.pool.is_failed() {
+        return f64::INFINITY;
+    }
+    let mint_in = &token_mints[edge.from];
+    let mint_out = &token_mints[edge.to];
+    match marginal_weight(registry, &edge.pool, mint_in, mint_out) {
+        Ok(Some(weight)) => weight,
+        Ok(None) => {
+            debug!("no liquidity: {} {} -> {}", edge.pool.snapshot().get_name(), mint_in, mint_out);
+            f64::INFINITY
+        }
+        Err(err) => {
+            edge.pool.flag(&err);
+            f64::INFINITY
+        }
+    }
+}
+
+/// every directed pool edge in the graph, in a deterministic order
+/// (edges without liquidity get an infinite weight so bellman-ford skips them)
+pub fn build_edges(graph: &PoolGraph, registry: &MintRegistry, token_mints: &[Pubkey]) -> Vec<Edge> {
+    let mut srcs: Vec<&PoolIndex> = graph.0.keys().collect();
+    srcs.sort_by_key(|idx| idx.0);
+
+    let mut edges = vec![];
+    for src in srcs {
+        let pool_edge = &graph.0[src];
+        let mut dsts: Vec<&PoolIndex> = pool_edge.0.keys().collect();
+        dsts.sort_by_key(|idx| idx.0);
+
+        for dst in dsts {
+            for pool in &pool_edge.0[dst] {
+                edges.push(Edge {
+                    from: src.0,
+                    to: dst.0,
+                    pool: pool.clone(),
+                    weight: f64::INFINITY,
+                });
+            }
+        }
+    }
+
+    // quoting is the expensive part -- spread it over the cores
+    edges
+        .par_iter_mut()
+        .for_each(|edge| edge.weight = edge_weight(edge, registry, token_mints));
+    edges
+}
+
+/// re-weight only the edges of pools whose state changed
+pub fn update_edge_weights(
+    edges: &mut [Edge],
+    registry: &MintRegistry,
+    token_mints: &[Pubkey],
+    dirty: &[PoolQuote],
+) {
+    edges
+        .par_iter_mut()
+        .filter(|edge| dirty.iter().any(|pool| pool.ptr_eq(&edge.pool)))
+        .for_each(|edge| edge.weight = edge_weight(edge, registry, token_mints));
+}
+
+// walk the predecessor layers back from (hops, node) -- the nodes + edges
+fn walk_back(
+    pred: &[Vec<Option<(usize, usize)>>],
+    hops: usize,
+    node: usize,
+) -> (Vec<usize>, Vec<usize>) {
+    let mut nodes = vec![node];
+    let mut edge_idxs = vec![];
+    let mut curr = node;
+    for k in (1..=hops).rev() {
+        let (prev, edge_idx) = pred[k][curr].unwrap();
+        nodes.push(prev);
+        edge_idxs.push(edge_idx);
+        curr = prev;
+    }
+    nodes.reverse();
+    edge_idxs.reverse();
+    (nodes, edge_idxs)
+}
+
+/// hop-bounded bellman-ford: dist[k][v] = cheapest k-hop path start -> v.
+/// a cycle is a candidate when closing it back into the start mint is < 0.
+///
+/// only the cheapest path is kept per (k, v), so a pricier prefix into v is
+/// dropped even when it's the one whose closing edge back to the start would
+/// have been profitable (eg, the cheaper prefix already visits the mint the
+/// close goes through). the search can miss such cycles -- it finds
+/// candidates, it doesnt enumerate every profitable route.
+pub fn find_candidate_cycles(
+    edges: &[Edge],
+    n_mints: usize,
+    start_mint_idx: usize,
+    max_hops: usize,
+) -> Vec<Cycle> {
+    search_cycles(edges, n_mints, start_mint_idx, max_hops, None)
+}
+
+/// the candidate cycles through at least one of the dirty edges (by index) --
+/// paths are tracked apart by whether they crossed one yet, and only those
+/// which did are closed, so cycles of unchanged pools arent searched for (+
+/// a clean path into a mint doesnt shadow a dirty one)
+pub fn find_dirty_cycles(
+    edges: &[Edge],
+    n_mints: usize,
+    start_mint_idx: usize,
+    max_hops: usize,
+    dirty: &[bool],
+) -> Vec<Cycle> {
+    search_cycles(edges, n_mints, start_mint_idx, max_hops, Some(dirty))
+}
+
+// dp states are (mint, crossed a dirty edge) -- a full search starts as if
+// it already had, so every path counts
+fn search_cycles(
+    edges: &[Edge],
+    n_mints: usize,
+    start_mint_idx: usize,
+    max_hops: usize,
+    dirty: Option<&[bool]>,
+) -> Vec<Cycle> {
+    let state = |mint: usize, crossed: bool| mint * 2 + crossed as usize;
+    let mut dist = vec![vec![f64::INFINITY; n_mints * 2]; max_hops + 1];
+    let mut pred: Vec<Vec<Option<(usize, usize)>>> = vec![vec![None; n_mints * 2]; max_hops + 1];
+    dist[0][state(start_mint_idx, dirty.is_none())] = 0.0;
+
+    let mut cycles = vec![];
+    for k in 1..=max_hops {
+        for (edge_idx, edge) in edges.iter().enumerate() {
+            // failed pools can be flagged mid-search (eg, while sizing)
+            if edge.pool.is_failed() {
+                continue;
+            }
+            let crosses = matches!(dirty, Some(dirty) if dirty[edge_idx]);
+            for crossed in [false, true] {
+                let from = state(edge.from, crossed);
+                let d = dist[k - 1][from];
+                if !d.is_finite() {
+                    continue;
+                }
+                let new_dist = d + edge.weight;
+                let crossed = crossed || crosses;
+
+                if edge.to == start_mint_idx {
+                    // closing edge: dont relax into the start, record the cycle
+                    if crossed && k >= 2 && new_dist < 0.0 {
+                        let (states, mut edge_idxs) = walk_back(&pred, k - 1, from);
+                        let mut mints: Vec<usize> = states.iter().map(|s| s / 2).collect();
+                        mints.push(start_mint_idx);
+                        edge_idxs.push(edge_idx);
+                        cycles.push(Cycle {
+                            mints,
+                            pools: edge_idxs.iter().map(|i| edges[*i].pool.clone()).collect(),
+                            weight: new_dist,
+                        });
+                    }
+                    continue;
+                }
+
+                let to = state(edge.to, crossed);
+                if new_dist < dist[k][to] {
+                    // keep paths simple: no mint visited twice
+                    let (path, _) = walk_back(&pred, k - 1, from);
+                    if path.iter().any(|s| s / 2 == edge.to) {
+                        continue;
+                    }
+                    dist[k][to] = new_dist;
+                    pred[k][to] = Some((from, edge_idx));
+                }
+            }
+        }
+    }
+
+    // most negative (best) first
+    cycles.sort_by(|a, b| a.weight.partial_cmp(&b.weight).unwrap());
+    cycles
+}
+
+/// candidate cycles from each start mint, searched in parallel
+pub fn find_candidate_cycles_from(
+    edges: &[Edge],
+    n_mints: usize,
+    start_mint_idxs: &[usize],
+    max_hops: usize,
+) -> Vec<Cycle> {
+    let mut cycles: Vec<Cycle> = start_mint_idxs
+        .par_iter()
+        .map(|start_mint_idx| find_candidate_cycles(edges, n_mints, *start_mint_idx, max_hops))
+        .flatten()
+        .collect();
+    cycles.sort_by(|a, b| a.weight.partial_cmp(&b.weight).unwrap());
+    cycles
+}
+
+/// candidate cycles through the dirty pools from each start mint
+pub fn find_dirty_cycles_from(
+    edges: &[Edge],
+    n_mints: usize,
+    start_mint_idxs: &[usize],
+    max_hops: usize,
+    dirty: &[PoolQuote],
+) -> Vec<Cycle> {
+    let dirty: Vec<bool> = edges
+        .iter()
+        .map(|edge| dirty.iter().any(|pool| pool.ptr_eq(&edge.pool)))
+        .collect();
+    if !dirty.contains(&true) {
+        return vec![];
+    }
+    let mut cycles: Vec<Cycle> = start_mint_idxs
+        .par_iter()
+        .map(|start_mint_idx| find_dirty_cycles(edges, n_mints, *start_mint_idx, max_hops, &dirty))
+        .flatten()
+        .collect();
+    cycles.sort_by(|a, b| a.weight.partial_cmp(&b.weight).unwrap());
+    cycles
+}
+
+/// marginal rate of each mint into the value mint (value units per unit, both
+/// scaled) along its best simple path of <= max_hops, or None if it cant reach
+/// it. used to compare profits from different start mints in one unit.
+///
+/// paths never revisit a mint -- relaxing through an arbitrage cycle would
+/// otherwise loop it up to max_hops times and inflate the rate.
+pub fn value_rates(
+    edges: &[Edge],
+    n_mints: usize,
+    value_mint_idx: usize,
+    max_hops: usize,
+) -> Vec<Option<f64>> {
+    // dist[k][v] = cheapest k-hop path v -> value mint (relaxed backwards
+    // along edges), next[k][v] = the mint after v on it
+    let mut dist = vec![vec![f64::INFINITY; n_mints]; max_hops + 1];
+    let mut next: Vec<Vec<Option<usize>>> = vec![vec![None; n_mints]; max_hops + 1];
+    dist[0][value_mint_idx] = 0.0;
+    for k in 1..=max_hops {
+        for edge in edges {
+            let d = dist[k - 1][edge.to] + edge.weight;
+            if edge.from == value_mint_idx || !d.is_finite() || d >= dist[k][edge.from] {
+                continue;
+            }
+            // walk the path on from edge.to -- skip it if it passes edge.from
+            let mut curr = edge.to;
+            let mut visits = false;
+            for j in (1..k).rev() {
+                curr = next[j][curr].unwrap();
+                if curr == edge.from {
+                    visits = true;
+                    break;
+                }
+            }
+            if visits {
+                continue;
+            }
+            dist[k][edge.from] = d;
+            next[k][edge.from] = Some(edge.to);
+        }
+    }
+
+    (0..n_mints)
+        .map(|v| {
+            let best = (0..=max_hops).map(|k| dist[k][v]).fold(f64::INFINITY, f64::min);
+            if best.is_finite() { Some((-best).exp()) } else { None }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PoolError;
+    use crate::tests::mock::{add_mock_pool, mock_mints};
+
+    #[test]
+    fn finds_mispriced_triangle() {
+        let mints = mock_mints(3);
+        let mut graph = PoolGraph::new();
+        let big = 1_000_000_000_000;
+        // A/B = 1, B/C = 1, C/A = 1.1 -> A -> B -> C -> A is profitable
+        add_mock_pool(&mut graph, &mints, 0, 1, [big, big]);
+        add_mock_pool(&mut graph, &mints, 1, 2, [big, big]);
+        add_mock_pool(&mut graph, &mints, 2, 0, [big, big * 11 / 10]);
+
+        let edges = build_edges(&graph, &MintRegistry::default(), &mints);
+        let cycles = find_candidate_cycles(&edges, mints.len(), 0, MAX_HOPS);
+        assert!(!cycles.is_empty());
+
+        let best = &cycles[0];
+        assert_eq!(best.mints, vec![0, 1, 2, 0]);
+        assert!(best.quote(&MintRegistry::default(), &mints, 1_000_000).unwrap() > 1_000_000);
+    }
+
+    #[test]
+    fn size_errors_dont_flag_pools() {
+        let mints = mock_mints(3);
+        let mut graph = PoolGraph::new();
+        let big = 1_000_000_000_000;
+        let pool = add_mock_pool(&mut graph, &mints, 0, 1, [big, big]);
+        // the pool doesnt trade mints[2] -- a route error, not bad state
+        let cycle = Cycle { mints: vec![2, 0, 2], pools: vec![pool.clone(), pool.clone()], weight: 0.0 };
+        assert_eq!(cycle.quote(&MintRegistry::default(), &mints, 1_000_000).unwrap(), 0);
+        assert!(!pool.is_failed());
+
+        pool.flag(&PoolError::MissingAccount(Pubkey::new_unique()));
+        assert!(pool.is_failed());
+    }
+
+    #[test]
+    fn researches_only_cycles_through_dirty_pools() {
+        let mints = mock_mints(5);
+        let mut graph = PoolGraph::new();
+        let big = 1_000_000_000_000;
+        // A -> B -> C -> A and A -> D -> E -> A are both profitable
+        let dirty = add_mock_pool(&mut graph, &mints, 0, 1, [big, big]);
+        add_mock_pool(&mut graph, &mints, 1, 2, [big, big]);
+        add_mock_pool(&mut graph, &mints, 2, 0, [big, big * 11 / 10]);
+        let clean = add_mock_pool(&mut graph, &mints, 0, 3, [big, big]);
+        add_mock_pool(&mut graph, &mints, 3, 4, [big, big]);
+        add_mock_pool(&mut graph, &mints, 4, 0, [big, big * 12 / 10]);
+
+        let edges = build_edges(&graph, &MintRegistry::default(), &mints);
+        let all = find_candidate_cycles_from(&edges, mints.len(), &[0], MAX_HOPS);
+        assert!(all.iter().any(|cycle| cycle.contains_any(&[clean.clone()])));
+
+        let cycles = find_dirty_cycles_from(&edges, mints.len(), &[0], MAX_HOPS, &[dirty.clone()]);
+        assert!(!cycles.is_empty());
+        assert!(cycles.iter().all(|cycle| cycle.contains_any(&[dirty.clone()])));
+        assert!(cycles.iter().all(|cycle| !cycle.contains_any(&[clean.clone()])));
+        // nothing changed -> nothing searched
+        assert!(find_dirty_cycles_from(&edges, mints.len(), &[0], MAX_HOPS, &[]).is_empty());
+    }
+
+    #[test]
+    fn graph_is_send_sync() {
+        fn check<T: Send + Sync>() {}
+        check::<PoolGraph>();
+        check::<Edge>();
+        check::<Cycle>();
+    }
+
+    #[test]
+    fn no_cycles_when_fairly_priced() {
+        let mints = mock_mints(3);
+        let mut graph = PoolGraph::new();
+        let big = 1_000_000_000_000;
+        add_mock_pool(&mut graph, &mints, 0, 1, [big, big]);
+        add_mock_pool(&mut graph, &mints, 1, 2, [big, big]);
+        add_mock_pool(&mut graph, &mints, 2, 0, [big, big]);
+
+        let edges = build_edges(&graph, &MintRegistry::default(), &mints);
+        let cycles = find_candidate_cycles(&edges, mints.len(), 0, MAX_HOPS);
+        assert!(cycles.is_empty());
+    }
+
+    #[test]
+    fn values_mints_through_paths() {
+        let mints = mock_mints(4);
+        let mut graph = PoolGraph::new();
+        let big = 1_000_000_000_000;
+        // 1 B = 2 A, 1 C = 2 B, D is unconnected
+        add_mock_pool(&mut graph, &mints, 0, 1, [big * 2, big]);
+        add_mock_pool(&mut graph, &mints, 1, 2, [big * 2, big]);
+
+        let edges = build_edges(&graph, &MintRegistry::default(), &mints);
+        let rates = value_rates(&edges, mints.len(), 0, MAX_HOPS);
+        assert_eq!(rates[0], Some(1.0));
+        assert!((rates[1].unwrap() - 2.0).abs() < 1e-3);
+        assert!((rates[2].unwrap() - 4.0).abs() < 1e-3);
+        assert_eq!(rates[3], None);
+    }
+
+    #[test]
+    fn rates_dont_loop_through_arbitrage_cycles() {
+        let mints = mock_mints(3);
+        let mut graph = PoolGraph::new();
+        let big = 1_000_000_000_000;
+        // B -> C -> B through the two B/C pools is +10%
+        add_mock_pool(&mut graph, &mints, 0, 1, [big, big]);
+        add_mock_pool(&mut graph, &mints, 1, 2, [big, big]);
+        add_mock_pool(&mut graph, &mints, 2, 1, [big, big * 11 / 10]);
+
+        let edges = build_edges(&graph, &MintRegistry::default(), &mints);
+        let rates = value_rates(&edges, mints.len(), 0, MAX_HOPS);
+        assert!((rates[1].unwrap() - 1.0).abs() < 1e-3);
+        // C's best path into A is through the cheap pool, once
+        assert!((rates[2].unwrap() - 1.1).abs() < 1e-2);
+    }
+}
@@ -3,6 +3,9 @@ use anchor_client::solana_sdk::program_option::COption;
 use anchor_client::solana_sdk::program_error::ProgramError;
 use arrayref::{array_ref, array_refs};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
+use solana_sdk::account::Account;
+
+use crate::error::{PoolError, PoolResult};
 
 use std::fmt::Debug;
 use std::str::FromStr;
@@ -150,4 +153,174 @@ pub fn unpack_token_account(data: &[u8]) -> TokenAccount {
         delegated_amount: u64::from_le_bytes(*delegated_amount),
         close_authority: unpack_coption_key(close_authority).unwrap(),
     }
-}
\ No newline at end of file
+}
+/// token amount of a pool vault, erroring (not panicking) on a missing or short account
+pub fn try_unpack_token_amount(pk: &Pubkey, account: &Option<Account>) -> PoolResult<u128> {
+    let account = account.as_ref().ok_or(PoolError::MissingAccount(*pk))?;
+    if account.data.len() < 165 {
+        return Err(PoolError::InvalidAccountData(
+            *pk,
+            format!("{} bytes is too short for a token account", account.data.len()),
+        ));
+    }
+    Ok(unpack_token_account(&account.data).amount as u128)
+}
+
+// TOKEN-2022 EXTENSIONS
+
+// token-2022 pads mints up to the account length so the account type byte
+// (+ the tlv extensions after it) sit at the same offset for both
+const BASE_ACCOUNT_LEN: usize = 165;
+const MINT_LEN: usize = 82;
+
+pub const ACCOUNT_TYPE_MINT: u8 = 1;
+pub const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+
+pub const EXTENSION_TRANSFER_FEE_CONFIG: u16 = 1;
+pub const EXTENSION_NON_TRANSFERABLE: u16 = 9;
+pub const EXTENSION_TRANSFER_HOOK: u16 = 14;
+
+/// the (extension type, value) entries of a token-2022 mint / account
+/// (empty for legacy spl accounts or ones of another account type)
+pub fn unpack_extensions(data: &[u8], account_type: u8) -> Vec<(u16, &[u8])> {
+    let mut extensions = vec![];
+    if data.len() <= BASE_ACCOUNT_LEN || data[BASE_ACCOUNT_LEN] != account_type {
+        return extensions;
+    }
+    let mut offset = BASE_ACCOUNT_LEN + 1;
+    while offset + 4 <= data.len() {
+        let tipe = u16::from_le_bytes(*array_ref![data, offset, 2]);
+        let len = u16::from_le_bytes(*array_ref![data, offset + 2, 2]) as usize;
+        // uninitialized = the rest is padding
+        if tipe == 0 || offset + 4 + len > data.len() {
+            break;
+        }
+        extensions.push((tipe, &data[offset + 4..offset + 4 + len]));
+        offset += 4 + len;
+    }
+    extensions
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TransferFee {
+    /// first epoch the fee applies
+    pub epoch: u64,
+    pub maximum_fee: u64,
+    pub basis_points: u16,
+}
+
+impl TransferFee {
+    /// fee withheld from a transfer of `amount` (rounded up, like the token program)
+    pub fn fee(&self, amount: u64) -> u64 {
+        if self.basis_points == 0 || amount == 0 {
+            return 0;
+        }
+        let fee = (amount as u128 * self.basis_points as u128 + 9_999) / 10_000;
+        (fee as u64).min(self.maximum_fee)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TransferFeeConfig {
+    pub older: TransferFee,
+    pub newer: TransferFee,
+}
+
+impl TransferFeeConfig {
+    /// the fee in effect at `epoch` (None = the newest one)
+    pub fn at_epoch(&self, epoch: Option<u64>) -> &TransferFee {
+        match epoch {
+            Some(epoch) if epoch < self.newer.epoch => &self.older,
+            _ => &self.newer,
+        }
+    }
+}
+
+fn unpack_transfer_fee(src: &[u8; 18]) -> TransferFee {
+    let (epoch, maximum_fee, basis_points) = array_refs![src, 8, 8, 2];
+    TransferFee {
+        epoch: u64::from_le_bytes(*epoch),
+        maximum_fee: u64::from_le_bytes(*maximum_fee),
+        basis_points: u16::from_le_bytes(*basis_points),
+    }
+}
+
+/// Mint data (+ the token-2022 extensions which change how it transfers)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mint {
+    pub decimals: u8,
+    pub transfer_fee: Option<TransferFeeConfig>,
+    /// transfers cpi into a hook program -- swaps would need its extra accounts
+    pub transfer_hook: bool,
+    pub non_transferable: bool,
+}
+
+pub fn try_unpack_mint(pk: &Pubkey, account: &Option<Account>) -> PoolResult<Mint> {
+    let account = account.as_ref().ok_or(PoolError::MissingAccount(*pk))?;
+    if account.data.len() < MINT_LEN {
+        return Err(PoolError::InvalidAccountData(
+            *pk,
+            format!("{} bytes is too short for a mint", account.data.len()),
+        ));
+    }
+    let mut mint = Mint { decimals: account.data[44], ..Mint::default() };
+    for (tipe, value) in unpack_extensions(&account.data, ACCOUNT_TYPE_MINT) {
+        match tipe {
+            // authorities (2 x 32) + withheld amount (8), then the fees
+            EXTENSION_TRANSFER_FEE_CONFIG if value.len() >= 108 => {
+                mint.transfer_fee = Some(TransferFeeConfig {
+                    older: unpack_transfer_fee(array_ref![value, 72, 18]),
+                    newer: unpack_transfer_fee(array_ref![value, 90, 18]),
+                });
+            }
+            EXTENSION_TRANSFER_HOOK => {
+                // (authority, hook program) -- an unset program is no hook
+                mint.transfer_hook = value.len() >= 64 && value[32..64] != [0; 32];
+            }
+            EXTENSION_NON_TRANSFERABLE => mint.non_transferable = true,
+            _ => {}
+        }
+    }
+    Ok(mint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extension(tipe: u16, value: &[u8]) -> Vec<u8> {
+        let mut data = tipe.to_le_bytes().to_vec();
+        data.extend((value.len() as u16).to_le_bytes());
+        data.extend(value);
+        data
+    }
+
+    #[test]
+    fn mints_with_transfer_fees() {
+        let mut fees = vec![0; 72];
+        for (epoch, maximum_fee, basis_points) in [(0_u64, 5_000_u64, 100_u16), (600, 1_000_000, 250)] {
+            fees.extend(epoch.to_le_bytes());
+            fees.extend(maximum_fee.to_le_bytes());
+            fees.extend(basis_points.to_le_bytes());
+        }
+        let mut data = vec![0; BASE_ACCOUNT_LEN];
+        data[44] = 6;
+        data.push(ACCOUNT_TYPE_MINT);
+        data.extend(extension(3, &[7; 32])); // close authority
+        data.extend(extension(EXTENSION_TRANSFER_FEE_CONFIG, &fees));
+        let account = Account { data, ..Account::default() };
+        let mint = try_unpack_mint(&Pubkey::new_unique(), &Some(account)).unwrap();
+
+        assert_eq!(mint.decimals, 6);
+        assert!(!mint.transfer_hook);
+        let config = mint.transfer_fee.unwrap();
+        assert_eq!(config.at_epoch(Some(599)).fee(1_000_000), 5_000); // capped
+        assert_eq!(config.at_epoch(Some(600)).fee(1_000_000), 25_000);
+        assert_eq!(config.at_epoch(None).fee(1), 1); // rounds up
+        assert_eq!(config.at_epoch(None).fee(0), 0);
+
+        // legacy spl mints have no extensions
+        let legacy = Account { data: vec![0; MINT_LEN], ..Account::default() };
+        assert_eq!(try_unpack_mint(&Pubkey::new_unique(), &Some(legacy)).unwrap().transfer_fee, None);
+    }
+}
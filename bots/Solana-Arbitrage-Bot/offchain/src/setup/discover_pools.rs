@@ -0,0 +1,76 @@
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use log::info;
+
+use client::discovery::{discover, parse_denylist, parse_min_liquidity, venue_layouts, DiscoveryFilter};
+use client::pool::PoolType;
+
+/// writes a pool json (`<out>/<venue>/<addr>_<venue>_pool.json`) for every
+/// pool the venues' programs own
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+pub struct Args {
+    #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
+    pub rpc_url: String,
+    /// repeat for each venue (default = every venue discovery supports)
+    #[clap(long)]
+    pub venue: Vec<String>,
+    #[clap(long, default_value = "../pools")]
+    pub out: String,
+    /// file of pool / mint pubkeys to skip (one per line, # comments)
+    #[clap(long)]
+    pub denylist: Option<String>,
+    /// <mint>=<ui amount>: keep only pools whose vault of a listed mint holds
+    /// at least that much (repeat for each mint)
+    #[clap(long)]
+    pub min_liquidity: Vec<String>,
+}
+
+fn venue_name(tipe: &PoolType) -> String {
+    serde_json::to_value(tipe).unwrap().as_str().unwrap().to_string()
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+
+    let connection = RpcClient::new_with_commitment(args.rpc_url.clone(), CommitmentConfig::confirmed());
+
+    let mut filter = DiscoveryFilter::default();
+    if let Some(path) = &args.denylist {
+        let contents = std::fs::read_to_string(path).with_context(|| format!("reading {}", path))?;
+        filter.denylist = parse_denylist(&contents)?;
+    }
+    for arg in &args.min_liquidity {
+        let (mint, amount) = parse_min_liquidity(arg)?;
+        filter.min_liquidity.insert(mint, amount);
+    }
+
+    let layouts = venue_layouts();
+    for venue in &args.venue {
+        if !layouts.iter().any(|layout| venue_name(&layout.tipe) == *venue) {
+            return Err(anyhow!("discovery doesnt support venue {}", venue));
+        }
+    }
+    let layouts = layouts
+        .into_iter()
+        .filter(|layout| args.venue.is_empty() || args.venue.contains(&venue_name(&layout.tipe)));
+
+    for layout in layouts {
+        let venue = venue_name(&layout.tipe);
+        let pools = discover(&connection, &layout, &filter)?;
+        let dir = Path::new(&args.out).join(&venue);
+        std::fs::create_dir_all(&dir).with_context(|| format!("creating {:?}", dir))?;
+        for (address, json) in &pools {
+            let path = dir.join(format!("{}_{}_pool.json", address, venue));
+            std::fs::write(&path, json).with_context(|| format!("writing {:?}", path))?;
+        }
+        info!("wrote {} {} pools to {:?}", pools.len(), venue, dir);
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+
+use anyhow::Result;
+use clap::Parser;
+use log::info;
+
+use client::config::Config;
+use client::ledger::{Ledger, LedgerSummary};
+
+/// fills in what each sent arb realized (from its landed tx) and prints
+/// the ledger's pnl + each venue's quote error
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+pub struct Args {
+    /// toml config (default = ./arb.toml if it exists)
+    #[clap(long)]
+    pub config: Option<String>,
+    /// default = the config's ledger_path
+    #[clap(long)]
+    pub ledger: Option<String>,
+    #[clap(long)]
+    pub rpc_url: Option<String>,
+    /// only print the report (no rpc)
+    #[clap(long)]
+    pub report: bool,
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+    let config = Config::load(args.config.as_deref())?;
+
+    let ledger = Ledger::new(args.ledger.as_deref().unwrap_or(&config.ledger_path));
+    if !args.report {
+        let url = match args.rpc_url {
+            Some(url) => url,
+            None => config.rpc_url()?,
+        };
+        let connection = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
+        let reconciled = ledger.reconcile(&connection)?;
+        info!("reconciled {} arbs", reconciled);
+    }
+
+    print!("{}", LedgerSummary::new(&ledger.load()?));
+    Ok(())
+}
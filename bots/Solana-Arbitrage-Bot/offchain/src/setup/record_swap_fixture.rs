@@ -0,0 +1,135 @@
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_request::RpcRequest;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use log::info;
+use serde_json::{json, Value};
+
+use client::constants::*;
+use client::pool::{pool_factory, PoolType};
+use client::refresh::PoolRefresher;
+use client::snapshot::Recorder;
+use client::swap_fixture::{recorded_swap, SwapFixture};
+use client::utils::{unix_now, PoolQuote};
+
+const POLL_MILLIS: u64 = 400;
+const SIGNATURES_LIMIT: usize = 1_000;
+
+/// snapshots a clmm pool, waits for the next swap through it and saves both
+/// as a fixture the quotes are checked against
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+pub struct Args {
+    #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
+    pub rpc_url: String,
+    /// raydium_clmm or orca_whirlpool
+    #[clap(long)]
+    pub venue: String,
+    /// the pool's json
+    #[clap(long)]
+    pub pool: String,
+    #[clap(long, default_value = "src/tests/fixtures/swaps")]
+    pub out: String,
+    #[clap(long, default_value = "600")]
+    pub timeout_secs: i64,
+}
+
+// the oldest landed tx touching `address` after `slot` (None = none before
+// the deadline, or too many to tell which was first)
+fn next_tx(connection: &RpcClient, address: &Pubkey, slot: u64, deadline: i64) -> Result<Option<String>> {
+    while unix_now() < deadline {
+        let signatures: Value = connection.send(
+            RpcRequest::GetSignaturesForAddress,
+            json!([address.to_string(), {"limit": SIGNATURES_LIMIT, "commitment": "confirmed"}]),
+        )?;
+        let signatures = signatures.as_array().cloned().unwrap_or_default();
+        // newest first
+        let after: Vec<&Value> = signatures
+            .iter()
+            .filter(|sig| sig["slot"].as_u64().map_or(false, |sig_slot| sig_slot > slot))
+            .collect();
+        if after.len() == SIGNATURES_LIMIT {
+            return Ok(None);
+        }
+        // failed txs dont change the pool
+        if let Some(first) = after.iter().rev().find(|sig| sig["err"].is_null()) {
+            return Ok(first["signature"].as_str().map(|sig| sig.to_string()));
+        }
+        std::thread::sleep(Duration::from_millis(POLL_MILLIS));
+    }
+    Ok(None)
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+
+    let venue: PoolType = serde_json::from_value(Value::String(args.venue.clone()))
+        .map_err(|_| anyhow!("unknown venue {}", args.venue))?;
+    let program_id = match venue {
+        PoolType::RaydiumClmmPoolType => *RAYDIUM_CLMM_PROGRAM_ID,
+        PoolType::OrcaWhirlpoolPoolType => *ORCA_WHIRLPOOL_PROGRAM_ID,
+        _ => return Err(anyhow!("swap fixtures are recorded for clmm venues only")),
+    };
+    let json = std::fs::read_to_string(&args.pool).with_context(|| format!("reading {}", args.pool))?;
+    let pool_json: Value = serde_json::from_str(&json).with_context(|| format!("parsing {}", args.pool))?;
+
+    let connection = RpcClient::new_with_commitment(args.rpc_url.clone(), CommitmentConfig::confirmed());
+    let deadline = unix_now() + args.timeout_secs;
+    while unix_now() < deadline {
+        // the pool as of `slot`
+        let recorder = Recorder::new(&connection);
+        let mut refresher = PoolRefresher::new(vec![PoolQuote::new(pool_factory(&venue, &json)?)], Cluster::Mainnet);
+        refresher.refresh(&recorder)?;
+        let slot = recorder.slot().ok_or_else(|| anyhow!("the rpc didnt report a slot"))?;
+        let snapshot = recorder.snapshot(slot, unix_now());
+        let pool = refresher.pools[0].snapshot();
+        let address = pool.get_own_addr();
+        let mints = pool.get_mints();
+        let vaults = [pool.mint_2_addr(&mints[0]), pool.mint_2_addr(&mints[1])];
+
+        let signature = match next_tx(&connection, &address, slot, deadline)? {
+            Some(signature) => signature,
+            None => continue,
+        };
+        let tx: Value = connection.send(
+            RpcRequest::GetTransaction,
+            json!([signature, {
+                "encoding": "jsonParsed",
+                "commitment": "confirmed",
+                "maxSupportedTransactionVersion": 0,
+            }]),
+        )?;
+        let swap = match recorded_swap(&tx, &program_id, &address, &vaults) {
+            Some(swap) => swap,
+            None => {
+                info!("{} isnt a single exact input swap -- waiting for the next one", signature);
+                continue;
+            }
+        };
+
+        let fixture = SwapFixture {
+            venue: venue.clone(),
+            pool: pool_json.clone(),
+            signature,
+            mint_in: swap.mint_in,
+            mint_out: swap.mint_out,
+            amount_in: swap.amount_in,
+            amount_out: swap.amount_out,
+        };
+        let dir = Path::new(&args.out).join(format!("{}_{}", args.venue, &fixture.signature[..16]));
+        let dir = dir.to_string_lossy().to_string();
+        snapshot.save(&dir)?;
+        fixture.save(&dir)?;
+        info!("recorded {} to {}", fixture.signature, dir);
+        return Ok(());
+    }
+    Err(anyhow!("no swap through the pool to record in {}s", args.timeout_secs))
+}
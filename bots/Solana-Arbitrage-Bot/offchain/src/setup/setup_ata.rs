@@ -29,7 +29,8 @@ use log::{warn};
 use client::serialize::{
     token::unpack_token_account,
 };
-use client::utils::{derive_token_address, read_json_dir};
+use client::mints::MintRegistry;
+use client::utils::read_json_dir;
 use client::pool::{PoolType, PoolOperations, pool_factory};
 use client::constants::*;
 
@@ -81,7 +82,7 @@ fn main() {
         
         for pool_path in pool_paths {
             let json_str = std::fs::read_to_string(&pool_path).unwrap();
-            let pool = pool_factory(&pool_dir.tipe, &json_str);
+            let pool = pool_factory(&pool_dir.tipe, &json_str).unwrap();
             let pool_mints = pool.get_mints();
             if pool_mints.len() != 2 { // only support 2 mint pools
                 warn!("skipping pool with mints != 2: {:?}", pool_path);
@@ -95,11 +96,14 @@ fn main() {
         }
     }
 
+    // token-2022 mints get their ata under the token-2022 program
+    let registry = MintRegistry::load(&connection, &token_mints, None).unwrap();
+
     // make sure all tokens have ATA
     // print initial balances
     let mut user_token_addrs = vec![];
     for mint in &token_mints {
-        let user_token_addr = derive_token_address(&owner.pubkey(), mint);
+        let user_token_addr = registry.token_address(&owner.pubkey(), mint);
         user_token_addrs.push(user_token_addr);
     }
     // get pool amounts in single RPC 
@@ -142,7 +146,7 @@ fn main() {
             AccountMeta::new_readonly(owner.pubkey(), false), 
             AccountMeta::new_readonly(*mint, false), 
             AccountMeta::new_readonly(system_program::id(), false), 
-            AccountMeta::new_readonly(*TOKEN_PROGRAM_ID, false), 
+            AccountMeta::new_readonly(registry.program(mint), false), 
             AccountMeta::new_readonly(solana_sdk::sysvar::rent::id(), false),
         ];
         let ix = Instruction { 
@@ -59,6 +59,7 @@ fn main() {
     let serum_dir = PoolDir {
         tipe: PoolType::SerumPoolType,
         dir_path: "../pools/serum/".to_string(),
+        enabled: true,
     };    
 
     let pool_paths = read_json_dir(&serum_dir.dir_path);
@@ -0,0 +1,154 @@
+//! Input sizing for a candidate cycle
+//!
+//! profit(x) = quote(x) - x rises while the edge outruns price impact and
+//! falls after, so a golden-section search over [min, max] finds the peak
+//! in a few dozen quotes instead of trying fixed sizes.
+
+use std::collections::BTreeMap;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+
+use crate::error::PoolResult;
+use crate::mints::MintRegistry;
+use crate::search::Cycle;
+
+// 1 / golden ratio
+const INV_PHI: f64 = 0.618_033_988_749_895;
+const MAX_ITERATIONS: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct SizedArb {
+    pub amount_in: u128,
+    pub amount_out: u128,
+    pub profit: i128,
+    // every (amount_in, profit) quoted during the search, sorted by size
+    pub curve: Vec<(u128, i128)>,
+}
+
+struct ProfitCurve<'a> {
+    cycle: &'a Cycle,
+    registry: &'a MintRegistry,
+    token_mints: &'a [Pubkey],
+    points: BTreeMap<u128, (u128, i128)>,
+}
+
+impl<'a> ProfitCurve<'a> {
+    fn profit(&mut self, amount_in: u128) -> PoolResult<i128> {
+        if let Some((_, profit)) = self.points.get(&amount_in) {
+            return Ok(*profit);
+        }
+        let amount_out = self.cycle.quote(self.registry, self.token_mints, amount_in)?;
+        let profit = amount_out as i128 - amount_in as i128;
+        self.points.insert(amount_in, (amount_out, profit));
+        Ok(profit)
+    }
+}
+
+/// profit-maximizing input for the cycle, bounded by [min_amount, max_amount]
+/// (max_amount = wallet balance of the start mint). errors if any pool
+/// in the cycle fails to quote
+pub fn optimal_size(
+    cycle: &Cycle,
+    registry: &MintRegistry,
+    token_mints: &[Pubkey],
+    min_amount: u128,
+    max_amount: u128,
+) -> PoolResult<SizedArb> {
+    let mut curve = ProfitCurve {
+        cycle,
+        registry,
+        token_mints,
+        points: BTreeMap::new(),
+    };
+
+    let mut lo = min_amount.min(max_amount);
+    let mut hi = max_amount;
+
+    let interior = |lo: u128, hi: u128| {
+        let step = ((hi - lo) as f64 * INV_PHI) as u128;
+        (hi - step, lo + step)
+    };
+
+    let (mut x1, mut x2) = interior(lo, hi);
+    let mut f1 = curve.profit(x1)?;
+    let mut f2 = curve.profit(x2)?;
+
+    for _ in 0..MAX_ITERATIONS {
+        if hi - lo <= 2 {
+            break;
+        }
+        if f1 < f2 {
+            // peak is right of x1
+            lo = x1;
+            x1 = x2;
+            f1 = f2;
+            x2 = interior(lo, hi).1;
+            f2 = curve.profit(x2)?;
+        } else {
+            // peak is left of x2
+            hi = x2;
+            x2 = x1;
+            f2 = f1;
+            x1 = interior(lo, hi).0;
+            f1 = curve.profit(x1)?;
+        }
+    }
+
+    // the edges are valid sizes too (eg, profit still rising at the balance)
+    curve.profit(min_amount.min(max_amount))?;
+    curve.profit(max_amount)?;
+
+    let (amount_in, (amount_out, profit)) = curve
+        .points
+        .iter()
+        .max_by_key(|(_, (_, profit))| *profit)
+        .map(|(amount_in, point)| (*amount_in, *point))
+        .unwrap();
+
+    Ok(SizedArb {
+        amount_in,
+        amount_out,
+        profit,
+        curve: curve
+            .points
+            .iter()
+            .map(|(amount_in, (_, profit))| (*amount_in, *profit))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{build_edges, find_candidate_cycles, MAX_HOPS};
+    use crate::tests::mock::{add_mock_pool, mock_mints};
+    use crate::utils::PoolGraph;
+
+    #[test]
+    fn finds_interior_optimum() {
+        let mints = mock_mints(3);
+        let mut graph = PoolGraph::new();
+        let depth = 1_000_000_000;
+        add_mock_pool(&mut graph, &mints, 0, 1, [depth, depth]);
+        add_mock_pool(&mut graph, &mints, 1, 2, [depth, depth]);
+        add_mock_pool(&mut graph, &mints, 2, 0, [depth, depth * 11 / 10]);
+
+        let registry = MintRegistry::default();
+        let edges = build_edges(&graph, &registry, &mints);
+        let cycle = &find_candidate_cycles(&edges, mints.len(), 0, MAX_HOPS)[0];
+
+        // balance is way past the point where impact eats the edge
+        let balance = depth;
+        let sized = optimal_size(cycle, &registry, &mints, 1, balance).unwrap();
+
+        assert!(sized.profit > 0);
+        assert!(sized.amount_in < balance);
+        // nothing on the quoted curve beats the chosen size
+        assert!(sized.curve.iter().all(|(_, profit)| *profit <= sized.profit));
+        // a little more or less is no better
+        for amount in [sized.amount_in * 9 / 10, sized.amount_in * 11 / 10] {
+            let profit = cycle.quote(&registry, &mints, amount).unwrap() as i128 - amount as i128;
+            assert!(profit <= sized.profit);
+        }
+    }
+}
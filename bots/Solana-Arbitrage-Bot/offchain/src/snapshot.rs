@@ -0,0 +1,216 @@
+//! Account snapshots for replaying a search
+//!
+//! a snapshot is every account the engine read (the pools' update accounts,
+//! the mints + the owner's token accounts) at one slot, saved as a directory:
+//!
+//! ```text
+//! <dir>/snapshot.json      slot, unix timestamp + the accounts which didnt exist
+//! <dir>/accounts/<pk>.json one per account, in `solana account --output json` format
+//! ```
+//!
+//! (so a snapshot's accounts dir also works as the svm's `accounts_dir`).
+//! replaying one pins the clock to the recorded time, so the same snapshot
+//! always gives the same search.
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_sdk::account::Account;
+
+use crate::refresh::AccountSource;
+use crate::utils::read_json_dir;
+
+const META_FILE: &str = "snapshot.json";
+const ACCOUNTS_DIR: &str = "accounts";
+
+// `solana account --output json` format
+#[derive(Deserialize, Serialize)]
+struct KeyedAccount {
+    pubkey: String,
+    account: UiAccount,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotMeta {
+    slot: u64,
+    unix_timestamp: i64,
+    missing: Vec<String>,
+}
+
+pub fn parse_account(json: &str) -> Result<(Pubkey, Account)> {
+    let keyed: KeyedAccount = serde_json::from_str(json)?;
+    let pubkey = Pubkey::from_str(&keyed.pubkey).map_err(|_| anyhow!("invalid pubkey: {}", keyed.pubkey))?;
+    let account = keyed
+        .account
+        .decode::<Account>()
+        .ok_or_else(|| anyhow!("cant decode account data of {}", pubkey))?;
+    Ok((pubkey, account))
+}
+
+pub fn account_json(pubkey: &Pubkey, account: &Account) -> Result<String> {
+    let keyed = KeyedAccount {
+        pubkey: pubkey.to_string(),
+        account: UiAccount::encode(pubkey, account, UiAccountEncoding::Base64, None, None),
+    };
+    Ok(serde_json::to_string_pretty(&keyed)?)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    pub slot: u64,
+    pub unix_timestamp: i64,
+    // None = the account didnt exist
+    pub accounts: BTreeMap<Pubkey, Option<Account>>,
+}
+
+impl Snapshot {
+    pub fn load(dir: &str) -> Result<Self> {
+        let meta_path = Path::new(dir).join(META_FILE);
+        let meta: SnapshotMeta = serde_json::from_str(
+            &std::fs::read_to_string(&meta_path).with_context(|| format!("reading {:?}", meta_path))?,
+        )
+        .with_context(|| format!("parsing {:?}", meta_path))?;
+
+        let mut accounts = BTreeMap::new();
+        for key in &meta.missing {
+            let pubkey = Pubkey::from_str(key).map_err(|_| anyhow!("invalid pubkey: {}", key))?;
+            accounts.insert(pubkey, None);
+        }
+        let accounts_dir = Path::new(dir).join(ACCOUNTS_DIR).to_string_lossy().to_string();
+        if Path::new(&accounts_dir).is_dir() {
+            for path in read_json_dir(&accounts_dir) {
+                let json = std::fs::read_to_string(&path).with_context(|| format!("reading {}", path))?;
+                let (pubkey, account) = parse_account(&json).with_context(|| format!("parsing {}", path))?;
+                accounts.insert(pubkey, Some(account));
+            }
+        }
+        Ok(Self { slot: meta.slot, unix_timestamp: meta.unix_timestamp, accounts })
+    }
+
+    pub fn save(&self, dir: &str) -> Result<()> {
+        let accounts_dir = Path::new(dir).join(ACCOUNTS_DIR);
+        std::fs::create_dir_all(&accounts_dir).with_context(|| format!("creating {:?}", accounts_dir))?;
+        let mut missing = vec![];
+        for (pubkey, account) in &self.accounts {
+            match account {
+                Some(account) => {
+                    let path = accounts_dir.join(format!("{}.json", pubkey));
+                    std::fs::write(&path, account_json(pubkey, account)?)
+                        .with_context(|| format!("writing {:?}", path))?;
+                }
+                None => missing.push(pubkey.to_string()),
+            }
+        }
+        let meta = SnapshotMeta { slot: self.slot, unix_timestamp: self.unix_timestamp, missing };
+        std::fs::write(Path::new(dir).join(META_FILE), serde_json::to_string_pretty(&meta)?)
+            .with_context(|| format!("writing the snapshot to {}", dir))
+    }
+}
+
+impl AccountSource for Snapshot {
+    // an account the recording never read is an error -- replaying it as
+    // missing would quietly change the search
+    fn get_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        pubkeys
+            .iter()
+            .map(|pk| {
+                self.accounts
+                    .get(pk)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("{} isnt in the snapshot", pk))
+            })
+            .collect()
+    }
+
+    fn get_accounts_at(&self, pubkeys: &[Pubkey]) -> Result<(Option<u64>, Vec<Option<Account>>)> {
+        Ok((Some(self.slot), self.get_accounts(pubkeys)?))
+    }
+}
+
+/// passes reads through to `source`, keeping the last value of every
+/// account it has returned
+pub struct Recorder<'a> {
+    source: &'a dyn AccountSource,
+    accounts: RefCell<BTreeMap<Pubkey, Option<Account>>>,
+    // slot of the last read (None = the source didnt say)
+    slot: Cell<Option<u64>>,
+}
+
+impl<'a> Recorder<'a> {
+    pub fn new(source: &'a dyn AccountSource) -> Self {
+        Self { source, accounts: RefCell::new(BTreeMap::new()), slot: Cell::new(None) }
+    }
+
+    /// the slot the last read was served at
+    pub fn slot(&self) -> Option<u64> {
+        self.slot.get()
+    }
+
+    /// everything read so far, as of `slot`
+    pub fn snapshot(&self, slot: u64, unix_timestamp: i64) -> Snapshot {
+        Snapshot { slot, unix_timestamp, accounts: self.accounts.borrow().clone() }
+    }
+}
+
+impl AccountSource for Recorder<'_> {
+    fn get_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        Ok(self.get_accounts_at(pubkeys)?.1)
+    }
+
+    fn get_accounts_at(&self, pubkeys: &[Pubkey]) -> Result<(Option<u64>, Vec<Option<Account>>)> {
+        let (slot, fetched) = self.source.get_accounts_at(pubkeys)?;
+        self.slot.set(slot);
+        let mut accounts = self.accounts.borrow_mut();
+        for (pk, account) in pubkeys.iter().zip(fetched.iter()) {
+            accounts.insert(*pk, account.clone());
+        }
+        Ok((slot, fetched))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_snapshots_round_trip() {
+        let existing = Pubkey::new_unique();
+        let missing = Pubkey::new_unique();
+        let account = Account {
+            lamports: 2_039_280,
+            data: vec![1, 2, 3],
+            owner: spl_token::id(),
+            executable: false,
+            rent_epoch: 361,
+        };
+        let live = Snapshot {
+            slot: 42,
+            unix_timestamp: 0,
+            accounts: BTreeMap::from([(existing, Some(account.clone())), (missing, None)]),
+        };
+
+        let recorder = Recorder::new(&live);
+        let fetched = recorder.get_accounts(&[existing, missing]).unwrap();
+        assert_eq!(fetched, vec![Some(account), None]);
+        assert_eq!(recorder.slot(), Some(42));
+        let snapshot = recorder.snapshot(42, 1_700_000_000);
+        assert_eq!(snapshot.accounts, live.accounts);
+
+        let dir = std::env::temp_dir().join(format!("snapshot-{}", Pubkey::new_unique()));
+        let dir = dir.to_str().unwrap();
+        snapshot.save(dir).unwrap();
+        let loaded = Snapshot::load(dir).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+        assert_eq!(loaded, snapshot);
+
+        // reads outside the recording fail instead of replaying as missing
+        assert!(loaded.get_accounts(&[Pubkey::new_unique()]).is_err());
+    }
+}
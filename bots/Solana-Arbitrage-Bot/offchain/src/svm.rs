@@ -0,0 +1,471 @@
+//! Offline arb simulation in an in-process bank (solana-program-test)
+//!
+//! programs are loaded from binaries dumped with `solana program dump`
+//! (`<program id>.so`) and accounts from `solana account --output json`
+//! files, so no validator or network is needed. each arb's exact ixs are run
+//! twice on a fresh bank: once as the single tx which would be sent (compute
+//! used + whether profit_or_revert passed), then step by step -- committing
+//! each hop -- to read what every hop really paid out next to its quote.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::{Keypair, Signer};
+use anyhow::{anyhow, Context, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use solana_program_test::{BanksClient, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::bpf_loader;
+use solana_sdk::clock::Clock;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::rent::Rent;
+use solana_sdk::transaction::{self, Transaction, TransactionError};
+use spl_token::native_mint;
+
+use crate::arb::ArbSteps;
+use crate::compute::{ComputeBudget, MAX_COMPUTE_UNITS};
+use crate::constants::TOKEN_PROGRAM_ID;
+use crate::refresh::AccountSource;
+use crate::serialize::token::unpack_token_account;
+use crate::snapshot::parse_account;
+use crate::utils::{derive_token_address_with_program, read_json_dir};
+
+// the owner pays fees + rent in the bank -- topped up to at least this
+const OWNER_LAMPORTS: u64 = 1_000 * 1_000_000_000;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct SvmConfig {
+    /// `<program id>.so` binaries of every program a route calls
+    pub programs_dir: String,
+    /// `solana account --output json` dumps of the pools + the owner's accounts
+    pub accounts_dir: String,
+}
+
+impl Default for SvmConfig {
+    fn default() -> Self {
+        Self {
+            programs_dir: "svm/programs".to_string(),
+            accounts_dir: "svm/accounts".to_string(),
+        }
+    }
+}
+
+/// accounts dumped to json files -- stands in for rpc when refreshing pools
+#[derive(Debug, Default, Clone)]
+pub struct AccountsDir {
+    pub accounts: HashMap<Pubkey, Account>,
+}
+
+impl AccountsDir {
+    pub fn load(dir: &str) -> Result<Self> {
+        if !Path::new(dir).is_dir() {
+            return Err(anyhow!("accounts dir {} doesnt exist", dir));
+        }
+        let mut accounts = HashMap::new();
+        for path in read_json_dir(&dir.to_string()) {
+            let json = std::fs::read_to_string(&path).with_context(|| format!("reading {}", path))?;
+            let (pubkey, account) = parse_account(&json).with_context(|| format!("parsing {}", path))?;
+            accounts.insert(pubkey, account);
+        }
+        Ok(Self { accounts })
+    }
+}
+
+impl AccountSource for AccountsDir {
+    fn get_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        Ok(pubkeys.iter().map(|pk| self.accounts.get(pk).cloned()).collect())
+    }
+}
+
+/// what one hop quoted vs what it paid out in the bank
+#[derive(Debug, Clone)]
+pub struct HopReport {
+    pub venue: String,
+    pub mint_out: Pubkey,
+    pub quoted_out: u128,
+    // None = the hop failed or wasnt reached
+    pub realized_out: Option<u128>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SimReport {
+    pub hops: Vec<HopReport>,
+    // of the whole route as one tx
+    pub units_consumed: Option<u64>,
+    // None = the route failed before profit_or_revert ran
+    pub profit_or_revert: Option<bool>,
+    pub error: Option<String>,
+    pub logs: Vec<String>,
+}
+
+impl fmt::Display for SimReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "offline simulation:")?;
+        for (i, hop) in self.hops.iter().enumerate() {
+            write!(f, "  hop {} {} -> {}: quoted {}", i, hop.venue, hop.mint_out, hop.quoted_out)?;
+            match (hop.realized_out, &hop.error) {
+                (Some(realized), _) => {
+                    let diff = if hop.quoted_out > 0 {
+                        (realized as f64 - hop.quoted_out as f64) / hop.quoted_out as f64 * 10_000.0
+                    } else {
+                        0.0
+                    };
+                    writeln!(f, ", realized {} ({:+.1} bps)", realized, diff)?
+                }
+                (None, Some(err)) => writeln!(f, ", failed: {}", err)?,
+                (None, None) => writeln!(f, ", not reached")?,
+            }
+        }
+        match self.units_consumed {
+            Some(units) => writeln!(f, "  compute units: {}", units)?,
+            None => writeln!(f, "  compute units: unknown")?,
+        }
+        let outcome = match self.profit_or_revert {
+            Some(true) => "passed",
+            Some(false) => "reverted",
+            None => "not reached",
+        };
+        write!(f, "  profit_or_revert: {}", outcome)?;
+        if let Some(err) = &self.error {
+            write!(f, " ({})", err)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Simulator {
+    // program id -> elf
+    programs: Vec<(Pubkey, Vec<u8>)>,
+    accounts: AccountsDir,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl Simulator {
+    pub fn load(config: &SvmConfig) -> Result<Self> {
+        let mut programs = vec![];
+        let entries = std::fs::read_dir(&config.programs_dir)
+            .with_context(|| format!("reading programs dir {}", config.programs_dir))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("so") {
+                continue;
+            }
+            let program_id = program_id(&path)?;
+            let elf = std::fs::read(&path).with_context(|| format!("reading {:?}", path))?;
+            programs.push((program_id, elf));
+        }
+        let accounts = AccountsDir::load(&config.accounts_dir)?;
+        info!(
+            "offline simulation with {} programs + {} accounts",
+            programs.len(),
+            accounts.accounts.len()
+        );
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        Ok(Self { programs, accounts, runtime })
+    }
+
+    pub fn accounts(&self) -> &AccountsDir {
+        &self.accounts
+    }
+
+    /// run the arb on a fresh bank holding the dumped state, its clock at
+    /// `unix_timestamp` (the time the arb's quotes were made at)
+    pub fn simulate(
+        &self,
+        owner: &Keypair,
+        steps: &ArbSteps,
+        mints: &[Pubkey],
+        venues: &[String],
+        unix_timestamp: i64,
+    ) -> Result<SimReport> {
+        self.runtime.block_on(self.run(owner, steps, mints, venues, unix_timestamp))
+    }
+
+    async fn start(&self, owner: &Pubkey, unix_timestamp: i64) -> Result<ProgramTestContext> {
+        let mut program_test = ProgramTest::default();
+        let rent = Rent::default();
+        for (program_id, elf) in &self.programs {
+            program_test.add_account(
+                *program_id,
+                Account {
+                    lamports: rent.minimum_balance(elf.len()),
+                    data: elf.clone(),
+                    owner: bpf_loader::id(),
+                    executable: true,
+                    rent_epoch: 0,
+                },
+            );
+        }
+        for (pubkey, account) in &self.accounts.accounts {
+            program_test.add_account(*pubkey, account.clone());
+        }
+        let mut owner_account = self.accounts.accounts.get(owner).cloned().unwrap_or_default();
+        owner_account.lamports = owner_account.lamports.max(OWNER_LAMPORTS);
+        program_test.add_account(*owner, owner_account);
+
+        let mut context = program_test.start_with_context().await;
+        // pools which quote off the time (eg, vesting / fee schedules) see the same clock
+        let mut clock: Clock = context.banks_client.get_sysvar().await?;
+        clock.unix_timestamp = unix_timestamp;
+        context.set_sysvar(&clock);
+        Ok(context)
+    }
+
+    async fn run(
+        &self,
+        owner: &Keypair,
+        steps: &ArbSteps,
+        mints: &[Pubkey],
+        venues: &[String],
+        unix_timestamp: i64,
+    ) -> Result<SimReport> {
+        let payer = owner.pubkey();
+        let mut context = self.start(&payer, unix_timestamp).await?;
+        let blockhash = context.last_blockhash;
+        // the steps' fees come out of the bank's own payer, so the owner's
+        // lamports only move with what the hops pay
+        let fee_payer = &context.payer;
+        let client = &mut context.banks_client;
+
+        // the route as it would be sent
+        let ixs = with_budget(steps.instructions());
+        let end_idx = ixs.len() - steps.end.len();
+        let tx = Transaction::new_signed_with_payer(&ixs, Some(&payer), &[owner], blockhash);
+        let simulation = client.simulate_transaction(tx).await?;
+        let result = simulation.result.ok_or_else(|| anyhow!("the bank didnt run the route"))?;
+        let details = simulation.simulation_details;
+        let mut report = SimReport {
+            hops: vec![],
+            units_consumed: details.as_ref().map(|details| details.units_consumed),
+            profit_or_revert: profit_or_revert_outcome(&result, end_idx),
+            error: result.err().map(|err| err.to_string()),
+            logs: details.map(|details| details.logs).unwrap_or_default(),
+        };
+
+        // then one committed step at a time, reading each hop's output
+        let mut failed = send(client, fee_payer, owner, &steps.start, blockhash)
+            .await
+            .err()
+            .map(|err| format!("start_swap: {}", err));
+        for (i, hop) in steps.hops.iter().enumerate() {
+            let mint_out = mints[i + 1];
+            let mut hop_report = HopReport {
+                venue: venues[i].clone(),
+                mint_out,
+                quoted_out: steps.quoted[i],
+                realized_out: None,
+                error: None,
+            };
+            if failed.is_none() {
+                let before = hop_balance(client, &payer, &mint_out).await?;
+                match send(client, fee_payer, owner, hop, blockhash).await {
+                    Ok(()) => {
+                        let after = hop_balance(client, &payer, &mint_out).await?;
+                        hop_report.realized_out = Some(after.saturating_sub(before) as u128);
+                    }
+                    Err(err) => {
+                        hop_report.error = Some(err.to_string());
+                        failed = Some(format!("hop {}: {}", i, err));
+                    }
+                }
+            }
+            report.hops.push(hop_report);
+        }
+        if let (Some(err), None) = (failed, &report.error) {
+            report.error = Some(err);
+        }
+        Ok(report)
+    }
+}
+
+// the most compute a tx can use -- the report measures it
+fn with_budget(mut ixs: Vec<Instruction>) -> Vec<Instruction> {
+    ComputeBudget { units: MAX_COMPUTE_UNITS, unit_price: 0 }.apply(&mut ixs);
+    ixs
+}
+
+async fn send(
+    client: &mut BanksClient,
+    fee_payer: &Keypair,
+    owner: &Keypair,
+    ixs: &[Instruction],
+    blockhash: Hash,
+) -> Result<()> {
+    let tx = Transaction::new_signed_with_payer(
+        &with_budget(ixs.to_vec()),
+        Some(&fee_payer.pubkey()),
+        &[fee_payer, owner],
+        blockhash,
+    );
+    client.process_transaction(tx).await?;
+    Ok(())
+}
+
+// what the owner holds of a hop's output -- sol can be paid out native (eg,
+// pump.fun sells), so wsol counts the owner's lamports too. the ata is under
+// the mint's owner (token-2022 mints arent legacy atas)
+async fn hop_balance(client: &mut BanksClient, owner: &Pubkey, mint: &Pubkey) -> Result<u64> {
+    let token_program = client.get_account(*mint).await?.map_or(*TOKEN_PROGRAM_ID, |account| account.owner);
+    let balance = token_balance(client, &derive_token_address_with_program(owner, mint, &token_program)).await?;
+    if *mint != native_mint::id() {
+        return Ok(balance);
+    }
+    Ok(balance + client.get_balance(*owner).await?)
+}
+
+async fn token_balance(client: &mut BanksClient, address: &Pubkey) -> Result<u64> {
+    Ok(client
+        .get_account(*address)
+        .await?
+        .map(|account| unpack_token_account(&account.data).amount)
+        .unwrap_or(0))
+}
+
+// `<program id>.so`
+fn program_id(path: &Path) -> Result<Pubkey> {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| anyhow!("bad program path {:?}", path))?;
+    Pubkey::from_str(stem).map_err(|_| anyhow!("program binary {:?} isnt named by its program id", path))
+}
+
+/// Some(passed) once profit_or_revert (the ixs from `end_idx`) ran
+fn profit_or_revert_outcome(result: &transaction::Result<()>, end_idx: usize) -> Option<bool> {
+    match result {
+        Ok(()) => Some(true),
+        Err(TransactionError::InstructionError(idx, _)) if *idx as usize >= end_idx => Some(false),
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::account_json;
+    use solana_sdk::instruction::InstructionError;
+    use solana_sdk::program_pack::Pack;
+    use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+    #[test]
+    fn parses_cli_account_dumps() {
+        let json = r#"{
+            "pubkey": "11111111111111111111111111111112",
+            "account": {
+                "lamports": 2039280,
+                "data": ["AQID", "base64"],
+                "owner": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+                "executable": false,
+                "rentEpoch": 361,
+                "space": 3
+            }
+        }"#;
+        let (pubkey, account) = parse_account(json).unwrap();
+        assert_eq!(pubkey, Pubkey::from_str("11111111111111111111111111111112").unwrap());
+        assert_eq!(account.data, vec![1, 2, 3]);
+        assert_eq!(account.lamports, 2039280);
+        assert_eq!(account.owner, spl_token::id());
+
+        let dir = AccountsDir { accounts: HashMap::from([(pubkey, account.clone())]) };
+        let fetched = dir.get_accounts(&[pubkey, Pubkey::new_unique()]).unwrap();
+        assert_eq!(fetched, vec![Some(account), None]);
+    }
+
+    #[test]
+    fn profit_or_revert_outcomes() {
+        let failed_at = |idx| Err(TransactionError::InstructionError(idx, InstructionError::Custom(0)));
+        assert_eq!(profit_or_revert_outcome(&Ok(()), 4), Some(true));
+        assert_eq!(profit_or_revert_outcome(&failed_at(4), 4), Some(false));
+        // a hop failed first
+        assert_eq!(profit_or_revert_outcome(&failed_at(2), 4), None);
+        assert_eq!(profit_or_revert_outcome(&Err(TransactionError::AccountNotFound), 4), None);
+    }
+
+    #[test]
+    fn runs_token_hops_in_the_bank() {
+        let owner = Keypair::new();
+        let owner_key = owner.pubkey();
+        let [mint_a, mint_b, vault_a, vault_b] = [0; 4].map(|_| Pubkey::new_unique());
+        let [ata_a, ata_b] =
+            [mint_a, mint_b].map(|mint| derive_token_address_with_program(&owner_key, &mint, &spl_token::id()));
+        let rent = Rent::default();
+        let packed = |len: usize, pack: &dyn Fn(&mut [u8])| {
+            let mut data = vec![0; len];
+            pack(&mut data);
+            Account {
+                lamports: rent.minimum_balance(len),
+                data,
+                owner: spl_token::id(),
+                executable: false,
+                rent_epoch: 0,
+            }
+        };
+        let mint = || {
+            let mint = Mint { decimals: 6, is_initialized: true, ..Mint::default() };
+            packed(Mint::LEN, &|data: &mut [u8]| mint.pack_into_slice(data))
+        };
+        // the "pool" vaults are the owner's too, so the hops are plain transfers
+        let token_account = |mint: Pubkey, amount: u64| {
+            let account = TokenAccount {
+                mint,
+                owner: owner_key,
+                amount,
+                state: AccountState::Initialized,
+                ..TokenAccount::default()
+            };
+            packed(TokenAccount::LEN, &|data: &mut [u8]| account.pack_into_slice(data))
+        };
+        let accounts = [
+            (mint_a, mint()),
+            (mint_b, mint()),
+            (ata_a, token_account(mint_a, 100)),
+            (ata_b, token_account(mint_b, 0)),
+            (vault_a, token_account(mint_a, 1_000)),
+            (vault_b, token_account(mint_b, 1_000)),
+        ];
+
+        let dir = std::env::temp_dir().join(format!("svm-{}", std::process::id()));
+        let config = SvmConfig {
+            programs_dir: dir.join("programs").to_string_lossy().to_string(),
+            accounts_dir: dir.join("accounts").to_string_lossy().to_string(),
+        };
+        std::fs::create_dir_all(&config.programs_dir).unwrap();
+        std::fs::create_dir_all(&config.accounts_dir).unwrap();
+        for (pubkey, account) in &accounts {
+            let path = Path::new(&config.accounts_dir).join(format!("{}.json", pubkey));
+            std::fs::write(path, account_json(pubkey, account).unwrap()).unwrap();
+        }
+        let simulator = Simulator::load(&config);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let simulator = simulator.unwrap();
+
+        let transfer = |src: &Pubkey, dst: &Pubkey, amount: u64| {
+            spl_token::instruction::transfer(&spl_token::id(), src, dst, &owner_key, &[], amount).unwrap()
+        };
+        let steps = ArbSteps {
+            start: vec![],
+            hops: vec![
+                vec![transfer(&ata_a, &vault_a, 100), transfer(&vault_b, &ata_b, 90)],
+                vec![transfer(&ata_b, &vault_b, 90), transfer(&vault_a, &ata_a, 105)],
+            ],
+            quoted: vec![90, 104],
+            end: vec![],
+        };
+        let venues = vec!["mock".to_string(); 2];
+        let mints = [mint_a, mint_b, mint_a];
+        let report = simulator.simulate(&owner, &steps, &mints, &venues, 1_700_000_000).unwrap();
+
+        assert_eq!(report.error, None);
+        assert_eq!(report.profit_or_revert, Some(true));
+        let realized: Vec<Option<u128>> = report.hops.iter().map(|hop| hop.realized_out).collect();
+        assert_eq!(realized, vec![Some(90), Some(105)]);
+        assert!(report.units_consumed.unwrap() > 0);
+    }
+}
@@ -0,0 +1,214 @@
+//! Recorded on-chain swaps to check the quotes against
+//!
+//! a fixture is a snapshot of a pool's accounts as of the slot before one of
+//! its swaps landed, plus what that swap moved through the pool's vaults:
+//!
+//! ```text
+//! <dir>/swap.json the venue, the pool json + the swap's vault deltas
+//! <dir>/...       the snapshot (see `snapshot`)
+//! ```
+//!
+//! quoting the recorded input against the snapshot has to give exactly the
+//! recorded output. `record_swap_fixture` writes them -- only exact input
+//! swaps which were the first change to the pool after the snapshot's slot.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_sdk::bs58;
+
+use crate::pool::{pool_factory, PoolType};
+use crate::refresh::PoolRefresher;
+use crate::snapshot::Snapshot;
+use crate::utils::{anchor_discriminator, PoolQuote};
+
+const SWAP_FILE: &str = "swap.json";
+// swap / swap_v2 { amount, other_amount_threshold, sqrt_price_limit, exact_in, .. }
+// (the same on raydium clmm + whirlpools)
+const EXACT_IN_FLAG: usize = 8 + 8 + 8 + 16;
+
+lazy_static! {
+    static ref SWAP_DISCRIMINATORS: [[u8; 8]; 2] = [
+        anchor_discriminator("global", "swap"),
+        anchor_discriminator("global", "swap_v2"),
+    ];
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SwapFixture {
+    pub venue: PoolType,
+    pub pool: Value,
+    pub signature: String,
+    pub mint_in: String,
+    pub mint_out: String,
+    // into / out of the pool's vaults (so after any token-2022 fees)
+    pub amount_in: u64,
+    pub amount_out: u64,
+}
+
+impl SwapFixture {
+    pub fn load(dir: &str) -> Result<Self> {
+        let path = Path::new(dir).join(SWAP_FILE);
+        let json = std::fs::read_to_string(&path).with_context(|| format!("reading {:?}", path))?;
+        serde_json::from_str(&json).with_context(|| format!("parsing {:?}", path))
+    }
+
+    pub fn save(&self, dir: &str) -> Result<()> {
+        let path = Path::new(dir).join(SWAP_FILE);
+        std::fs::write(&path, serde_json::to_string_pretty(self)?).with_context(|| format!("writing {:?}", path))
+    }
+
+    /// the pool, loaded from the snapshot's accounts
+    pub fn pool(&self, snapshot: &Snapshot) -> Result<PoolQuote> {
+        let pool = pool_factory(&self.venue, &self.pool.to_string())?;
+        let mut refresher = PoolRefresher::new(vec![PoolQuote::new(pool)], Cluster::Mainnet);
+        refresher.refresh(snapshot)?;
+        Ok(refresher.pools[0].clone())
+    }
+
+    /// the pool's quote for the recorded input
+    pub fn quote(&self, snapshot: &Snapshot) -> Result<u128> {
+        let mint_in = parse_pubkey(&self.mint_in)?;
+        let mint_out = parse_pubkey(&self.mint_out)?;
+        let pool = self.pool(snapshot)?;
+        let amount_out = pool.snapshot().get_quote_with_amounts_scaled(self.amount_in as u128, &mint_in, &mint_out)?;
+        Ok(amount_out)
+    }
+}
+
+fn parse_pubkey(key: &str) -> Result<Pubkey> {
+    Pubkey::from_str(key).map_err(|_| anyhow!("invalid pubkey: {}", key))
+}
+
+/// a pool's swap in a landed tx (a `getTransaction` result, `jsonParsed`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedSwap {
+    pub mint_in: String,
+    pub mint_out: String,
+    pub amount_in: u64,
+    pub amount_out: u64,
+}
+
+/// the tx's swap through the pool -- None unless it's a single exact input
+/// swap (any other ix of the program on the pool would mix into the vault
+/// deltas)
+pub fn recorded_swap(tx: &Value, program_id: &Pubkey, pool: &Pubkey, vaults: &[Pubkey; 2]) -> Option<RecordedSwap> {
+    if !tx["meta"]["err"].is_null() {
+        return None;
+    }
+    let (program_id, pool) = (program_id.to_string(), pool.to_string());
+    let outer = tx["transaction"]["message"]["instructions"].as_array().into_iter().flatten();
+    let inner = tx["meta"]["innerInstructions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|ixs| ixs["instructions"].as_array().into_iter().flatten());
+    let pool_ixs: Vec<Vec<u8>> = outer
+        .chain(inner)
+        .filter(|ix| ix["programId"] == program_id.as_str())
+        .filter(|ix| ix["accounts"].as_array().map_or(false, |accounts| accounts.iter().any(|a| a == pool.as_str())))
+        .map(|ix| ix["data"].as_str().and_then(|data| bs58::decode(data).into_vec().ok()).unwrap_or_default())
+        .collect();
+    let data = match pool_ixs.as_slice() {
+        [data] => data,
+        _ => return None,
+    };
+    let is_swap = data.len() > EXACT_IN_FLAG && SWAP_DISCRIMINATORS.iter().any(|disc| data[..8] == disc[..]);
+    if !is_swap || data[EXACT_IN_FLAG] != 1 {
+        return None;
+    }
+
+    // vault -> (mint, post - pre)
+    let keys: Vec<&str> = tx["transaction"]["message"]["accountKeys"]
+        .as_array()?
+        .iter()
+        .map(|key| key["pubkey"].as_str().unwrap_or_default())
+        .collect();
+    let mut deltas: HashMap<&str, (String, i128)> = HashMap::new();
+    for (key, sign) in [("preTokenBalances", -1), ("postTokenBalances", 1)] {
+        for balance in tx["meta"][key].as_array().into_iter().flatten() {
+            let account = match balance["accountIndex"].as_u64().and_then(|idx| keys.get(idx as usize)) {
+                Some(account) => *account,
+                None => continue,
+            };
+            let amount = balance["uiTokenAmount"]["amount"].as_str()?.parse::<i128>().ok()?;
+            let mint = balance["mint"].as_str()?.to_string();
+            deltas.entry(account).or_insert((mint, 0)).1 += sign * amount;
+        }
+    }
+    let delta = |vault: &Pubkey| deltas.get(vault.to_string().as_str()).cloned();
+    let ((mint_in, amount_in), (mint_out, amount_out)) = match (delta(&vaults[0])?, delta(&vaults[1])?) {
+        (a, b) if a.1 > 0 && b.1 < 0 => (a, b),
+        (a, b) if b.1 > 0 && a.1 < 0 => (b, a),
+        _ => return None,
+    };
+    Some(RecordedSwap {
+        mint_in,
+        mint_out,
+        amount_in: u64::try_from(amount_in).ok()?,
+        amount_out: u64::try_from(-amount_out).ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn swap_data(exact_in: bool) -> String {
+        let mut data = SWAP_DISCRIMINATORS[1].to_vec();
+        data.extend_from_slice(&[0; EXACT_IN_FLAG - 8]);
+        data.push(exact_in as u8);
+        data.push(1);
+        bs58::encode(data).into_string()
+    }
+
+    fn tx(program: &Pubkey, pool: &Pubkey, vaults: &[Pubkey; 2], datas: &[String]) -> Value {
+        let [mint_a, mint_b] = [Pubkey::new_unique(), Pubkey::new_unique()].map(|pk| pk.to_string());
+        let balance = |idx: usize, mint: &str, amount: u64| {
+            json!({"accountIndex": idx, "mint": mint, "uiTokenAmount": {"amount": amount.to_string()}})
+        };
+        json!({
+            "transaction": {"message": {
+                "accountKeys": [
+                    {"pubkey": Pubkey::new_unique().to_string()},
+                    {"pubkey": vaults[0].to_string()},
+                    {"pubkey": vaults[1].to_string()},
+                ],
+                "instructions": [],
+            }},
+            "meta": {
+                "err": null,
+                // routed through an aggregator
+                "innerInstructions": [{"index": 0, "instructions": datas.iter().map(|data| json!({
+                    "programId": program.to_string(),
+                    "accounts": [pool.to_string()],
+                    "data": data,
+                })).collect::<Vec<_>>()}],
+                "preTokenBalances": [balance(1, &mint_a, 1_000), balance(2, &mint_b, 5_000)],
+                "postTokenBalances": [balance(1, &mint_a, 1_100), balance(2, &mint_b, 4_521)],
+            },
+        })
+    }
+
+    #[test]
+    fn only_single_exact_in_swaps_are_recorded() {
+        let [program, pool] = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let vaults = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        let swap = recorded_swap(&tx(&program, &pool, &vaults, &[swap_data(true)]), &program, &pool, &vaults).unwrap();
+        assert_eq!((swap.amount_in, swap.amount_out), (100, 479));
+        assert_ne!(swap.mint_in, swap.mint_out);
+
+        assert!(recorded_swap(&tx(&program, &pool, &vaults, &[swap_data(false)]), &program, &pool, &vaults).is_none());
+        let two_swaps = tx(&program, &pool, &vaults, &[swap_data(true), swap_data(true)]);
+        assert!(recorded_swap(&two_swaps, &program, &pool, &vaults).is_none());
+    }
+}
@@ -0,0 +1,121 @@
+//! constant product pool for search/sizing tests (no rpc needed)
+
+use std::collections::HashMap;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::{Cluster, Program};
+use solana_sdk::account::Account;
+use solana_sdk::instruction::Instruction;
+
+use crate::error::{PoolError, PoolResult};
+use crate::pool::PoolOperations;
+use crate::serialize::token::try_unpack_token_amount;
+use crate::utils::{PoolEdge, PoolGraph, PoolIndex, PoolQuote};
+
+#[derive(Debug, Clone)]
+pub struct MockPool {
+    pub mints: [Pubkey; 2],
+    pub vaults: [Pubkey; 2],
+    pub amounts: [u128; 2],
+}
+
+impl PoolOperations for MockPool {
+    fn get_name(&self) -> String {
+        "Mock".to_string()
+    }
+
+    // vaults are unique per mock pool
+    fn get_own_addr(&self) -> Pubkey {
+        self.vaults[0]
+    }
+
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        self.vaults.to_vec()
+    }
+
+    fn set_update_accounts(
+        &mut self,
+        accounts: Vec<Option<Account>>,
+        _cluster: Cluster,
+    ) -> PoolResult<()> {
+        for (i, account) in accounts.iter().enumerate() {
+            self.amounts[i] = try_unpack_token_amount(&self.vaults[i], account)?;
+        }
+        Ok(())
+    }
+
+    fn mint_2_addr(&self, _mint: &Pubkey) -> Pubkey {
+        Pubkey::default()
+    }
+
+    fn get_mints(&self) -> Vec<Pubkey> {
+        self.mints.to_vec()
+    }
+
+    fn mint_2_scale(&self, _mint: &Pubkey) -> u64 {
+        6
+    }
+
+    fn get_quote_with_amounts_scaled(
+        &self,
+        amount_in: u128,
+        mint_in: &Pubkey,
+        _mint_out: &Pubkey,
+    ) -> PoolResult<u128> {
+        let (src, dst) = if *mint_in == self.mints[0] {
+            (self.amounts[0], self.amounts[1])
+        } else if *mint_in == self.mints[1] {
+            (self.amounts[1], self.amounts[0])
+        } else {
+            return Err(PoolError::UnknownMint(*mint_in));
+        };
+        Ok(dst * amount_in / (src + amount_in))
+    }
+
+    fn swap_ix(
+        &self,
+        _program: &Program,
+        _owner: &Pubkey,
+        _mint_in: &Pubkey,
+        _mint_out: &Pubkey,
+        _amount_in: u64,
+        _min_amount_out: u64,
+    ) -> PoolResult<Vec<Instruction>> {
+        Ok(vec![])
+    }
+
+    fn can_trade(&self, _mint_in: &Pubkey, _mint_out: &Pubkey) -> bool {
+        self.amounts.iter().all(|amount| *amount > 0)
+    }
+}
+
+pub fn mock_mints(n: usize) -> Vec<Pubkey> {
+    (0..n).map(|_| Pubkey::new_unique()).collect()
+}
+
+/// adds a mints[i] <-> mints[j] pool in both directions
+pub fn add_mock_pool(
+    graph: &mut PoolGraph,
+    mints: &[Pubkey],
+    i: usize,
+    j: usize,
+    amounts: [u128; 2],
+) -> PoolQuote {
+    let pool: Box<dyn PoolOperations> = Box::new(MockPool {
+        mints: [mints[i], mints[j]],
+        vaults: [Pubkey::new_unique(), Pubkey::new_unique()],
+        amounts,
+    });
+    let quote = PoolQuote::new(pool);
+    for (a, b) in [(i, j), (j, i)] {
+        graph
+            .0
+            .entry(PoolIndex(a))
+            .or_insert_with(|| PoolEdge(HashMap::new()))
+            .0
+            .entry(PoolIndex(b))
+            .or_insert_with(Vec::new)
+            .push(quote.clone());
+    }
+    quote
+}
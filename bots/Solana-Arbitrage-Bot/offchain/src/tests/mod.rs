@@ -1 +1,6 @@
-pub mod quotes;
\ No newline at end of file
+#[cfg(test)]
+pub mod mock;
+#[cfg(test)]
+mod quotes;
+#[cfg(test)]
+mod swap_fixtures;
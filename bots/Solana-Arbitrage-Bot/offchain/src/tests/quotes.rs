@@ -9,7 +9,6 @@ use solana_sdk::transaction::Transaction;
 use spl_token::instruction::mint_to;
 
 use std::rc::Rc;
-use std::vec;
 
 use tmp::accounts as tmp_accounts;
 use tmp::instruction as tmp_ix;
@@ -20,6 +19,7 @@ use crate::constants::*;
 
 
 #[test]
+#[ignore = "needs the localnet mainnet fork (../mainnet/start_localnet.sh)"]
 fn serum() {
     let pool_dir = "../pools/serum/".to_string();
     let pool_tipe = PoolType::SerumPoolType; 
@@ -27,6 +27,7 @@ fn serum() {
 }
 
 #[test]
+#[ignore = "needs the localnet mainnet fork (../mainnet/start_localnet.sh)"]
 fn aldrin() {
     let pool_dir = "../pools/aldrin/".to_string();
     let pool_tipe = PoolType::AldrinPoolType; 
@@ -34,6 +35,7 @@ fn aldrin() {
 }
 
 #[test]
+#[ignore = "needs the localnet mainnet fork (../mainnet/start_localnet.sh)"]
 fn saber() {
     let pool_dir = "../pools/saber/".to_string();
     let pool_tipe = PoolType::SaberPoolType; 
@@ -41,6 +43,7 @@ fn saber() {
 }
 
 #[test]
+#[ignore = "needs the localnet mainnet fork (../mainnet/start_localnet.sh)"]
 fn mercurial() {
     let pool_dir = "../pools/mercurial/".to_string();
     let pool_tipe = PoolType::MercurialPoolType; 
@@ -48,6 +51,7 @@ fn mercurial() {
 }
 
 #[test]
+#[ignore = "needs the localnet mainnet fork (../mainnet/start_localnet.sh)"]
 fn orca() {
     let pool_dir = "../pools/orca/".to_string();
     let pool_tipe = PoolType::OrcaPoolType; 
@@ -67,7 +71,7 @@ fn test_all_pool_quotes(
 
     let owner_kp_path = "../mainnet-fork/localnet_owner.key";   
     // setup anchor things 
-    let owner = read_keypair_file(owner_kp_path.clone()).unwrap();
+    let owner = read_keypair_file(owner_kp_path).unwrap();
     println!("owner: {}", owner.pubkey());
 
     let provider = Client::new_with_options(
@@ -76,7 +80,7 @@ fn test_all_pool_quotes(
         CommitmentConfig::confirmed() 
     );
     let program = provider.program(*ARB_PROGRAM_ID);
-    let owner = read_keypair_file(owner_kp_path.clone()).unwrap();     
+    let owner = read_keypair_file(owner_kp_path).unwrap();
 
     let pool_paths = read_json_dir(&pool_dir);
     let mut err_count = 0; 
@@ -86,7 +90,7 @@ fn test_all_pool_quotes(
     for pool_path in pool_paths {        
 
         let contents = std::fs::read_to_string(&pool_path).unwrap();
-        let mut pool = pool_factory(&pool_tipe, &contents);
+        let mut pool = pool_factory(&pool_tipe, &contents).unwrap();
 
         // println!("{}", pool_path);
         let err_flag = test_pool_quote(
@@ -113,7 +117,7 @@ fn test_pool_quote(
     let accounts = connection
             .get_multiple_accounts(&update_accounts)
             .unwrap();
-    pool.set_update_accounts(accounts, Cluster::Localnet);
+    pool.set_update_accounts(accounts, Cluster::Localnet).unwrap();
 
     // get a quote 
     let pool_mints = pool.get_mints(); 
@@ -130,13 +134,12 @@ fn test_pool_quote(
         return 1; 
     }
     
-    let mut amount_in; 
-    if src_scale >= 2  {
+    let mut amount_in = if src_scale >= 2  {
         // scale -2 bc sometimes saber pool amounts are too small for full 1 swap
-        amount_in = 10_u128.pow((src_scale-2) as u32);
+        10_u128.pow((src_scale-2) as u32)
     } else { 
-        amount_in = 10_u128.pow(src_scale as u32);
-    }
+        10_u128.pow(src_scale as u32)
+    };
 
     // println!("---");
     // println!("{:#?}", pool);
@@ -151,7 +154,7 @@ fn test_pool_quote(
             amount_in, 
             mint_in, 
             mint_out
-        );
+        ).unwrap();
         // println!("quote: {}", quote_out_amount);
 
         if quote_out_amount == 0 {
@@ -175,7 +178,7 @@ fn test_pool_quote(
     if amount_in > src_balance {
         // println!("src balance: {} amount in {}", src_balance / 10_u128.pow(src_scale as u32), amount_in / 10_u128.pow(src_scale as u32));
 
-        if mint_in.to_string() != "So11111111111111111111111111111111111111112" {
+        if *mint_in != spl_token::native_mint::id() {
             let mint_ix = mint_to(
                 &TOKEN_PROGRAM_ID, 
                 mint_in, 
@@ -233,8 +236,10 @@ fn test_pool_quote(
         program, 
         &owner.pubkey(), 
         mint_in, 
-        mint_out
-    );
+        mint_out,
+        amount_in as u64,
+        0,
+    ).unwrap();
     ixs.push(swap_ix);
     
     let ixs = ixs.concat();
@@ -258,7 +263,6 @@ fn test_pool_quote(
                     println!("quote: {amount_in} -> {quote_out_amount}");
                     println!("logs: {:#?}", s.value.logs);
                     panic!("ahhh");
-                    1
                 }, 
                 None => {
                     // parse logs for last output amount 
@@ -292,7 +296,6 @@ fn test_pool_quote(
             println!("pool path: {}", pool_path);
             println!("quote: {} -> {}", amount_in, quote_out_amount);
             panic!("ahhh");
-            1
         }
     }
 
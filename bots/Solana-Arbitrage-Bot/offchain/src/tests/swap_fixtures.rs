@@ -0,0 +1,38 @@
+use std::path::Path;
+
+use crate::pool::PoolType;
+use crate::snapshot::Snapshot;
+use crate::swap_fixture::SwapFixture;
+
+// one dir per recorded swap (see record_swap_fixture)
+const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/fixtures/swaps");
+
+#[test]
+fn clmm_quotes_match_recorded_swaps() {
+    let mut dirs: Vec<String> = std::fs::read_dir(FIXTURES_DIR)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .map(|path| path.to_str().unwrap().to_string())
+        .collect();
+    dirs.sort();
+
+    // an empty dir would check nothing -- every clmm venue needs a swap
+    let mut venues = vec![];
+    for dir in &dirs {
+        let fixture = SwapFixture::load(dir).unwrap();
+        venues.push(fixture.venue.clone());
+        let snapshot = Snapshot::load(dir).unwrap();
+        let quoted = fixture.quote(&snapshot).unwrap();
+        assert_eq!(
+            quoted,
+            fixture.amount_out as u128,
+            "{} ({:?})",
+            fixture.signature,
+            Path::new(dir).file_name().unwrap()
+        );
+    }
+    for venue in [PoolType::RaydiumClmmPoolType, PoolType::OrcaWhirlpoolPoolType] {
+        assert!(venues.contains(&venue), "no recorded {:?} swap in {}", venue, FIXTURES_DIR);
+    }
+}
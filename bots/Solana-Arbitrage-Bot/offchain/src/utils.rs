@@ -1,10 +1,18 @@
 use crate::constants::*;
+use crate::error::{PoolError, PoolResult};
+use crate::mints::MintRegistry;
 use crate::pool::PoolOperations;
 use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use solana_sdk::account::Account;
 use std::collections::HashMap;
 use std::fs;
-use std::rc::Rc;
 use std::str::FromStr;
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn read_json_dir(dir: &String) -> Vec<String> {
     let _paths = fs::read_dir(dir).unwrap();
@@ -29,24 +37,125 @@ pub fn str2pubkey(s: &str) -> Pubkey {
     Pubkey::from_str(s).unwrap()
 }
 
+// legacy spl token mints (see `MintRegistry::token_address` for any mint)
 pub fn derive_token_address(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    derive_token_address_with_program(owner, mint, &TOKEN_PROGRAM_ID)
+}
+
+// ata for mints owned by another token program (eg, token-2022)
+pub fn derive_token_address_with_program(
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Pubkey {
     let (pda, _) = Pubkey::find_program_address(
-        &[
-            &owner.to_bytes(),
-            &TOKEN_PROGRAM_ID.to_bytes(),
-            &mint.to_bytes(),
-        ],
+        &[&owner.to_bytes(), &token_program.to_bytes(), &mint.to_bytes()],
         &ASSOCIATED_TOKEN_PROGRAM_ID,
     );
     pda
 }
 
+// replays pin the clock to the snapshot's time (0 = not pinned)
+static PINNED_UNIX_TIME: AtomicI64 = AtomicI64::new(0);
+
+pub fn pin_unix_time(unix_timestamp: Option<i64>) {
+    PINNED_UNIX_TIME.store(unix_timestamp.unwrap_or(0), Ordering::Relaxed);
+}
+
+// pools whose fees / reserves move with time (eg, meteora) quote against the wall clock
+pub fn unix_now() -> i64 {
+    let pinned = PINNED_UNIX_TIME.load(Ordering::Relaxed);
+    if pinned != 0 {
+        return pinned;
+    }
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// sha256("{namespace}:{name}")[..8] -- eg, ("global", "swap") for an
+/// instruction or ("account", "PoolState") for an account
+pub fn anchor_discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("{}:{}", namespace, name).as_bytes());
+    let mut discriminator = [0; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+// shared between the graph + the refresher: searches read an immutable
+// snapshot of the pool, updates swap in a new snapshot without blocking them
 #[derive(Debug, Clone)]
-pub struct PoolQuote(pub Rc<Box<dyn PoolOperations>>);
+pub struct PoolQuote {
+    pub pool: Arc<RwLock<Arc<dyn PoolOperations>>>,
+    // set when the pool errors (bad json, stale/missing account, ...) -- the
+    // search skips it until an update succeeds again
+    pub failed: Arc<AtomicBool>,
+}
 
 impl PoolQuote {
-    pub fn new(quote: Rc<Box<dyn PoolOperations>>) -> Self {
-        Self(quote)
+    pub fn new(pool: Box<dyn PoolOperations>) -> Self {
+        Self {
+            pool: Arc::new(RwLock::new(Arc::from(pool))),
+            failed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn snapshot(&self) -> Arc<dyn PoolOperations> {
+        self.pool.read().unwrap().clone()
+    }
+
+    /// applies the accounts to a copy of the pool; on error the old snapshot
+    /// is kept and the pool is flagged
+    pub fn update(&self, accounts: Vec<Option<Account>>, cluster: Cluster) -> PoolResult<()> {
+        let mut next = dyn_clone::clone_box(&*self.snapshot());
+        if let Err(err) = next.set_update_accounts(accounts, cluster) {
+            self.disable(&err);
+            return Err(err);
+        }
+        *self.pool.write().unwrap() = Arc::from(next);
+        self.failed.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// the pool's quote net of token-2022 transfer fees: the pool gets
+    /// amount_in less mint_in's fee, we get its output less mint_out's
+    pub fn quote(
+        &self,
+        registry: &MintRegistry,
+        amount_in: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> PoolResult<u128> {
+        registry.check_tradeable(mint_in)?;
+        registry.check_tradeable(mint_out)?;
+        let amount_in = registry.after_fee(mint_in, amount_in);
+        let amount_out = self.snapshot().get_quote_with_amounts_scaled(amount_in, mint_in, mint_out)?;
+        Ok(registry.after_fee(mint_out, amount_out))
+    }
+
+    /// skips the pool until its next successful update -- size / route
+    /// errors (eg, an oversized probe overflowing) dont flag it
+    pub fn flag(&self, err: &PoolError) {
+        if !err.is_pool_state() {
+            debug!("{}: {}", self.snapshot().get_name(), err);
+            return;
+        }
+        self.disable(err);
+    }
+
+    fn disable(&self, err: &PoolError) {
+        if !self.failed.swap(true, Ordering::Relaxed) {
+            warn!("skipping pool {}: {}", self.snapshot().get_name(), err);
+        }
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    pub fn ptr_eq(&self, other: &PoolQuote) -> bool {
+        Arc::ptr_eq(&self.pool, &other.pool)
     }
 }
 